@@ -1,8 +1,11 @@
 use circular_buffer::CircularBuffer;
-use scamble::dsp::decode::decode_into;
-use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::decode::{Decoded, LoadState, Loader};
+use scamble::dsp::interop;
+use scamble::dsp::layout::Upmix;
+use scamble::dsp::resample::{Interpolation, Resampler};
+use scamble::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
 use scamble::dsp::{Dsp, DspType, Parameter, ParameterType, ProcessResult};
-use scamble::{bool_param, enum_param, float_param, int_param};
+use scamble::{bool_param, enum_param, float_param, int_param, loader_param};
 
 #[derive(Copy, Clone)]
 enum VoiceMode {
@@ -11,38 +14,46 @@ enum VoiceMode {
     Overtake,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 struct TrailingNote {
     pos: usize,
     end: usize,
     fadeout_samples: usize,
+    // converts from the sample's rate and the playback speed to the mixer's rate
+    resampler: Resampler,
 }
 
 pub struct CassettePlayer {
     // user-specified parameters
-    samples: Vec<f32>, // downsample to mono for now
+    samples: Loader,
     num_notes: usize,
     _async: bool,
     start_offset_percent: f32,
     end_offset_percent: f32,
     voices: u8,
     voice_mode: VoiceMode,
+    speed: f32,
     // game-state parameters
     note_frac: f32,
     // state
     trailing_notes: CircularBuffer<8, TrailingNote>,
     prev_note_frac: f32,
     async_note_tick: usize,
+    note_buf: SignalBuf,
 }
 
 impl CassettePlayer {
+    fn frames(&self) -> usize {
+        self.samples.current().map_or(0, Decoded::frames)
+    }
+
     fn start_idx(&self) -> usize {
-        let samples_f = self.samples.len() as f32;
+        let samples_f = self.frames() as f32;
         (samples_f * self.start_offset_percent).clamp(0., samples_f) as usize
     }
 
     fn end_idx(&self) -> usize {
-        let samples_f = self.samples.len() as f32;
+        let samples_f = self.frames() as f32;
         (samples_f * self.end_offset_percent).clamp(0., samples_f) as usize
     }
 
@@ -62,6 +73,7 @@ impl CassettePlayer {
             pos: start,
             end,
             fadeout_samples: 0,
+            resampler: Resampler::new(Interpolation::default(), 1.),
         }
     }
 }
@@ -81,18 +93,8 @@ impl Dsp for CassettePlayer {
 
     fn parameters() -> Vec<Parameter<Self>> {
         vec![
-            Parameter {
-                ty: ParameterType::Data {
-                    setter: |data, dsp| {
-                        dsp.samples.clear();
-                        decode_into(data, &mut dsp.samples);
-                    },
-                    getter: |_| None,
-                },
-                name: "samples",
-                unit: "",
-                desc: "",
-            },
+            // decoded in the background, as sample files can be large
+            Parameter::new("samples", loader_param!(samples)),
             Parameter::new("num_notes", int_param!(num_notes: usize, range: 1..4096, default: 256)),
             Parameter::new("async", bool_param!(_async, default: false)),
             Parameter::with_unit("start_offset", "%", float_param!(start_offset_percent, range: 0.0..100.0, default: 0.)),
@@ -102,29 +104,33 @@ impl Dsp for CassettePlayer {
                 "voice_mode",
                 enum_param!(voice_mode: VoiceMode, options: [Combine, Average, Overtake], default: Combine),
             ),
+            Parameter::with_unit("speed", "x", float_param!(speed, range: 0.25..4.0, default: 1.)),
             Parameter::new("note", float_param!(note_frac, range: 0.0..1.0, default: 0.)),
         ]
     }
 
     fn create() -> Self {
         CassettePlayer {
-            samples: vec![],
+            samples: Loader::new(),
             num_notes: 0,
             _async: false,
             start_offset_percent: 0.0,
             end_offset_percent: 100.0,
             voices: 4,
             voice_mode: VoiceMode::Combine,
+            speed: 1.,
             note_frac: 0.,
             trailing_notes: Default::default(),
             prev_note_frac: 2., // make sure the initial beat always triggers
             async_note_tick: 0,
+            note_buf: SignalBuf::for_block(),
         }
     }
 
     fn reset(&mut self) {
         self.trailing_notes.clear();
         self.prev_note_frac = 2.;
+        self.note_buf.reserve_block();
     }
 
     fn should_process(&mut self, _: bool, _: usize) -> ProcessResult {
@@ -140,6 +146,14 @@ impl Dsp for CassettePlayer {
         // TODO: should scamble do ^ automatically?
         output.samples_mut().fill(0.);
 
+        // swap in newly decoded samples, which playing notes don't belong to
+        if self.samples.update() {
+            self.trailing_notes.clear();
+            if let LoadState::Failed(error) = self.samples.state() {
+                interop::log_error(&format!("Couldn't decode samples: {error}"));
+            }
+        }
+
         // trigger new notes when parameter changes
         if self.note_frac != self.prev_note_frac {
             if self._async {
@@ -163,21 +177,30 @@ impl Dsp for CassettePlayer {
         if self.trailing_notes.len() > 0 {
             // hold onto output channel layout
             let out_len = output.length();
-            let out_chan = output.channels();
+            let out_layout = output.layout();
+            // play at the mixer's rate, sped up or slowed down
+            let mixer_rate = interop::sample_rate().unwrap_or(48000);
+            let (samples, channels, sample_rate) = match self.samples.current() {
+                Some(decoded) => (decoded.signal.samples(), decoded.channels(), decoded.sample_rate),
+                None => (&[][..], 0, mixer_rate),
+            };
+            let ratio = sample_rate as f64 / mixer_rate as f64 * self.speed as f64;
+            let frames = samples.len() / channels.max(1);
+            self.note_buf.resize(out_len, channels);
             // reversed indexed loop to allow removal in loop
             for nidx in (0..self.trailing_notes.len()).rev() {
                 let note = &mut self.trailing_notes[nidx];
-                // number of samples to actually write
-                let note_len = note.end.saturating_sub(note.pos).min(out_len);
-                for i in 0..note_len {
-                    // use `output` as a buffer; directly read samples since we know it's always
-                    // mono (currently)
-                    let old = output.samples_mut()[i * out_chan];
-                    output.write_sample(i, old + self.samples[i + note.pos]);
+                note.resampler.set_ratio(ratio);
+                let source = &samples[note.pos.min(frames) * channels..note.end.min(frames) * channels];
+                let (read, written) = note.resampler.process(SignalConst::new(source, channels), self.note_buf.as_signal_mut());
+                // mix the sample's channels into the output's, using `output` as a buffer
+                let mixed = self.note_buf.mix_to(out_layout, Upmix::default());
+                for (out, x) in output.samples_mut().iter_mut().zip(mixed).take(written * out_layout.channels()) {
+                    *out += x;
                 }
                 // move note forward, remove empty notes
-                note.pos += note_len;
-                if note.end.saturating_sub(note.pos) == 0 {
+                note.pos += read;
+                if note.pos >= note.end.min(frames) {
                     self.trailing_notes.remove(nidx);
                 }
             }
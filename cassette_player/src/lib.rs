@@ -1,5 +1,4 @@
 #![feature(macro_metavar_expr)]
-#![feature(macro_metavar_expr_concat)]
 
 use crate::cassette_player::CassettePlayer;
 
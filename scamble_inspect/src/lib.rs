@@ -0,0 +1,459 @@
+//! Reads the DSPs exported by a built plugin, without loading it into FMOD.
+//!
+//! A plugin exports either `FMODGetDSPDescription` for a single DSP (see `scamble::expose_dsp`) or
+//! `FMODGetPluginDescriptionList` for several (see `scamble::expose_dsp_list`). [load] calls
+//! whichever is exported, and copies out each [Description] so the library can be unloaded.
+//!
+//! ```
+//! use scamble::dsp::interop::into_desc;
+//! use scamble::dsp::signal::{SignalConst, SignalMut};
+//! use scamble::dsp::{Dsp, DspType};
+//! use scamble_inspect::Description;
+//!
+//! struct Silence;
+//!
+//! impl Dsp for Silence {
+//!     fn name() -> &'static str { "Silence" }
+//!     fn version() -> u32 { 3 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Silence }
+//!     fn read(&mut self, _: SignalConst, _: SignalMut) {}
+//! }
+//!
+//! let description = unsafe { Description::read(&into_desc::<Silence>()) };
+//! assert_eq!(description.name, "Silence");
+//! assert_eq!(description.version, 3);
+//! assert!(description.parameters.is_empty());
+//! ```
+
+use libloading::{Library, Symbol};
+use scamble::dsp::typed::Value;
+use scamble::raw_bindings::FMOD_DSP_PARAMETER_DATA_TYPE::*;
+use scamble::raw_bindings::FMOD_DSP_PARAMETER_TYPE::*;
+use scamble::raw_bindings::FMOD_PLUGINTYPE::{FMOD_PLUGINTYPE_DSP, FMOD_PLUGINTYPE_MAX};
+use scamble::raw_bindings::*;
+use std::error::Error;
+use std::ffi::{CStr, OsStr, c_char};
+use std::fmt::{Display, Formatter};
+use std::{ptr, slice};
+
+#[cfg(windows)]
+type GetDescription = unsafe extern "stdcall" fn() -> *const FMOD_DSP_DESCRIPTION;
+#[cfg(not(windows))]
+type GetDescription = unsafe extern "C" fn() -> *const FMOD_DSP_DESCRIPTION;
+
+#[cfg(windows)]
+type GetDescriptionList = unsafe extern "stdcall" fn() -> *const FMOD_PLUGINLIST;
+#[cfg(not(windows))]
+type GetDescriptionList = unsafe extern "C" fn() -> *const FMOD_PLUGINLIST;
+
+/// A reason that a plugin couldn't be inspected.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The library couldn't be loaded.
+    Library(libloading::Error),
+    /// The library doesn't export either of FMOD's plugin entry points.
+    NoEntryPoint,
+    /// The entry point returned a null description.
+    NullDescription,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Library(error) => write!(f, "couldn't load library: {error}"),
+            LoadError::NoEntryPoint => write!(f, "doesn't export FMODGetDSPDescription or FMODGetPluginDescriptionList"),
+            LoadError::NullDescription => write!(f, "returned a null description"),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Library(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a plugin library and reads the descriptions of the DSPs it exports, in order. Plugins of
+/// other types in a description list are skipped.
+///
+/// Loading a library runs its initialisation code, so only trusted plugins should be inspected.
+pub fn load(path: impl AsRef<OsStr>) -> Result<Vec<Description>, LoadError> {
+    let library = unsafe { Library::new(path) }.map_err(LoadError::Library)?;
+    unsafe {
+        if let Ok(get) = library.get::<GetDescriptionList>(b"FMODGetPluginDescriptionList") {
+            read_list(get)
+        } else if let Ok(get) = library.get::<GetDescription>(b"FMODGetDSPDescription") {
+            read_single(get)
+        } else {
+            Err(LoadError::NoEntryPoint)
+        }
+    }
+}
+
+unsafe fn read_single(get: Symbol<GetDescription>) -> Result<Vec<Description>, LoadError> {
+    let desc = unsafe { get() };
+    if desc.is_null() {
+        return Err(LoadError::NullDescription);
+    }
+    Ok(vec![unsafe { Description::read(&*desc) }])
+}
+
+unsafe fn read_list(get: Symbol<GetDescriptionList>) -> Result<Vec<Description>, LoadError> {
+    let mut entry = unsafe { get() };
+    if entry.is_null() {
+        return Err(LoadError::NullDescription);
+    }
+    let mut descriptions = vec![];
+    loop {
+        // read as an integer, since an unknown plugin type isn't a valid enum value
+        let ty = unsafe { ptr::addr_of!((*entry).type_).cast::<u32>().read() };
+        if ty == FMOD_PLUGINTYPE_MAX as u32 {
+            return Ok(descriptions);
+        }
+        let desc = unsafe { (*entry).description } as *const FMOD_DSP_DESCRIPTION;
+        if ty == FMOD_PLUGINTYPE_DSP as u32 && !desc.is_null() {
+            descriptions.push(unsafe { Description::read(&*desc) });
+        }
+        entry = unsafe { entry.add(1) };
+    }
+}
+
+/// A DSP's description, as given to FMOD.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Description {
+    /// The name shown in FMOD Studio.
+    pub name: String,
+    /// The plugin's version.
+    pub version: u32,
+    /// The number of input buffers.
+    pub inputs: i32,
+    /// The number of output buffers.
+    pub outputs: i32,
+    /// The names of the callbacks that are set, as named in `FMOD_DSP_DESCRIPTION`.
+    pub callbacks: Vec<&'static str>,
+    /// The DSP's parameters, in order.
+    pub parameters: Vec<ParameterDescription>,
+}
+
+/// A parameter's description, as given to FMOD.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterDescription {
+    /// The parameter's name.
+    pub name: String,
+    /// The unit shown next to the parameter's value.
+    pub unit: String,
+    /// The parameter's description.
+    pub desc: String,
+    /// The type, range and default of the parameter.
+    pub ty: ParameterKind,
+}
+
+/// The type of a [ParameterDescription], along with its range and default.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterKind {
+    /// A float parameter.
+    Float {
+        /// The minimum value.
+        min: f32,
+        /// The maximum value.
+        max: f32,
+        /// The default value.
+        default: f32,
+    },
+    /// An integer parameter, which may have a name for each value.
+    Int {
+        /// The minimum value.
+        min: i32,
+        /// The maximum value.
+        max: i32,
+        /// The default value.
+        default: i32,
+        /// Whether the maximum value is shown as infinity.
+        max_is_inf: bool,
+        /// The name of each value, from the minimum up.
+        names: Option<Vec<String>>,
+    },
+    /// A boolean parameter.
+    Bool {
+        /// The default value.
+        default: bool,
+        /// The names of the false and true values.
+        names: Option<(String, String)>,
+    },
+    /// A data parameter.
+    Data {
+        /// The FMOD data type, which is `0` for user data.
+        ty: i32,
+    },
+    /// A type that FMOD doesn't define.
+    Unknown(u32),
+}
+
+impl ParameterKind {
+    /// Gets the name of the parameter type.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ParameterKind::Float { .. } => "float",
+            ParameterKind::Int { .. } => "int",
+            ParameterKind::Bool { .. } => "bool",
+            ParameterKind::Data { .. } => "data",
+            ParameterKind::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl Description {
+    /// Copies out a DSP description.
+    ///
+    /// # Safety
+    /// The description must be valid, as it would have to be for FMOD to load it.
+    pub unsafe fn read(desc: &FMOD_DSP_DESCRIPTION) -> Self {
+        let callbacks = [
+            ("create", desc.create.is_some()),
+            ("release", desc.release.is_some()),
+            ("reset", desc.reset.is_some()),
+            ("read", desc.read.is_some()),
+            ("process", desc.process.is_some()),
+            ("setposition", desc.setposition.is_some()),
+            ("setparameterfloat", desc.setparameterfloat.is_some()),
+            ("setparameterint", desc.setparameterint.is_some()),
+            ("setparameterbool", desc.setparameterbool.is_some()),
+            ("setparameterdata", desc.setparameterdata.is_some()),
+            ("getparameterfloat", desc.getparameterfloat.is_some()),
+            ("getparameterint", desc.getparameterint.is_some()),
+            ("getparameterbool", desc.getparameterbool.is_some()),
+            ("getparameterdata", desc.getparameterdata.is_some()),
+            ("shouldiprocess", desc.shouldiprocess.is_some()),
+            ("sys_register", desc.sys_register.is_some()),
+            ("sys_deregister", desc.sys_deregister.is_some()),
+            ("sys_mix", desc.sys_mix.is_some()),
+        ];
+        let parameters = if desc.paramdesc.is_null() || desc.numparameters <= 0 {
+            &[][..]
+        } else {
+            unsafe { slice::from_raw_parts(desc.paramdesc, desc.numparameters as usize) }
+        };
+        Description {
+            name: fixed_str(&desc.name),
+            version: desc.version,
+            inputs: desc.numinputbuffers,
+            outputs: desc.numoutputbuffers,
+            callbacks: callbacks.into_iter().filter(|(_, set)| *set).map(|(name, _)| name).collect(),
+            parameters: parameters.iter().map(|it| unsafe { ParameterDescription::read(&**it) }).collect(),
+        }
+    }
+
+    /// Converts the description to a [Value], such as to be written as JSON.
+    pub fn to_value(&self) -> Value {
+        Value::Map(vec![
+            ("name".into(), Value::String(self.name.clone())),
+            ("version".into(), Value::Int(self.version.into())),
+            ("inputs".into(), Value::Int(self.inputs.into())),
+            ("outputs".into(), Value::Int(self.outputs.into())),
+            (
+                "callbacks".into(),
+                Value::List(self.callbacks.iter().map(|it| Value::String(it.to_string())).collect()),
+            ),
+            (
+                "parameters".into(),
+                Value::List(self.parameters.iter().map(ParameterDescription::to_value).collect()),
+            ),
+        ])
+    }
+}
+
+impl ParameterDescription {
+    /// Copies out a parameter description.
+    ///
+    /// # Safety
+    /// The description must be valid, as it would have to be for FMOD to load it.
+    pub unsafe fn read(desc: &FMOD_DSP_PARAMETER_DESC) -> Self {
+        // read as an integer, since an unknown parameter type isn't a valid enum value
+        let ty = unsafe { ptr::addr_of!(desc.type_).cast::<u32>().read() };
+        let ty = unsafe {
+            match ty {
+                ty if ty == FMOD_DSP_PARAMETER_TYPE_FLOAT as u32 => {
+                    let float = desc.__bindgen_anon_1.floatdesc;
+                    ParameterKind::Float {
+                        min: float.min,
+                        max: float.max,
+                        default: float.defaultval,
+                    }
+                }
+                ty if ty == FMOD_DSP_PARAMETER_TYPE_INT as u32 => {
+                    let int = desc.__bindgen_anon_1.intdesc;
+                    let count = (int.max as i64 - int.min as i64 + 1).max(0) as usize;
+                    ParameterKind::Int {
+                        min: int.min,
+                        max: int.max,
+                        default: int.defaultval,
+                        max_is_inf: int.goestoinf != 0,
+                        names: names(int.valuenames, count),
+                    }
+                }
+                ty if ty == FMOD_DSP_PARAMETER_TYPE_BOOL as u32 => {
+                    let bool = desc.__bindgen_anon_1.booldesc;
+                    ParameterKind::Bool {
+                        default: bool.defaultval != 0,
+                        names: names(bool.valuenames, 2).map(|it| (it[0].clone(), it[1].clone())),
+                    }
+                }
+                ty if ty == FMOD_DSP_PARAMETER_TYPE_DATA as u32 => ParameterKind::Data {
+                    ty: desc.__bindgen_anon_1.datadesc.datatype,
+                },
+                ty => ParameterKind::Unknown(ty),
+            }
+        };
+        ParameterDescription {
+            name: fixed_str(&desc.name),
+            unit: fixed_str(&desc.label),
+            desc: unsafe { c_str(desc.description) }.unwrap_or_default(),
+            ty,
+        }
+    }
+
+    /// Converts the description to a [Value], such as to be written as JSON.
+    pub fn to_value(&self) -> Value {
+        let mut fields = vec![
+            ("name".into(), Value::String(self.name.clone())),
+            ("unit".into(), Value::String(self.unit.clone())),
+            ("desc".into(), Value::String(self.desc.clone())),
+            ("type".into(), Value::String(self.ty.type_name().into())),
+        ];
+        let strings = |names: &[&String]| Value::List(names.iter().map(|it| Value::String(it.to_string())).collect());
+        match &self.ty {
+            ParameterKind::Float { min, max, default } => {
+                fields.extend([("min".into(), float(*min)), ("max".into(), float(*max)), ("default".into(), float(*default))])
+            }
+            ParameterKind::Int {
+                min,
+                max,
+                default,
+                max_is_inf,
+                names,
+            } => {
+                fields.extend([
+                    ("min".into(), Value::Int((*min).into())),
+                    ("max".into(), Value::Int((*max).into())),
+                    ("default".into(), Value::Int((*default).into())),
+                    ("max_is_inf".into(), Value::Bool(*max_is_inf)),
+                ]);
+                if let Some(names) = names {
+                    fields.push(("names".into(), strings(&names.iter().collect::<Vec<_>>())));
+                }
+            }
+            ParameterKind::Bool { default, names } => {
+                fields.push(("default".into(), Value::Bool(*default)));
+                if let Some((off, on)) = names {
+                    fields.push(("names".into(), strings(&[off, on])));
+                }
+            }
+            ParameterKind::Data { ty } => {
+                fields.push(("data_type".into(), Value::Int((*ty).into())));
+                if let Some(name) = data_type_name(*ty) {
+                    fields.push(("data_type_name".into(), Value::String(name.into())));
+                }
+            }
+            ParameterKind::Unknown(ty) => fields.push(("raw_type".into(), Value::Int((*ty).into()))),
+        }
+        Value::Map(fields)
+    }
+}
+
+impl Display for Description {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} (version {})", self.name, self.version)?;
+        writeln!(f, "  buffers: {} in, {} out", self.inputs, self.outputs)?;
+        writeln!(f, "  callbacks: {}", self.callbacks.join(", "))?;
+        if self.parameters.is_empty() {
+            return writeln!(f, "  parameters: none");
+        }
+        writeln!(f, "  parameters:")?;
+        for (i, param) in self.parameters.iter().enumerate() {
+            writeln!(f, "    {i}: {param}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ParameterDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.ty.type_name())?;
+        let unit = if self.unit.trim().is_empty() { String::new() } else { format!(" {}", self.unit.trim()) };
+        match &self.ty {
+            ParameterKind::Float { min, max, default } => write!(f, ", {min} to {max}{unit}, default {default}{unit}")?,
+            ParameterKind::Int {
+                min,
+                max,
+                default,
+                max_is_inf,
+                names,
+            } => {
+                let max = if *max_is_inf { "inf".to_string() } else { max.to_string() };
+                write!(f, ", {min} to {max}{unit}, default {default}{unit}")?;
+                if let Some(names) = names {
+                    write!(f, ", names [{}]", names.join(", "))?;
+                }
+            }
+            ParameterKind::Bool { default, names } => {
+                write!(f, ", default {default}")?;
+                if let Some((off, on)) = names {
+                    write!(f, ", names [{off}, {on}]")?;
+                }
+            }
+            ParameterKind::Data { ty } => match data_type_name(*ty) {
+                Some(name) => write!(f, ", {name}")?,
+                None => write!(f, ", type {ty}")?,
+            },
+            ParameterKind::Unknown(ty) => write!(f, ", raw type {ty}")?,
+        }
+        if !self.desc.is_empty() {
+            write!(f, "\n       {}", self.desc)?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the name of one of FMOD's data parameter types, such as `"user"` for `0`.
+pub fn data_type_name(ty: i32) -> Option<&'static str> {
+    [
+        (FMOD_DSP_PARAMETER_DATA_TYPE_USER, "user"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_OVERALLGAIN, "overall gain"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_3DATTRIBUTES, "3D attributes"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_SIDECHAIN, "sidechain"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_FFT, "FFT"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_3DATTRIBUTES_MULTI, "3D attributes (multiple listeners)"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_ATTENUATION_RANGE, "attenuation range"),
+        (FMOD_DSP_PARAMETER_DATA_TYPE_DYNAMIC_RESPONSE, "dynamic response"),
+    ]
+    .into_iter()
+    .find(|(it, _)| *it as i32 == ty)
+    .map(|(_, name)| name)
+}
+
+// converts through the shortest decimal form, so that e.g. 0.1 isn't written as 0.10000000149011612
+fn float(value: f32) -> Value {
+    Value::Float(value.to_string().parse().unwrap_or(value.into()))
+}
+
+// reads a string that's cut at the first NUL, or the end of the array if it's missing
+fn fixed_str(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars.iter().map(|it| *it as u8).take_while(|it| *it != 0).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+unsafe fn c_str(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+}
+
+unsafe fn names(ptr: *const *const c_char, count: usize) -> Option<Vec<String>> {
+    if ptr.is_null() {
+        return None;
+    }
+    let names = unsafe { slice::from_raw_parts(ptr, count) };
+    Some(names.iter().map(|it| unsafe { c_str(*it) }.unwrap_or_default()).collect())
+}
@@ -0,0 +1,59 @@
+//! Prints the DSPs exported by built plugins.
+//!
+//! Usage: `scamble-inspect [--json] <plugin>...`
+
+use scamble::dsp::typed::{Codec, Json, Value};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: scamble-inspect [--json] <plugin>...";
+
+fn main() -> ExitCode {
+    let mut json = false;
+    let mut paths = vec![];
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown option {flag}\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    let mut plugins = vec![];
+    for path in &paths {
+        match scamble_inspect::load(path) {
+            Ok(descriptions) if json => plugins.push(Value::Map(vec![
+                ("path".into(), Value::String(path.clone())),
+                ("dsps".into(), Value::List(descriptions.iter().map(|it| it.to_value()).collect())),
+            ])),
+            Ok(descriptions) => {
+                println!("{path}: {} DSP{}", descriptions.len(), if descriptions.len() == 1 { "" } else { "s" });
+                for description in descriptions {
+                    print!("\n{description}");
+                }
+            }
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+            }
+        }
+    }
+    if json {
+        let mut out = vec![];
+        Json::encode(&Value::List(plugins), &mut out);
+        println!("{}", String::from_utf8_lossy(&out));
+    }
+
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -0,0 +1,157 @@
+#![feature(macro_metavar_expr)]
+
+use scamble::dsp::interop::into_desc;
+use scamble::dsp::mix::WetDry;
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::typed::{Codec, Json, Value};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+use scamble::{bool_param, enum_param, float_param, int_param};
+use scamble_inspect::{Description, LoadError, ParameterKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Copy, Clone)]
+enum Shape {
+    Sine,
+    Square,
+}
+
+struct Synth {
+    pitch: f32,
+    voices: i32,
+    shape: Shape,
+    legato: bool,
+}
+
+impl Dsp for Synth {
+    fn name() -> &'static str {
+        "Synth"
+    }
+
+    fn version() -> u32 {
+        4
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter::with_unit("pitch", "st", float_param!(pitch, range: -12.0..12.0, default: 0.1)),
+            Parameter::new("voices", int_param!(voices, range: 1..8, default: 4)),
+            Parameter::new("shape", enum_param!(shape: Shape, options: [Sine, Square], default: Square)),
+            Parameter::new("legato", bool_param!(legato, default: true)),
+            Parameter::new(
+                "table",
+                ParameterType::Data {
+                    setter: |_, _| {},
+                    getter: |_| None,
+                },
+            ),
+        ]
+    }
+
+    fn create() -> Self {
+        Synth {
+            pitch: 0.1,
+            voices: 4,
+            shape: Shape::Square,
+            legato: true,
+        }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {}
+}
+
+#[test]
+fn descriptions_are_read() {
+    let description = unsafe { Description::read(&into_desc::<WetDry<Synth>>()) };
+    assert_eq!((description.name.as_str(), description.version), ("Synth", 4));
+    assert_eq!((description.inputs, description.outputs), (0, 1));
+    assert!(description.callbacks.contains(&"process"));
+    assert!(!description.callbacks.contains(&"sys_mix"));
+
+    let params: Vec<_> = description.parameters.iter().map(|it| (it.name.as_str(), it.unit.as_str(), &it.ty)).collect();
+    assert_eq!(
+        params[0],
+        (
+            "pitch",
+            "st",
+            &ParameterKind::Float {
+                min: -12.,
+                max: 12.,
+                default: 0.1
+            }
+        )
+    );
+    assert_eq!(
+        params[2].2,
+        &ParameterKind::Int {
+            min: 0,
+            max: 1,
+            default: 1,
+            max_is_inf: false,
+            names: Some(vec!["Sine".into(), "Square".into()]),
+        }
+    );
+    assert_eq!(params[3].2, &ParameterKind::Bool { default: true, names: None });
+    assert_eq!(params[4].2, &ParameterKind::Data { ty: 0 });
+    // the wrapper's own parameters follow
+    assert_eq!(params[5].0, "mix");
+    assert_eq!(description.parameters[6].desc, "Whether to pass the signal through unprocessed");
+}
+
+#[test]
+fn descriptions_are_printed() {
+    let description = unsafe { Description::read(&into_desc::<Synth>()) };
+    let text = description.to_string();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[0], "Synth (version 4)");
+    assert_eq!(lines[1], "  buffers: 0 in, 1 out");
+    assert_eq!(lines[4], "    0: pitch (float), -12 to 12 st, default 0.1 st");
+    assert_eq!(lines[6], "    2: shape (int), 0 to 1, default 1, names [Sine, Square]");
+    assert_eq!(lines[8], "    4: table (data), user");
+
+    let mut json = vec![];
+    Json::encode(&description.to_value(), &mut json);
+    let value = Json::decode(&json).unwrap();
+    let Some(Value::List(params)) = value.get("parameters") else {
+        panic!("{value:?}")
+    };
+    assert_eq!(params[0].get("default"), Some(&Value::Float(0.1)));
+    assert_eq!(
+        params[2].get("names"),
+        Some(&Value::List(vec![Value::String("Sine".into()), Value::String("Square".into())]))
+    );
+    assert_eq!(params[4].get("data_type_name"), Some(&Value::String("user".into())));
+}
+
+#[test]
+fn missing_libraries_are_reported() {
+    let error = scamble_inspect::load("/nonexistent/scamble/plugin.so").unwrap_err();
+    assert!(matches!(error, LoadError::Library(_)), "{error:?}");
+}
+
+#[test]
+#[ignore = "builds another crate of the workspace, which is slow"]
+fn built_plugins_are_loaded() {
+    // builds the example plugin in this workspace, which is usually up to date already
+    let status = Command::new(env!("CARGO")).args(["build", "--quiet", "-p", "tcp_effects"]).status().unwrap();
+    assert!(status.success());
+    let target = std::env::var_os("CARGO_TARGET_DIR").map_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../target"), PathBuf::from);
+    let path = target.join("debug").join(libloading::library_filename("tcp_effects"));
+
+    // whichever DSPs it exports, each should be described fully
+    let descriptions = scamble_inspect::load(&path).unwrap();
+    assert!(!descriptions.is_empty());
+    for description in &descriptions {
+        assert!(!description.name.is_empty());
+        assert!(description.callbacks.contains(&"process"), "{:?}", description.callbacks);
+        assert!(description.parameters.iter().all(|it| !it.name.is_empty()), "{:?}", description.parameters);
+    }
+}
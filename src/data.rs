@@ -1,7 +1,7 @@
 //! Data types used as DSP parameters.
 
 /// A 3D vector. Part of DSP parameter types.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -9,7 +9,7 @@ pub struct Vec3 {
 }
 
 /// A perspective in 3D. Part of DSP parameter types.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct _3DAttributes {
     pub position: Vec3,
     pub velocity: Vec3,
@@ -18,14 +18,14 @@ pub struct _3DAttributes {
 }
 
 /// Multi-channel spectra, for use with [dsp::ParameterType::Fft].
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct FftData {
     /// Per-channel spectra, as a vector of per-channel data.
     pub data: Vec<Vec<f32>>
 }
 
 /// Dynamic response, for use with [dsp::ParameterType::DynamicResponse].
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct DynamicResponseData {
     /// Per-channel average (RMS) gain factor.
     pub rms: Vec<f32>
@@ -33,7 +33,7 @@ pub struct DynamicResponseData {
 
 /// Overall gain, for use with [dsp::ParameterType::OverallGain]. FMOD reads this parameter type
 /// to determine when to virtualize voices.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct OverallGainData {
     /// Overall linear gain applied to the signal.
     pub linear_gain: f32,
@@ -43,7 +43,7 @@ pub struct OverallGainData {
 
 /// Listener attributes, for use with [dsp::ParameterType::ListenerAttributes]. FMOD sets this
 /// parameter type based on the listener's and event's locations automatically.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct ListenerAttributesData {
     /// Relative position of the sound to the listener.
     pub relative: _3DAttributes,
@@ -53,7 +53,7 @@ pub struct ListenerAttributesData {
 
 /// Listener attributes when multiple listeners exist, for use with [dsp::ParameterType::ListenerAttributesList].
 /// FMOD sets this parameter type based on the listeners' and event's locations automatically.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct ListenerAttributesListData {
     /// The number of listeners, equal to the length of the `relative` and `weights` fields.
     pub count: usize,
@@ -67,7 +67,7 @@ pub struct ListenerAttributesListData {
 
 /// Attenuation range of an event, for use with [dsp::ParameterType::AttenuationRange]. FMOD Studio
 /// sets and updates this parameter type based on the event's minimum and maximum distance.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct AttenuationRangeData {
     /// Minimum distance for attenuation.
     pub min: f32,
@@ -0,0 +1,443 @@
+//! Combinators for building one DSP out of several, in series or in parallel.
+//!
+//! ```
+//! use scamble::dsp::compose::{Chain, Parallel};
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//! use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+//! use scamble::float_param;
+//!
+//! struct Gain {
+//!     gain: f32
+//! }
+//!
+//! impl Dsp for Gain {
+//!     fn name() -> &'static str { "Gain" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Gain { gain: 1. } }
+//!
+//!     fn parameters() -> Vec<Parameter<Self>> {
+//!         vec![Parameter::new("gain", float_param!(gain, range: 0.0..2.0, default: 1.0))]
+//!     }
+//!
+//!     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+//!         for (out, x) in output.samples_mut().iter_mut().zip(input.samples()) {
+//!             *out = x * self.gain;
+//!         }
+//!     }
+//! }
+//!
+//! type Stack = Chain<Gain, Parallel<Gain, Gain>>;
+//! let names: Vec<_> = Stack::parameters().iter().map(|it| it.name).collect();
+//! assert_eq!(names, ["1.gain", "2.1.gain", "2.2.gain"]);
+//!
+//! let mut host = OfflineHost::<Stack>::new(48000, 4);
+//! host.set_float(0, 0.5);
+//! host.set_float(2, 2.);
+//! let rendered = host.render(SignalConst::new(&[1.; 4], 1), 4);
+//! assert_eq!(rendered.samples, [1.5; 4]);
+//! ```
+
+use crate::dsp::layout::Upmix;
+use crate::dsp::migrate::{self, Migration};
+use crate::dsp::signal::{DelayLine, Signal, SignalBuf, SignalConst, SignalMut};
+use crate::dsp::{Dsp, DspError, DspType, Parameter, ParameterVisitor, ProcessResult};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Runs the output of one DSP into another.
+///
+/// Parameters of `A` come first, prefixed with `1.`, followed by those of `B` prefixed with `2.`.
+/// FMOD limits parameter names to 15 characters, which leaves 13 for a name within one combination.
+/// Longer names are cut short and end with a hash of the full name, so that they stay distinct. Any
+/// sidechain input is passed to both DSPs.
+///
+/// The name of the chain joins those of `A` and `B`, and is shortened in the same way if it's longer
+/// than the 31 characters FMOD allows. Its version pairs theirs, so that it's different for every
+/// pair and changes whenever either of theirs does. The [migrations](Dsp::migrations) of `A` and `B`
+/// are carried over to their own parameters.
+pub struct Chain<A: Dsp, B: Dsp> {
+    a: A,
+    b: B,
+    // how each stage should be processed, as decided by the last query
+    a_state: ProcessResult,
+    b_state: ProcessResult,
+    // output of `a`
+    mid: SignalBuf,
+}
+
+impl<A: Dsp, B: Dsp> Chain<A, B> {
+    /// Gets the first DSP in the chain.
+    pub fn first(&self) -> &A {
+        &self.a
+    }
+
+    /// Gets the first DSP in the chain mutably.
+    pub fn first_mut(&mut self) -> &mut A {
+        &mut self.a
+    }
+
+    /// Gets the second DSP in the chain.
+    pub fn second(&self) -> &B {
+        &self.b
+    }
+
+    /// Gets the second DSP in the chain mutably.
+    pub fn second_mut(&mut self) -> &mut B {
+        &mut self.b
+    }
+
+    fn wrap(a: A, b: B) -> Self {
+        Chain {
+            a,
+            b,
+            a_state: ProcessResult::Continue,
+            b_state: ProcessResult::Continue,
+            mid: SignalBuf::for_block(),
+        }
+    }
+}
+
+impl<A: Dsp, B: Dsp> Dsp for Chain<A, B> {
+    fn name() -> &'static str {
+        join(&[A::name(), " > ", B::name()], NAME_LENGTH)
+    }
+
+    fn version() -> u32 {
+        migrate::pair_versions(A::version(), B::version())
+    }
+
+    fn ty() -> DspType {
+        A::ty()
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        merge_parameters::<Self, A, B>()
+    }
+
+    fn parameter_count() -> usize {
+        A::parameter_count() + B::parameter_count()
+    }
+
+    fn visit_parameter<V: ParameterVisitor>(&mut self, index: usize, visitor: V) -> Option<V::Output> {
+        let a_count = A::parameter_count();
+        if index < a_count {
+            self.a.visit_parameter(index, visitor)
+        } else {
+            self.b.visit_parameter(index - a_count, visitor)
+        }
+    }
+
+    fn migrations() -> Vec<Migration> {
+        merge_migrations::<A, B>()
+    }
+
+    fn create() -> Self {
+        Chain::wrap(A::create(), B::create())
+    }
+
+    fn try_create() -> Result<Self, DspError> {
+        Ok(Chain::wrap(A::try_create()?, B::try_create()?))
+    }
+
+    fn reset(&mut self) {
+        let _ = self.try_reset();
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.mid.reserve_block();
+        // both are reset even if the first fails
+        let a = self.a.try_reset();
+        a.and(self.b.try_reset())
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.a_state = self.a.should_process(idle, incoming_length);
+        let mid_idle = match self.a_state {
+            ProcessResult::Continue => false,
+            ProcessResult::SkipNoEffect => idle,
+            ProcessResult::SkipSilent => true,
+        };
+        self.b_state = self.b.should_process(mid_idle, incoming_length);
+        match (self.a_state, self.b_state) {
+            (ProcessResult::SkipNoEffect, ProcessResult::SkipNoEffect) => ProcessResult::SkipNoEffect,
+            (ProcessResult::SkipSilent, ProcessResult::SkipNoEffect) | (_, ProcessResult::SkipSilent) => ProcessResult::SkipSilent,
+            _ => ProcessResult::Continue,
+        }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        self.b.preferred_out_channels().or(self.a.preferred_out_channels())
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.a.latency_samples() + self.b.latency_samples()
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.read_with_sidechain(input, None, output);
+    }
+
+    fn read_with_sidechain(&mut self, input: SignalConst, sidechain: Option<SignalConst>, output: SignalMut) {
+        let _ = self.try_read(input, sidechain, output);
+    }
+
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, output: SignalMut) -> Result<(), DspError> {
+        let length = output.length();
+        let mid_channels = self.a.preferred_out_channels().unwrap_or(input.channels());
+        self.mid.resize(length, mid_channels);
+        run(&mut self.a, self.a_state, input, sidechain, self.mid.as_signal_mut())?;
+        run(&mut self.b, self.b_state, self.mid.as_signal(), sidechain, output)
+    }
+}
+
+/// Runs two DSPs on the same input, and sums their outputs.
+///
+/// Parameters, names, versions and migrations are combined as in [Chain]. The output of the DSP with
+/// less latency is delayed to line up with the other. If the DSPs have different output channel
+/// counts, the output has the larger of the two, and the other DSP's output is mixed to its
+/// [layout](crate::dsp::layout).
+pub struct Parallel<A: Dsp, B: Dsp> {
+    a: A,
+    b: B,
+    a_state: ProcessResult,
+    b_state: ProcessResult,
+    a_out: SignalBuf,
+    b_out: SignalBuf,
+    a_delay: DelayLine,
+    b_delay: DelayLine,
+}
+
+impl<A: Dsp, B: Dsp> Parallel<A, B> {
+    /// Gets the first DSP.
+    pub fn first(&self) -> &A {
+        &self.a
+    }
+
+    /// Gets the first DSP mutably.
+    pub fn first_mut(&mut self) -> &mut A {
+        &mut self.a
+    }
+
+    /// Gets the second DSP.
+    pub fn second(&self) -> &B {
+        &self.b
+    }
+
+    /// Gets the second DSP mutably.
+    pub fn second_mut(&mut self) -> &mut B {
+        &mut self.b
+    }
+
+    fn wrap(a: A, b: B) -> Self {
+        let mut parallel = Parallel {
+            a,
+            b,
+            a_state: ProcessResult::Continue,
+            b_state: ProcessResult::Continue,
+            a_out: SignalBuf::for_block(),
+            b_out: SignalBuf::for_block(),
+            a_delay: DelayLine::new(),
+            b_delay: DelayLine::new(),
+        };
+        parallel.reserve_delays();
+        parallel
+    }
+
+    // either output may be delayed by up to the full latency
+    fn reserve_delays(&mut self) {
+        let latency = self.latency_samples();
+        self.a_delay.reserve(latency);
+        self.b_delay.reserve(latency);
+    }
+}
+
+impl<A: Dsp, B: Dsp> Dsp for Parallel<A, B> {
+    fn name() -> &'static str {
+        join(&[A::name(), " | ", B::name()], NAME_LENGTH)
+    }
+
+    fn version() -> u32 {
+        migrate::pair_versions(A::version(), B::version())
+    }
+
+    fn ty() -> DspType {
+        if A::ty() == DspType::Generator && B::ty() == DspType::Generator {
+            DspType::Generator
+        } else {
+            DspType::Effect
+        }
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        merge_parameters::<Self, A, B>()
+    }
+
+    fn parameter_count() -> usize {
+        A::parameter_count() + B::parameter_count()
+    }
+
+    fn visit_parameter<V: ParameterVisitor>(&mut self, index: usize, visitor: V) -> Option<V::Output> {
+        let a_count = A::parameter_count();
+        if index < a_count {
+            self.a.visit_parameter(index, visitor)
+        } else {
+            self.b.visit_parameter(index - a_count, visitor)
+        }
+    }
+
+    fn migrations() -> Vec<Migration> {
+        merge_migrations::<A, B>()
+    }
+
+    fn create() -> Self {
+        Parallel::wrap(A::create(), B::create())
+    }
+
+    fn try_create() -> Result<Self, DspError> {
+        Ok(Parallel::wrap(A::try_create()?, B::try_create()?))
+    }
+
+    fn reset(&mut self) {
+        let _ = self.try_reset();
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.a_out.reserve_block();
+        self.b_out.reserve_block();
+        self.a_delay.clear();
+        self.b_delay.clear();
+        // both are reset even if the first fails
+        let a = self.a.try_reset();
+        let result = a.and(self.b.try_reset());
+        // the latencies may change on reset
+        self.reserve_delays();
+        result
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.a_state = self.a.should_process(idle, incoming_length);
+        self.b_state = self.b.should_process(idle, incoming_length);
+        // both outputs are summed, so passing through either still changes the signal
+        let silent = self.a_state == ProcessResult::SkipSilent && self.b_state == ProcessResult::SkipSilent;
+        if silent && self.a_delay.is_silent() && self.b_delay.is_silent() {
+            ProcessResult::SkipSilent
+        } else {
+            ProcessResult::Continue
+        }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        match (self.a.preferred_out_channels(), self.b.preferred_out_channels()) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.a.latency_samples().max(self.b.latency_samples())
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.read_with_sidechain(input, None, output);
+    }
+
+    fn read_with_sidechain(&mut self, input: SignalConst, sidechain: Option<SignalConst>, output: SignalMut) {
+        let _ = self.try_read(input, sidechain, output);
+    }
+
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+        let length = output.length();
+        let a_channels = self.a.preferred_out_channels().unwrap_or(input.channels());
+        let b_channels = self.b.preferred_out_channels().unwrap_or(input.channels());
+        self.a_out.resize(length, a_channels);
+        self.b_out.resize(length, b_channels);
+        run(&mut self.a, self.a_state, input, sidechain, self.a_out.as_signal_mut())?;
+        run(&mut self.b, self.b_state, input, sidechain, self.b_out.as_signal_mut())?;
+
+        let latency = self.latency_samples();
+        self.a_delay.process(self.a_out.samples_mut(), a_channels, latency - self.a.latency_samples());
+        self.b_delay.process(self.b_out.samples_mut(), b_channels, latency - self.b.latency_samples());
+
+        output.fill(0.);
+        add_into(self.a_out.as_signal(), &mut output);
+        add_into(self.b_out.as_signal(), &mut output);
+        Ok(())
+    }
+}
+
+// processes a single stage as decided by its query
+fn run<D: Dsp>(dsp: &mut D, state: ProcessResult, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+    match state {
+        ProcessResult::Continue => return dsp.try_read(input, sidechain, output),
+        ProcessResult::SkipNoEffect => {
+            output.fill(0.);
+            add_into(input, &mut output);
+        }
+        ProcessResult::SkipSilent => output.fill(0.),
+    }
+    Ok(())
+}
+
+// adds one signal onto another, mixing between their layouts if they differ
+fn add_into(from: SignalConst, to: &mut SignalMut) {
+    let layout = to.layout();
+    if from.channels() == 0 || layout.channels() == 0 {
+        return;
+    }
+    if from.layout() == layout {
+        to.add(&from);
+    } else {
+        for (out, x) in to.samples_mut().iter_mut().zip(from.mix_to(layout, Upmix::default())) {
+            *out += x;
+        }
+    }
+}
+
+// FMOD's limits on the lengths of DSP and parameter names
+const NAME_LENGTH: usize = 31;
+const PARAMETER_NAME_LENGTH: usize = 15;
+
+// stages are numbered rather than named, as FMOD leaves little room in parameter names
+const PREFIXES: [&str; 2] = ["1.", "2."];
+
+fn merge_parameters<W: Dsp, A: Dsp, B: Dsp>() -> Vec<Parameter<W>> {
+    let a = A::parameters().into_iter().map(|it| prefixed(PREFIXES[0], it.forwarded()));
+    let b = B::parameters().into_iter().map(|it| prefixed(PREFIXES[1], it.forwarded()));
+    a.chain(b).collect()
+}
+
+fn prefixed<W>(prefix: &'static str, mut param: Parameter<W>) -> Parameter<W> {
+    param.name = join(&[prefix, param.name], PARAMETER_NAME_LENGTH);
+    param
+}
+
+fn merge_migrations<A: Dsp, B: Dsp>() -> Vec<Migration> {
+    let a = A::migrations().into_iter().map(|it| it.staged(false, PREFIXES[0]));
+    let b = B::migrations().into_iter().map(|it| it.staged(true, PREFIXES[1]));
+    a.chain(b).collect()
+}
+
+// joins each distinct list of names once, so that combined names can be handed out as
+// `&'static str` without allocating again whenever they're asked for. FMOD would cut names longer
+// than `max_length` characters at the same place, leaving names that differ after it the same, so
+// they're cut here instead and end with a hash of the full name
+fn join(parts: &[&'static str], max_length: usize) -> &'static str {
+    static JOINED: Mutex<BTreeMap<(Vec<&'static str>, usize), &'static str>> = Mutex::new(BTreeMap::new());
+    let mut joined = JOINED.lock().unwrap_or_else(|it| it.into_inner());
+    let key = (parts.to_vec(), max_length);
+    if let Some(it) = joined.get(&key) {
+        return it;
+    }
+    let mut name = parts.concat();
+    if name.chars().count() > max_length {
+        // FNV-1a, which unlike std's hashers stays the same between releases
+        let hash = name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193));
+        let hash = format!("~{:04x}", hash >> 16);
+        name = name.chars().take(max_length - hash.len()).chain(hash.chars()).collect();
+    }
+    let leaked: &'static str = name.leak();
+    joined.insert(key, leaked);
+    leaked
+}
@@ -0,0 +1,66 @@
+//! AIFF and uncompressed AIFF-C decoding.
+
+use crate::dsp::decode::{DecodeError, Decoded, Format, PcmSample, RawPcm, pcm};
+
+pub(super) fn decode(data: &[u8]) -> Result<Decoded, DecodeError> {
+    let compressed = match data.get(8..12) {
+        Some(b"AIFF") => false,
+        Some(b"AIFC") => true,
+        _ => return Err(DecodeError::BadHeader),
+    };
+    let (mut common, mut sound) = (None, None);
+    let mut chunks = &data[12..];
+    while chunks.len() >= 8 {
+        let len = u32::from_be_bytes(chunks[4..8].try_into().unwrap()) as usize;
+        let body = chunks.get(8..8 + len).ok_or(DecodeError::Truncated)?;
+        match &chunks[..4] {
+            b"COMM" => common = Some(read_common(body, compressed)?),
+            b"SSND" => sound = Some(body),
+            _ => {}
+        }
+        // chunks are padded to an even length
+        chunks = chunks.get(8 + len + len % 2..).unwrap_or_default();
+    }
+    let (format, frames) = common.ok_or(DecodeError::BadHeader)?;
+    let sound = sound.ok_or(DecodeError::Truncated)?;
+    // samples start after the offset and block size, skipping the offset
+    let offset = u32::from_be_bytes(sound.get(..4).ok_or(DecodeError::Truncated)?.try_into().unwrap()) as usize;
+    let samples = sound.get(8 + offset..).ok_or(DecodeError::Truncated)?;
+    let len = frames * format.sample.width() * format.channels as usize;
+    pcm::decode(samples.get(..len).ok_or(DecodeError::Truncated)?, format)
+}
+
+// reads the format and number of frames
+fn read_common(body: &[u8], compressed: bool) -> Result<(RawPcm, usize), DecodeError> {
+    let field = |range: std::ops::Range<usize>| body.get(range).ok_or(DecodeError::BadHeader);
+    let channels = u16::from_be_bytes(field(0..2)?.try_into().unwrap());
+    let frames = u32::from_be_bytes(field(2..6)?.try_into().unwrap()) as usize;
+    let bits = u16::from_be_bytes(field(6..8)?.try_into().unwrap());
+    let sample_rate = read_extended(field(8..18)?.try_into().unwrap());
+    let compression = if compressed { field(18..22)? } else { b"NONE" };
+    // samples that don't fill their bytes are padded on the right, so can be read as if they did
+    let sample = match (compression, bits) {
+        (b"NONE" | b"sowt", 1..=8) => PcmSample::I8,
+        (b"NONE" | b"sowt", 9..=16) => PcmSample::I16,
+        (b"NONE" | b"sowt", 17..=24) => PcmSample::I24,
+        (b"NONE" | b"sowt", 25..=32) => PcmSample::I32,
+        (b"fl32" | b"FL32", _) => PcmSample::F32,
+        _ => return Err(DecodeError::Unsupported(Format::Aiff)),
+    };
+    let format = RawPcm {
+        sample,
+        // byte-swapped samples are little-endian
+        big_endian: compression != b"sowt",
+        channels,
+        sample_rate,
+    };
+    Ok((format, frames))
+}
+
+// reads an 80-bit extended precision float, which AIFF uses for sample rates
+fn read_extended(bytes: [u8; 10]) -> u32 {
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7fff) as i32 - 16383;
+    // the mantissa has an explicit integer bit at the top
+    let mantissa = u64::from_be_bytes(bytes[2..].try_into().unwrap());
+    (mantissa as f64 * 2f64.powi(exponent - 63)).round() as u32
+}
@@ -0,0 +1,30 @@
+//! FLAC decoding, through `claxon`.
+
+use crate::dsp::decode::{DecodeError, Decoded, Format, skip_id3};
+use crate::dsp::signal::SignalBuf;
+use claxon::FlacReader;
+
+pub(super) fn decode(data: &[u8]) -> Result<Decoded, DecodeError> {
+    let mut reader = FlacReader::new(skip_id3(data)).map_err(|it| error(it, DecodeError::BadHeader))?;
+    let info = reader.streaminfo();
+    let max = (1u64 << (info.bits_per_sample - 1)) as f32;
+    let samples = reader
+        .samples()
+        .map(|it| it.map(|it| it as f32 / max))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|it| error(it, DecodeError::BadData))?;
+    Ok(Decoded {
+        signal: SignalBuf::from_samples(samples, info.channels as usize),
+        sample_rate: info.sample_rate,
+    })
+}
+
+// format errors are blamed on whichever part was being read
+fn error(error: claxon::Error, format_error: DecodeError) -> DecodeError {
+    match error {
+        // reading from memory only fails when the data runs out
+        claxon::Error::IoError(_) => DecodeError::Truncated,
+        claxon::Error::FormatError(_) => format_error,
+        claxon::Error::Unsupported(_) => DecodeError::Unsupported(Format::Flac),
+    }
+}
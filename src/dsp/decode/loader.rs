@@ -0,0 +1,203 @@
+//! Decoding on a worker thread, for data parameters too large to decode in a setter.
+
+use crate::dsp::decode::{DecodeError, Decoded, decode};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, Sender, SyncSender, channel, sync_channel};
+use std::time::{Duration, Instant};
+use std::{mem, thread};
+
+/// The state of a [Loader].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LoadState {
+    /// Nothing has been loaded.
+    Empty,
+    /// Data is being decoded. Any previously loaded audio is still available until it finishes.
+    Loading,
+    /// The most recently loaded data was decoded, and is available.
+    Ready,
+    /// The most recently loaded data couldn't be decoded, and no audio is available.
+    Failed(DecodeError),
+}
+
+/// Decodes data parameters on a worker thread, handing the result to the mixer thread.
+///
+/// [Loader::load] is called from a parameter setter (see [loader_param](crate::loader_param)),
+/// and copies the data to decode in the background. [Loader::update] is called at the start of
+/// [Dsp::read](crate::dsp::Dsp::read), and swaps in finished audio without locking, so that
+/// the audio only changes between blocks. If data is loaded again before the last load finishes,
+/// the older result is discarded.
+///
+/// Each loader starts one worker thread on its first load, which lives as long as the loader.
+/// Audio replaced or discarded by [Loader::update] is sent back to the worker to be freed, so the
+/// mixer thread doesn't spend time freeing large buffers.
+///
+/// ```
+/// use scamble::dsp::decode::{LoadState, Loader};
+/// use std::time::Duration;
+///
+/// let mut loader = Loader::new();
+/// loader.load(b"not audio");
+/// assert_eq!(loader.state(), LoadState::Loading);
+/// assert!(loader.wait(Duration::from_secs(10)));
+/// assert!(matches!(loader.state(), LoadState::Failed(_)));
+/// assert!(loader.current().is_none());
+/// ```
+pub struct Loader {
+    current: Option<Decoded>,
+    state: LoadState,
+    // incremented by each load, to tell which results are still wanted
+    generation: u64,
+    // the generation of the latest load, shared with the worker so it can skip older loads
+    latest: Arc<AtomicU64>,
+    // jobs for the worker, or [None] before the first load or if it couldn't be started
+    jobs: Option<SyncSender<Job>>,
+    sender: Sender<Loaded>,
+    receiver: Receiver<Loaded>,
+}
+
+type Loaded = (u64, Result<Decoded, DecodeError>);
+
+// jobs that can wait for the worker before `load` blocks, or before `update` frees audio itself
+const QUEUE_LEN: usize = 16;
+
+enum Job {
+    Decode(u64, Box<[u8]>),
+    // audio that's no longer needed, to be freed off the mixer thread
+    Free(Decoded),
+}
+
+// carries out a job, on the worker thread if there is one
+fn run(job: Job, latest: &AtomicU64, results: &Sender<Loaded>) {
+    match job {
+        // loads that have been replaced aren't worth decoding
+        Job::Decode(generation, data) if generation == latest.load(Ordering::Relaxed) => {
+            // the loader may have been dropped in the meantime, leaving nobody to receive this
+            let _ = results.send((generation, decode(&data)));
+        }
+        Job::Decode(..) => {}
+        Job::Free(decoded) => drop(decoded),
+    }
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Loader {
+            current: None,
+            state: LoadState::Empty,
+            generation: 0,
+            latest: Arc::new(AtomicU64::new(0)),
+            jobs: None,
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl Loader {
+    /// Creates a loader with nothing loaded. The worker thread isn't started until the first load.
+    pub fn new() -> Self {
+        Loader::default()
+    }
+
+    /// Starts decoding a copy of `data` on the worker thread, [detecting](super::Format::detect)
+    /// its format. The current audio stays available until decoding finishes.
+    pub fn load(&mut self, data: &[u8]) {
+        self.generation += 1;
+        self.latest.store(self.generation, Ordering::Relaxed);
+        self.state = LoadState::Loading;
+        if self.jobs.is_none() {
+            self.jobs = self.spawn_worker();
+        }
+        let job = Job::Decode(self.generation, data.into());
+        let unsent = match &self.jobs {
+            Some(jobs) => jobs.send(job).err().map(|it| it.0),
+            None => Some(job),
+        };
+        // without a worker, decoding here is better than never
+        if let Some(job) = unsent {
+            run(job, &self.latest, &self.sender);
+        }
+    }
+
+    fn spawn_worker(&self) -> Option<SyncSender<Job>> {
+        let (jobs, queue) = sync_channel(QUEUE_LEN);
+        let (latest, results) = (self.latest.clone(), self.sender.clone());
+        // runs until the loader, and with it the sending half of the queue, is dropped
+        let work = move || {
+            for job in queue {
+                run(job, &latest, &results);
+            }
+        };
+        thread::Builder::new().name("scamble decoder".into()).spawn(work).ok()?;
+        Some(jobs)
+    }
+
+    // hands audio to the worker to be freed. Never blocks; if the queue is full, it's freed here
+    fn discard(&self, decoded: Decoded) {
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.try_send(Job::Free(decoded));
+        }
+    }
+
+    // swaps in a finished load if it's the latest, returning whether it was
+    fn receive(&mut self, (generation, result): Loaded) -> bool {
+        if generation != self.generation {
+            if let Ok(decoded) = result {
+                self.discard(decoded);
+            }
+            return false;
+        }
+        let (current, state) = match result {
+            Ok(decoded) => (Some(decoded), LoadState::Ready),
+            Err(error) => (None, LoadState::Failed(error)),
+        };
+        if let Some(old) = mem::replace(&mut self.current, current) {
+            self.discard(old);
+        }
+        self.state = state;
+        true
+    }
+
+    /// Swaps in the result of the most recent load if it has finished, returning whether it did.
+    /// Never blocks, so can be called from [Dsp::read](crate::dsp::Dsp::read).
+    pub fn update(&mut self) -> bool {
+        let mut updated = false;
+        while let Ok(loaded) = self.receiver.try_recv() {
+            updated |= self.receive(loaded);
+        }
+        updated
+    }
+
+    /// Blocks until the most recent load finishes and swaps it in, for at most `timeout`. Returns
+    /// whether nothing is still loading. Useful in tools and tests, but must not be called from
+    /// [Dsp::read](crate::dsp::Dsp::read).
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.is_loading() {
+            match self.receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(loaded) => {
+                    self.receive(loaded);
+                }
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+
+    /// Gets the currently available audio, if any.
+    pub fn current(&self) -> Option<&Decoded> {
+        self.current.as_ref()
+    }
+
+    /// Gets the state of this loader, as of the last [Loader::update].
+    pub fn state(&self) -> LoadState {
+        self.state
+    }
+
+    /// Checks whether data is still being decoded.
+    pub fn is_loading(&self) -> bool {
+        self.state == LoadState::Loading
+    }
+}
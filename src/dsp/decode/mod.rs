@@ -0,0 +1,219 @@
+//! Utilities for decoding samples from a plugin, for data inputs.
+//!
+//! WAV files are always supported. Other formats are each enabled by a cargo feature: `flac` for
+//! FLAC, `vorbis` for Ogg Vorbis, `aiff` for AIFF and uncompressed AIFF-C, and `raw` for headerless
+//! PCM. Without its feature, data in a format is still [detected](Format::detect), but not decoded.
+//!
+//! Decoding large files can take a while, so data parameters should usually decode them in the
+//! background with a [Loader].
+
+use crate::dsp::signal::{Signal, SignalBuf};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "aiff")]
+mod aiff;
+#[cfg(feature = "flac")]
+mod flac;
+mod loader;
+#[cfg(any(feature = "aiff", feature = "raw"))]
+mod pcm;
+#[cfg(feature = "vorbis")]
+mod vorbis;
+mod wav;
+
+pub use loader::{LoadState, Loader};
+
+/// A format of audio data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// A RIFF WAVE file.
+    Wav,
+    /// A FLAC file, which may start with an ID3 tag. Requires the `flac` feature.
+    Flac,
+    /// A Vorbis stream in an Ogg container. Requires the `vorbis` feature.
+    OggVorbis,
+    /// An AIFF file, or an uncompressed AIFF-C file. Requires the `aiff` feature.
+    Aiff,
+    /// Samples with no header, which can't be detected and must be described instead. Requires the
+    /// `raw` feature.
+    Raw(RawPcm),
+}
+
+/// A description of headerless PCM data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RawPcm {
+    /// The type of each sample.
+    pub sample: PcmSample,
+    /// Whether samples are stored most significant byte first.
+    pub big_endian: bool,
+    /// The number of interleaved channels.
+    pub channels: u16,
+    /// The sample rate of the data.
+    pub sample_rate: u32,
+}
+
+/// A type of PCM sample.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PcmSample {
+    /// Unsigned 8-bit integers, centred on 128.
+    U8,
+    /// Signed 8-bit integers.
+    I8,
+    /// Signed 16-bit integers.
+    I16,
+    /// Signed 24-bit integers, packed into three bytes.
+    I24,
+    /// Signed 32-bit integers.
+    I32,
+    /// 32-bit floats.
+    F32,
+}
+
+impl Format {
+    /// Guesses the format of a data buffer from its first bytes. Headerless PCM is never detected.
+    pub fn detect(data: &[u8]) -> Option<Format> {
+        match data {
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some(Format::Wav),
+            [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C', ..] => Some(Format::Aiff),
+            // the first page of an Ogg stream holds only the codec's identification header
+            [b'O', b'g', b'g', b'S', ..] if data.len() > 27 => {
+                let header = 27 + data[26] as usize;
+                (data.get(header..header + 7)? == b"\x01vorbis").then_some(Format::OggVorbis)
+            }
+            _ if skip_id3(data).starts_with(b"fLaC") => Some(Format::Flac),
+            _ => None,
+        }
+    }
+}
+
+// skips an ID3v2 tag at the start of the data, if there is one
+fn skip_id3(data: &[u8]) -> &[u8] {
+    match data {
+        // the size excludes the header and is stored in 7 bits per byte
+        [b'I', b'D', b'3', _, _, _, size @ ..] if size.len() >= 4 => {
+            let size = size[..4].iter().fold(0, |acc, it| acc << 7 | (*it & 0x7f) as usize);
+            data.get(10 + size..).unwrap_or_default()
+        }
+        _ => data,
+    }
+}
+
+/// A reason that data couldn't be decoded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The format of the data couldn't be detected.
+    UnknownFormat,
+    /// The data is in a format that can't be decoded, because its feature isn't enabled or because
+    /// it uses an encoding that isn't supported.
+    Unsupported(Format),
+    /// The data ended before all of the audio it describes.
+    Truncated,
+    /// The header describing the audio is invalid.
+    BadHeader,
+    /// The encoded audio is invalid.
+    BadData,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnknownFormat => write!(f, "unknown audio format"),
+            DecodeError::Unsupported(format) => write!(f, "unsupported audio format or encoding ({format:?})"),
+            DecodeError::Truncated => write!(f, "audio data is truncated"),
+            DecodeError::BadHeader => write!(f, "audio header is invalid"),
+            DecodeError::BadData => write!(f, "audio data is invalid"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Audio decoded from a data buffer, keeping all of its channels.
+#[derive(Clone, Debug)]
+pub struct Decoded {
+    /// The decoded samples, with the data's channel count.
+    pub signal: SignalBuf,
+    /// The sample rate of the data, which may differ from the mixer's; see
+    /// [resample](crate::dsp::resample) for converting between them.
+    pub sample_rate: u32,
+}
+
+/// How [Decoded::downmix] reduces multichannel audio to a single channel.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Downmix {
+    /// Keeps the first channel, discarding the others.
+    First,
+    /// Averages all of the channels.
+    Average,
+    /// Keeps the given channel, discarding the others. If the data has fewer channels, the last
+    /// channel is kept instead, so that mono data is kept as it is.
+    Channel(usize),
+}
+
+impl Decoded {
+    /// Gets the number of channels in the data.
+    pub fn channels(&self) -> usize {
+        self.signal.channels()
+    }
+
+    /// Gets the number of frames in the data.
+    pub fn frames(&self) -> usize {
+        self.signal.samples().len() / self.channels().max(1)
+    }
+
+    /// Reduces the data to a single channel, appending the samples to `output`.
+    pub fn downmix_into(&self, downmix: Downmix, output: &mut Vec<f32>) {
+        let channels = self.channels();
+        if channels == 0 {
+            return;
+        }
+        let frames = self.signal.samples().chunks_exact(channels);
+        match downmix {
+            Downmix::First => output.extend(frames.map(|frame| frame[0])),
+            Downmix::Average => output.extend(frames.map(|frame| frame.iter().sum::<f32>() / channels as f32)),
+            Downmix::Channel(channel) => output.extend(frames.map(|frame| frame[channel.min(channels - 1)])),
+        }
+    }
+
+    /// Reduces the data to a single channel.
+    pub fn downmix(&self, downmix: Downmix) -> Vec<f32> {
+        let mut output = Vec::with_capacity(self.frames());
+        self.downmix_into(downmix, &mut output);
+        output
+    }
+}
+
+/// Decode a data buffer with all of its channels, [detecting](Format::detect) its format.
+pub fn decode(data: &[u8]) -> Result<Decoded, DecodeError> {
+    decode_as(data, Format::detect(data).ok_or(DecodeError::UnknownFormat)?)
+}
+
+/// Decode a data buffer with all of its channels, in a known format.
+pub fn decode_as(data: &[u8], format: Format) -> Result<Decoded, DecodeError> {
+    match format {
+        Format::Wav => wav::decode(data),
+        #[cfg(feature = "flac")]
+        Format::Flac => flac::decode(data),
+        #[cfg(feature = "vorbis")]
+        Format::OggVorbis => vorbis::decode(data),
+        #[cfg(feature = "aiff")]
+        Format::Aiff => aiff::decode(data),
+        #[cfg(feature = "raw")]
+        Format::Raw(format) => pcm::decode(data, format),
+        #[allow(unreachable_patterns)]
+        _ => Err(DecodeError::Unsupported(format)),
+    }
+}
+
+/// Decode a data buffer into mono samples, guessing the correct format and keeping only the first
+/// channel. Returns the sample rate of the data, which may differ from the mixer's; see
+/// [resample](crate::dsp::resample) for converting between them.
+///
+/// Nothing is added to `output` if decoding fails. To keep every channel, or to choose how they're
+/// mixed down, use [decode].
+pub fn decode_into(data: &[u8], output: &mut Vec<f32>) -> Result<u32, DecodeError> {
+    let decoded = decode(data)?;
+    decoded.downmix_into(Downmix::First, output);
+    Ok(decoded.sample_rate)
+}
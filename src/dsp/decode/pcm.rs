@@ -0,0 +1,51 @@
+//! Headerless PCM decoding, also used for the sound data of AIFF files.
+
+use crate::dsp::decode::{DecodeError, Decoded, PcmSample, RawPcm};
+use crate::dsp::signal::SignalBuf;
+
+impl PcmSample {
+    // the number of bytes taken by each sample
+    pub(super) fn width(self) -> usize {
+        match self {
+            PcmSample::U8 | PcmSample::I8 => 1,
+            PcmSample::I16 => 2,
+            PcmSample::I24 => 3,
+            PcmSample::I32 | PcmSample::F32 => 4,
+        }
+    }
+
+    fn read(self, bytes: &[u8], big_endian: bool) -> f32 {
+        // sign-extends three bytes by shifting them into the top of an i32 and back down
+        let i24 = |b: &[u8]| match big_endian {
+            true => i32::from_be_bytes([b[0], b[1], b[2], 0]) >> 8,
+            false => i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8,
+        };
+        match (self, big_endian) {
+            (PcmSample::U8, _) => (bytes[0] as f32 - 128.) / 128.,
+            (PcmSample::I8, _) => bytes[0] as i8 as f32 / 128.,
+            (PcmSample::I16, true) => i16::from_be_bytes([bytes[0], bytes[1]]) as f32 / 32768.,
+            (PcmSample::I16, false) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.,
+            (PcmSample::I24, _) => i24(bytes) as f32 / 8388608.,
+            (PcmSample::I32, true) => i32::from_be_bytes(bytes.try_into().unwrap()) as f32 / 2147483648.,
+            (PcmSample::I32, false) => i32::from_le_bytes(bytes.try_into().unwrap()) as f32 / 2147483648.,
+            (PcmSample::F32, true) => f32::from_be_bytes(bytes.try_into().unwrap()),
+            (PcmSample::F32, false) => f32::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+pub(super) fn decode(data: &[u8], format: RawPcm) -> Result<Decoded, DecodeError> {
+    let width = format.sample.width();
+    let frame = width * format.channels as usize;
+    if frame == 0 {
+        return Err(DecodeError::BadHeader);
+    }
+    if !data.len().is_multiple_of(frame) {
+        return Err(DecodeError::Truncated);
+    }
+    let samples = data.chunks_exact(width).map(|it| format.sample.read(it, format.big_endian)).collect();
+    Ok(Decoded {
+        signal: SignalBuf::from_samples(samples, format.channels as usize),
+        sample_rate: format.sample_rate,
+    })
+}
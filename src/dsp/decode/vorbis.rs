@@ -0,0 +1,31 @@
+//! Ogg Vorbis decoding, through `lewton`.
+
+use crate::dsp::decode::{DecodeError, Decoded};
+use crate::dsp::signal::SignalBuf;
+use lewton::VorbisError;
+use lewton::inside_ogg::OggStreamReader;
+use std::io::Cursor;
+
+pub(super) fn decode(data: &[u8]) -> Result<Decoded, DecodeError> {
+    let mut reader = OggStreamReader::new(Cursor::new(data)).map_err(error)?;
+    let channels = reader.ident_hdr.audio_channels as usize;
+    let sample_rate = reader.ident_hdr.audio_sample_rate;
+    let mut samples = vec![];
+    // each packet is interleaved, and packets may be empty
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(error)? {
+        samples.extend(packet.into_iter().map(|it| it as f32 / 32768.));
+    }
+    Ok(Decoded {
+        signal: SignalBuf::from_samples(samples, channels),
+        sample_rate,
+    })
+}
+
+fn error(error: VorbisError) -> DecodeError {
+    match error {
+        VorbisError::BadHeader(_) => DecodeError::BadHeader,
+        VorbisError::BadAudio(_) => DecodeError::BadData,
+        // the container is read lazily, so running out of pages shows up here
+        VorbisError::OggError(_) => DecodeError::Truncated,
+    }
+}
@@ -0,0 +1,32 @@
+//! WAV decoding, through `hound`.
+
+use crate::dsp::decode::{DecodeError, Decoded, Format};
+use crate::dsp::signal::SignalBuf;
+use hound::{SampleFormat, WavReader};
+
+pub(super) fn decode(data: &[u8]) -> Result<Decoded, DecodeError> {
+    let mut reader = WavReader::new(data).map_err(error)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>(),
+        SampleFormat::Int => {
+            // integer samples are signed, so only have half their range on either side of zero
+            let max = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|it| it.map(|it| it as f32 / max)).collect()
+        }
+    };
+    Ok(Decoded {
+        signal: SignalBuf::from_samples(samples.map_err(error)?, spec.channels as usize),
+        sample_rate: spec.sample_rate,
+    })
+}
+
+fn error(error: hound::Error) -> DecodeError {
+    match error {
+        // reading from memory only fails when the data runs out
+        hound::Error::IoError(_) => DecodeError::Truncated,
+        hound::Error::UnfinishedSample => DecodeError::Truncated,
+        hound::Error::FormatError(_) | hound::Error::InvalidSampleFormat => DecodeError::BadHeader,
+        hound::Error::TooWide | hound::Error::Unsupported => DecodeError::Unsupported(Format::Wav),
+    }
+}
@@ -4,34 +4,37 @@
 use crate::raw_bindings::*;
 use crate::raw_bindings::FMOD_DSP_PARAMETER_DATA_TYPE::*;
 use crate::raw_bindings::FMOD_RESULT::{FMOD_ERR_DSP_DONTPROCESS, FMOD_ERR_DSP_SILENCE, FMOD_ERR_INVALID_PARAM, FMOD_ERR_PLUGIN, FMOD_OK};
-use crate::dsp::{Dsp, DspType, ParameterType, ProcessResult};
+use crate::dsp::{Dsp, DspError, DspType, Parameter, ParameterType, ParameterVisitor, ProcessResult};
+use crate::dsp::layout::ChannelLayout;
 use crate::dsp::signal::{SignalConst, SignalMut};
-use std::{alloc, panic, ptr};
-use std::alloc::Layout;
+use std::borrow::Cow;
+use std::{panic, ptr};
+use std::panic::AssertUnwindSafe;
+use std::cell::Cell;
 use std::ffi::{c_char, c_int, c_uint, c_void, CString};
 use std::ptr::{slice_from_raw_parts, slice_from_raw_parts_mut};
-use std::str::FromStr;
 
 // wrapping DSPs into FMOD's format
 
 /// Expose a DSP type to FMOD to be loaded as a dynamic library.
+///
+/// Any DSP type may be used, including wrapped ones such as `WetDry<MyDsp>`.
 #[macro_export]
 macro_rules! expose_dsp {
-    ($t:tt) => {
+    ($t:ty) => {
         const _: () = {
-            use scamble::dsp::interop;
+            use $crate::dsp::interop;
             use $crate::raw_bindings::FMOD_DSP_DESCRIPTION;
             use core::mem::MaybeUninit;
 
-            #[allow(non_snake_case)]
-            static mut ${concat($t, _DESC)}: MaybeUninit<FMOD_DSP_DESCRIPTION> = MaybeUninit::uninit();
+            static mut DESC: MaybeUninit<FMOD_DSP_DESCRIPTION> = MaybeUninit::uninit();
 
             #[cfg(windows)]
             #[allow(non_snake_case)]
             #[unsafe(no_mangle)]
             #[allow(static_mut_refs)]
             unsafe extern "stdcall" fn FMODGetDSPDescription() -> *const FMOD_DSP_DESCRIPTION {
-                unsafe { ${concat($t, _DESC)}.write(interop::into_desc::<$t>()) }
+                unsafe { DESC.write(interop::into_desc::<$t>()) }
             }
 
             #[cfg(not(windows))]
@@ -39,16 +42,18 @@ macro_rules! expose_dsp {
             #[unsafe(no_mangle)]
             #[allow(static_mut_refs)]
             unsafe extern "C" fn FMODGetDSPDescription() -> *const FMOD_DSP_DESCRIPTION {
-                unsafe { ${concat($t, _DESC)}.write(interop::into_desc::<$t>()) }
+                unsafe { DESC.write(interop::into_desc::<$t>()) }
             }
         };
     };
 }
 
 /// Expose multiple DSP types to FMOD to be loaded as a dynamic library.
+///
+/// Any DSP types may be used, including wrapped ones such as `WetDry<MyDsp>`.
 #[macro_export]
 macro_rules! expose_dsp_list {
-    ($($t:ident $(,)?)*) => {
+    ($($t:ty),* $(,)?) => {
         const _: () = {
             use core::mem::MaybeUninit;
             use core::ptr;
@@ -58,37 +63,33 @@ macro_rules! expose_dsp_list {
             use $crate::raw_bindings::FMOD_PLUGINTYPE::FMOD_PLUGINTYPE_DSP;
             use $crate::raw_bindings::FMOD_PLUGINTYPE::FMOD_PLUGINTYPE_MAX;
 
-            $(
-                #[allow(non_upper_case_globals)]
-                static mut ${concat($t, _DESC)}: MaybeUninit<FMOD_DSP_DESCRIPTION> = MaybeUninit::uninit();
+            static mut DESCS: MaybeUninit<[FMOD_DSP_DESCRIPTION; ${count($t)}]> = MaybeUninit::uninit();
+            static mut PLUGIN_LIST: MaybeUninit<[FMOD_PLUGINLIST; ${count($t)} + 1]> = MaybeUninit::uninit();
 
-                #[allow(static_mut_refs)]
-                #[allow(non_snake_case)]
-                fn ${concat(Write, $t)}() -> *const FMOD_DSP_DESCRIPTION {
-                    unsafe { ${concat($t, _DESC)}.write(interop::into_desc::<$t>()) }
+            #[allow(static_mut_refs)]
+            fn write_list() -> *const FMOD_PLUGINLIST {
+                unsafe {
+                    let descs = DESCS.write([$( interop::into_desc::<$t>(), )*]);
+                    let mut list = [FMOD_PLUGINLIST { type_: FMOD_PLUGINTYPE_MAX, description: ptr::null_mut() }; ${count($t)} + 1];
+                    for (entry, desc) in list.iter_mut().zip(descs.iter_mut()) {
+                        *entry = FMOD_PLUGINLIST { type_: FMOD_PLUGINTYPE_DSP, description: desc as *mut FMOD_DSP_DESCRIPTION as *mut _ };
+                    }
+                    PLUGIN_LIST.write(list).as_ptr()
                 }
-            )*
-
-            static mut PLUGIN_LIST: MaybeUninit<[FMOD_PLUGINLIST; ${count($t)} + 1]> = MaybeUninit::zeroed();
+            }
 
             #[cfg(windows)]
             #[allow(non_snake_case)]
             #[unsafe(no_mangle)]
-            #[allow(static_mut_refs)]
             unsafe extern "stdcall" fn FMODGetPluginDescriptionList() -> *const FMOD_PLUGINLIST {
-                unsafe {
-                    PLUGIN_LIST.write([$( FMOD_PLUGINLIST { type_: FMOD_PLUGINTYPE_DSP, description: ${concat(Write, $t)}() as *mut _ }, )* FMOD_PLUGINLIST{ type_: FMOD_PLUGINTYPE_MAX, description: ptr::null_mut() } ]).as_ptr()
-                }
+                write_list()
             }
 
             #[cfg(not(windows))]
             #[allow(non_snake_case)]
             #[unsafe(no_mangle)]
-            #[allow(static_mut_refs)]
             unsafe extern "C" fn FMODGetPluginDescriptionList() -> *const FMOD_PLUGINLIST {
-                unsafe {
-                    PLUGIN_LIST.write([$( FMOD_PLUGINLIST { type_: FMOD_PLUGINTYPE_DSP, description: ${concat(Write, $t)}() as *mut _ }, )* FMOD_PLUGINLIST{ type_: FMOD_PLUGINTYPE_MAX, description: ptr::null_mut() } ]).as_ptr()
-                }
+                write_list()
             }
         };
     };
@@ -108,7 +109,7 @@ pub fn into_desc<D: Dsp>() -> FMOD_DSP_DESCRIPTION {
             desc.push_str("\n    at: ");
             desc.push_str(&loc.to_string());
         }
-        let state = unsafe { CUR_STATE };
+        let state = CUR_STATE.get();
         if !state.is_null() {
             unsafe {
                 log_err(&desc, state);
@@ -119,14 +120,13 @@ pub fn into_desc<D: Dsp>() -> FMOD_DSP_DESCRIPTION {
     }));
 
     // name sanitization
-    let name = sanitize_str(D::name());
+    let name = fixed_c_str(D::name(), "name");
     // buffer counts
     let input_buffers = if D::ty() == DspType::Effect { 1 } else { 0 };
     // parameters
     let params = D::parameters();
     let mut param_conv: Vec<Box<FMOD_DSP_PARAMETER_DESC>> = Vec::new();
     for param in &params {
-        let param_desc= CString::from_str(param.desc).unwrap();
         param_conv.push(Box::new(FMOD_DSP_PARAMETER_DESC {
             type_: match &param.ty {
                 ParameterType::Float { .. } => FMOD_DSP_PARAMETER_TYPE::FMOD_DSP_PARAMETER_TYPE_FLOAT,
@@ -134,9 +134,9 @@ pub fn into_desc<D: Dsp>() -> FMOD_DSP_DESCRIPTION {
                 ParameterType::Bool { .. } => FMOD_DSP_PARAMETER_TYPE::FMOD_DSP_PARAMETER_TYPE_BOOL,
                 _ => FMOD_DSP_PARAMETER_TYPE::FMOD_DSP_PARAMETER_TYPE_DATA
             },
-            name: sanitize_str(param.name),
-            label: sanitize_str(param.unit),
-            description: param_desc.into_raw(),
+            name: fixed_c_str(param.name, "parameter name"),
+            label: fixed_c_str(param.unit, "parameter unit"),
+            description: leak_c_str(param.desc, "parameter description"),
             __bindgen_anon_1: match &param.ty {
                 ParameterType::Float { min, max, default, .. }
                     => FMOD_DSP_PARAMETER_DESC__bindgen_ty_1 { floatdesc: FMOD_DSP_PARAMETER_DESC_FLOAT {
@@ -161,8 +161,7 @@ pub fn into_desc<D: Dsp>() -> FMOD_DSP_DESCRIPTION {
                     valuenames: match names {
                         None => ptr::null(),
                         Some(v) => v.iter()
-                            .map(|it| CString::from_str(it))
-                            .map(|it| it.unwrap().into_raw() as *const c_char)
+                            .map(|it| leak_c_str(it, "value name"))
                             .collect::<Vec<_>>()
                             .leak()
                             .as_ptr()
@@ -174,8 +173,7 @@ pub fn into_desc<D: Dsp>() -> FMOD_DSP_DESCRIPTION {
                         valuenames: match names {
                             None => ptr::null(),
                             Some((ff, tt)) => {
-                                [ff, tt].map(|it| CString::from_str(it))
-                                    .map(|it| it.unwrap().into_raw() as *const c_char)
+                                [ff, tt].map(|it| leak_c_str(it, "value name"))
                                     .to_vec()
                                     .leak()
                                     .as_ptr()
@@ -256,45 +254,303 @@ pub fn into_desc<D: Dsp>() -> FMOD_DSP_DESCRIPTION {
     }
 }
 
-fn sanitize_str<const N: usize>(mut s: &str) -> [c_char; N]{
-    if !s.is_ascii() {
-        eprintln!("DSP string {s} is not valid ASCII, replacing with placeholder");
-        s = "(Invalid ASCII)";
+/// Converts a string to the ASCII that FMOD can display. Accented Latin letters lose their accents,
+/// typographic punctuation becomes its plain equivalent, and anything else becomes `?`.
+///
+/// ```
+/// use scamble::dsp::interop::to_ascii;
+///
+/// assert_eq!(to_ascii("Straße – “café”"), "Strasse - \"cafe\"");
+/// assert_eq!(to_ascii("F♯ 音"), "F# ?");
+/// ```
+pub fn to_ascii(s: &str) -> Cow<'_, str> {
+    if s.is_ascii() {
+        return Cow::Borrowed(s);
+    }
+    let mut ascii = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            c if c.is_ascii() => ascii.push(c),
+            c => ascii.push_str(transliterate(c).unwrap_or("?")),
+        }
+    }
+    Cow::Owned(ascii)
+}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'À'..='Å' => "A",
+        'Æ' => "AE",
+        'Ç' => "C",
+        'È'..='Ë' => "E",
+        'Ì'..='Ï' => "I",
+        'Ð' => "D",
+        'Ñ' => "N",
+        'Ò'..='Ö' | 'Ø' => "O",
+        'Ù'..='Ü' => "U",
+        'Ý' => "Y",
+        'Þ' => "Th",
+        'ß' => "ss",
+        'à'..='å' => "a",
+        'æ' => "ae",
+        'ç' => "c",
+        'è'..='ë' => "e",
+        'ì'..='ï' => "i",
+        'ð' => "d",
+        'ñ' => "n",
+        'ò'..='ö' | 'ø' => "o",
+        'ù'..='ü' => "u",
+        'ý' | 'ÿ' => "y",
+        'þ' => "th",
+        '‘' | '’' | '′' => "'",
+        '“' | '”' | '″' => "\"",
+        '‐'..='—' | '−' => "-",
+        '…' => "...",
+        '\u{a0}' => " ",
+        '×' => "x",
+        '÷' => "/",
+        '±' => "+-",
+        '°' => "deg",
+        'µ' | 'μ' => "u",
+        '♯' => "#",
+        '♭' => "b",
+        _ => return None,
+    })
+}
+
+/// Writes a string into a fixed-size C string buffer, such as a name or value string. The string is
+/// converted with [to_ascii], ends early at any nul, and is cut off to leave room for the nul
+/// terminator. Returns whether the string was written unchanged.
+///
+/// ```
+/// use scamble::dsp::interop::write_c_str;
+///
+/// let mut buffer = [1; 8];
+/// assert!(write_c_str(&mut buffer, "Gain"));
+/// assert_eq!(buffer, [b'G', b'a', b'i', b'n', 0, 0, 0, 0].map(|it| it as _));
+/// assert!(!write_c_str(&mut buffer, "Détendre"));
+/// assert_eq!(buffer, [b'D', b'e', b't', b'e', b'n', b'd', b'r', 0].map(|it| it as _));
+/// ```
+pub fn write_c_str(target: &mut [c_char], s: &str) -> bool {
+    let Some(capacity) = target.len().checked_sub(1) else {
+        return s.is_empty();
+    };
+    let ascii = to_ascii(s);
+    let text = ascii.split('\0').next().unwrap_or_default();
+    // ASCII is a byte per character, so any length is a character boundary
+    let len = text.len().min(capacity);
+    for (out, byte) in target.iter_mut().zip(&text.as_bytes()[..len]) {
+        *out = *byte as c_char;
+    }
+    target[len..].fill(0);
+    matches!(ascii, Cow::Borrowed(_)) && len == s.len()
+}
+
+// converts a string for a fixed-size field of a description, warning if it's changed
+fn fixed_c_str<const N: usize>(s: &str, what: &str) -> [c_char; N] {
+    let mut buffer = [0; N];
+    if !write_c_str(&mut buffer, s) {
+        let shown: String = buffer.iter().take_while(|it| **it != 0).map(|it| *it as u8 as char).collect();
+        eprintln!("DSP {what} {s:?} isn't ASCII, or is longer than {} characters, so is shown as {shown:?}", N - 1);
     }
-    if s.len() > N - 1 {
-        eprintln!("DSP string {s} is longer than {} characters and will be cut off", N - 1);
-        s = &s[0..N - 1];
+    buffer
+}
+
+// converts a string for FMOD without a length limit
+fn c_string(s: &str) -> CString {
+    let ascii = to_ascii(s);
+    // nothing is left after the first nul to contain another
+    CString::new(ascii.split('\0').next().unwrap_or_default()).unwrap()
+}
+
+// converts a string that's part of a description, which lives as long as the plugin
+fn leak_c_str(s: &str, what: &str) -> *const c_char {
+    let converted = c_string(s);
+    if converted.as_bytes() != s.as_bytes() {
+        eprintln!("DSP {what} {s:?} isn't ASCII, so is shown as {converted:?}");
     }
-    let mut san = s.chars().map(|x| x as _).collect::<Vec<_>>();
-    san.resize(N, 0);
-    san.try_into().unwrap()
+    converted.into_raw()
 }
 
 static DBGSTR: &'static str = "Rust DSP\0";
 
-// SAFETY: all accesses must happen from `Dsp::read`, which executes on the mixer thread only.
-static mut CUR_STATE: *mut FMOD_DSP_STATE = ptr::null_mut();
-static mut IN_LENGTH: usize = 0;
+thread_local! {
+    // the state of the DSP whose callback is running on this thread, if any
+    static CUR_STATE: Cell<*mut FMOD_DSP_STATE> = const { Cell::new(ptr::null_mut()) };
+    // factor applied to the reported sample rate, for DSPs running at a different rate to the mixer
+    static RATE_SCALE: Cell<u32> = const { Cell::new(1) };
+    // the result reported by the parameter callback that's running on this thread, if any is running
+    static FAILURE: Cell<Option<Result<(), DspError>>> = const { Cell::new(None) };
+}
 
-/// Read a sidechain input from a DSP. Should only be called from [Dsp::read], only if a
-/// [ParameterType::Sidechain] is present and enabled, otherwise [None] will always be provided.
-pub fn with_sidechain<T>(f: impl FnOnce(Option<SignalConst>) -> T) -> T{
-    let cur_state = unsafe { CUR_STATE };
+/// Gets the sample rate of the mixer running the current DSP. Should only be called from [Dsp]
+/// methods, otherwise [None] will always be provided.
+pub fn sample_rate() -> Option<u32> {
+    let cur_state = CUR_STATE.get();
     if cur_state.is_null() {
-        f(None)
-    } else {
-        let (sidechain_ptr, sidechain_channels) = unsafe { ((*cur_state).sidechaindata, (*cur_state).sidechainchannels) };
-        let slice = unsafe { &*slice_from_raw_parts(sidechain_ptr, IN_LENGTH) };
-        f(Some(SignalConst::new(slice, sidechain_channels as usize)))
+        return None;
+    }
+    unsafe {
+        let funcs = (*cur_state).functions;
+        if funcs.is_null() {
+            return None;
+        }
+        let mut rate: c_int = 0;
+        match (*funcs).getsamplerate?(cur_state, &mut rate) {
+            FMOD_OK if rate > 0 => Some(rate as u32 * RATE_SCALE.get()),
+            _ => None,
+        }
     }
 }
 
+/// Gets the largest block of audio that the mixer running the current DSP processes at once, as a
+/// number of frames and channels. Channels default to stereo if the mixer's speaker mode is unknown.
+/// Should only be called from [Dsp] methods, otherwise [None] will always be provided.
+///
+/// Scratch buffers can reserve this much when a DSP is created or reset, so that processing doesn't
+/// allocate; see [SignalBuf::for_block](crate::dsp::signal::SignalBuf::for_block).
+pub fn max_block() -> Option<(usize, usize)> {
+    let cur_state = CUR_STATE.get();
+    if cur_state.is_null() {
+        return None;
+    }
+    unsafe {
+        let funcs = (*cur_state).functions;
+        if funcs.is_null() {
+            return None;
+        }
+        let mut frames: c_uint = 0;
+        if (*funcs).getblocksize?(cur_state, &mut frames) != FMOD_OK {
+            return None;
+        }
+        let mut mixer = FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_DEFAULT;
+        let mut output = FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_DEFAULT;
+        let channels = match (*funcs).getspeakermode {
+            Some(getspeakermode) if getspeakermode(cur_state, &mut mixer, &mut output) == FMOD_OK => ChannelLayout::from_speaker_mode(mixer),
+            _ => None,
+        };
+        Some((frames as usize * RATE_SCALE.get() as usize, channels.unwrap_or(ChannelLayout::STEREO).channels()))
+    }
+}
+
+/// Logs an error through FMOD, attributed to the current DSP. Should only be called from [Dsp]
+/// methods or parameter setters and getters, otherwise nothing will be logged.
+pub fn log_error(message: &str) {
+    unsafe { log_err(message, CUR_STATE.get()) }
+}
+
+/// Reports the result of a fallible parameter setter to FMOD. On failure, the error's message is
+/// logged, and FMOD is given its result code instead of `FMOD_OK`. Should only be called from
+/// parameter setters, otherwise errors are ignored. The first error reported by a setter is kept.
+///
+/// Setters can also be run through [Parameter::try_set](crate::dsp::Parameter::try_set), which
+/// returns the reported error.
+///
+/// ```
+/// use scamble::dsp::{DspError, Parameter, ParameterType, interop};
+///
+/// struct Sampler {
+///     voices: i32,
+/// }
+///
+/// impl Sampler {
+///     fn set_voices(&mut self, voices: i32) -> Result<(), DspError> {
+///         if voices.count_ones() != 1 {
+///             return Err(DspError::invalid_param("voices must be a power of two"));
+///         }
+///         self.voices = voices;
+///         Ok(())
+///     }
+/// }
+///
+/// let _: Parameter<Sampler> = Parameter::new("voices", ParameterType::Int {
+///     min: 1,
+///     max: 64,
+///     default: 8,
+///     max_is_inf: false,
+///     names: None,
+///     setter: |value, dsp| interop::report(dsp.set_voices(value)),
+///     getter: |dsp| dsp.voices,
+/// });
+/// ```
+pub fn report(result: Result<(), DspError>) {
+    if let Some(previous) = FAILURE.take() {
+        FAILURE.set(Some(previous.and(result)));
+    }
+}
+
+// runs `f` as a parameter callback, returning its output alongside the result it reported
+pub(crate) fn reporting<T>(f: impl FnOnce() -> T) -> (T, Result<(), DspError>) {
+    // restore the outer callback's result even if `f` panics
+    struct Restore(Option<Result<(), DspError>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FAILURE.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(FAILURE.replace(Some(Ok(()))));
+    let output = f();
+    (output, FAILURE.take().unwrap_or(Ok(())))
+}
+
+// logs a failed callback, returning its result code
+unsafe fn fail(error: &DspError, dsp_state: *mut FMOD_DSP_STATE) -> FMOD_RESULT {
+    unsafe { log_err(error.message(), dsp_state) };
+    error.code()
+}
+
+// runs a parameter setter of the DSP, returning `None` if it wasn't found, or the result it reported
+fn set_param<D: Dsp, V: ParameterVisitor<Output = bool>>(dsp_state: *mut FMOD_DSP_STATE, index: c_int, visitor: V) -> Option<FMOD_RESULT> {
+    let data = unsafe { &mut *plugin_data::<D>(dsp_state) };
+    let visit = || reporting(|| with_state(dsp_state, || data.visit_parameter(index as usize, visitor)));
+    match panic::catch_unwind(AssertUnwindSafe(visit)) {
+        Ok((Some(true), Ok(()))) => Some(FMOD_OK),
+        Ok((Some(true), Err(error))) => Some(unsafe { fail(&error, dsp_state) }),
+        Ok(_) => None,
+        Err(_) => Some(FMOD_ERR_PLUGIN),
+    }
+}
+
+// runs a getter, giving `FMOD_ERR_PLUGIN` as the error if it panics
+fn get_param<D: Dsp, V: ParameterVisitor>(dsp_state: *mut FMOD_DSP_STATE, index: c_int, visitor: V) -> Result<Option<V::Output>, FMOD_RESULT> {
+    let data = unsafe { &mut *plugin_data::<D>(dsp_state) };
+    let visit = || with_state(dsp_state, || data.visit_parameter(index as usize, visitor));
+    panic::catch_unwind(AssertUnwindSafe(visit)).map_err(|_| FMOD_ERR_PLUGIN)
+}
+
+// runs `f` with `dsp_state` as the current DSP, for callbacks that don't otherwise set it
+fn with_state<T>(dsp_state: *mut FMOD_DSP_STATE, f: impl FnOnce() -> T) -> T {
+    // restore the previous state even if `f` panics
+    struct Restore(*mut FMOD_DSP_STATE);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CUR_STATE.set(self.0);
+        }
+    }
+    let _restore = Restore(CUR_STATE.replace(dsp_state));
+    f()
+}
+
+// runs `f` with the sample rate reported by `sample_rate` multiplied by `factor`
+pub(crate) fn with_rate_scale<T>(factor: u32, f: impl FnOnce() -> T) -> T {
+    // restore the previous scale even if `f` panics
+    struct Restore(u32);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            RATE_SCALE.set(self.0);
+        }
+    }
+    let _restore = Restore(RATE_SCALE.replace(RATE_SCALE.get() * factor));
+    f()
+}
+
 unsafe fn log_err(str: &str, s: *mut FMOD_DSP_STATE) {
     if s.is_null() {
         return;
     }
     unsafe {
-        let cstr = CString::from_str(str).unwrap();
+        let cstr = c_string(str);
         let funcs = (*s).functions;
         if !funcs.is_null() {
             if let Some(log) = (*funcs).log {
@@ -320,42 +576,55 @@ unsafe fn log_err(str: &str, s: *mut FMOD_DSP_STATE) {
     }
 }*/
 
+// the plugin data FMOD holds for each instance of a DSP
+struct Instance<D> {
+    dsp: D,
+    // the index of the DSP's sidechain parameter, if it has one
+    sidechain: Option<usize>,
+    // whether FMOD enabled the sidechain parameter, so that the sidechain is passed on
+    sidechain_enabled: bool,
+}
+
+// gets the DSP of an instance made by `create_callback`
+pub(crate) unsafe fn plugin_data<D>(dsp_state: *const FMOD_DSP_STATE) -> *mut D {
+    unsafe { &raw mut (*((*dsp_state).plugindata as *mut Instance<D>)).dsp }
+}
+
 extern "C" fn create_callback<D: Dsp>(dsp_state: *mut FMOD_DSP_STATE) -> FMOD_RESULT {
-    unsafe { CUR_STATE = dsp_state; }
-    let data = D::create();
-    unsafe {
-        let mem = alloc::alloc_zeroed(Layout::new::<D>()) as *mut D;
-        ptr::write(mem, data);
-        (*dsp_state).plugindata = mem as *mut _;
-        CUR_STATE = ptr::null_mut();
+    match with_state(dsp_state, || panic::catch_unwind(D::try_create)) {
+        Ok(Ok(dsp)) => {
+            let instance = Instance {
+                dsp,
+                sidechain: D::parameters().iter().position(|it| matches!(it.ty, ParameterType::Sidechain { .. })),
+                sidechain_enabled: false,
+            };
+            unsafe { (*dsp_state).plugindata = Box::into_raw(Box::new(instance)) as *mut _ };
+            FMOD_OK
+        }
+        Ok(Err(error)) => unsafe { fail(&error, dsp_state) },
+        Err(_) => FMOD_ERR_PLUGIN,
     }
-    FMOD_OK
 }
 
 extern "C" fn release_callback<D: Dsp>(dsp_state: *mut FMOD_DSP_STATE) -> FMOD_RESULT {
-    unsafe {
-        CUR_STATE = dsp_state;
-        let x = (*dsp_state).plugindata as *mut D;
-        ptr::drop_in_place(x);
-        alloc::dealloc(x as *mut u8, Layout::new::<D>());
-        CUR_STATE = ptr::null_mut();
-    }
+    with_state(dsp_state, || unsafe { drop(Box::from_raw((*dsp_state).plugindata as *mut Instance<D>)) });
     FMOD_OK
 }
 
 extern "C" fn reset_callback<D: Dsp>(dsp_state: *mut FMOD_DSP_STATE) -> FMOD_RESULT {
     unsafe {
-        CUR_STATE = dsp_state;
+        CUR_STATE.set(dsp_state);
 
         let result = panic::catch_unwind(|| {
-            let data = &mut *((*dsp_state).plugindata as *mut D);
-            data.reset();
+            let data = &mut *plugin_data::<D>(dsp_state);
+            data.try_reset()
         });
 
-        CUR_STATE = ptr::null_mut();
+        CUR_STATE.set(ptr::null_mut());
 
-        match result{
-            Ok(_) => FMOD_OK,
+        match result {
+            Ok(Ok(())) => FMOD_OK,
+            Ok(Err(error)) => fail(&error, dsp_state),
             Err(_) => FMOD_ERR_PLUGIN,
         }
     }
@@ -370,23 +639,25 @@ extern "C" fn should_process_callback<D: Dsp>(
     _: FMOD_SPEAKERMODE,
 ) -> FMOD_RESULT {
     unsafe {
-        CUR_STATE = dsp_state;
+        CUR_STATE.set(dsp_state);
 
         let result = panic::catch_unwind(|| {
-            let data = &mut *((*dsp_state).plugindata as *mut D);
-            match data.should_process(idle != 0, length as usize) {
-                ProcessResult::Continue => FMOD_OK,
-                ProcessResult::SkipNoEffect => FMOD_ERR_DSP_DONTPROCESS,
-                ProcessResult::SkipSilent => FMOD_ERR_DSP_SILENCE,
-            }
+            let data = &mut *plugin_data::<D>(dsp_state);
+            query(data, idle != 0, length as usize)
         });
 
-        CUR_STATE = ptr::null_mut();
+        CUR_STATE.set(ptr::null_mut());
 
-        match result {
-            Ok(_) => FMOD_OK,
-            Err(_) => FMOD_ERR_PLUGIN
-        }
+        result.unwrap_or(FMOD_ERR_PLUGIN)
+    }
+}
+
+// shared by both query paths, so that FMOD sees the same result whichever it uses
+fn query<D: Dsp>(data: &mut D, idle: bool, length: usize) -> FMOD_RESULT {
+    match data.should_process(idle, length) {
+        ProcessResult::Continue => FMOD_OK,
+        ProcessResult::SkipNoEffect => FMOD_ERR_DSP_DONTPROCESS,
+        ProcessResult::SkipSilent => FMOD_ERR_DSP_SILENCE,
     }
 }
 
@@ -399,26 +670,28 @@ extern "C" fn read_callback<D: Dsp>(
     out_channels: *mut std::os::raw::c_int,
 ) -> FMOD_RESULT {
     unsafe {
-        CUR_STATE = dsp_state;
+        CUR_STATE.set(dsp_state);
 
         let result = panic::catch_unwind(|| {
-            let data = &mut *((*dsp_state).plugindata as *mut D);
+            let data = &mut *plugin_data::<D>(dsp_state);
             let in_data = if <*mut f32>::is_null(in_data) {
                 &[]
             } else {
                 &*slice_from_raw_parts_mut(in_data, length as usize)
             };
             let out_data = &mut *slice_from_raw_parts_mut(out_data, length as usize);
-            data.read(
+            data.try_read(
                 SignalConst::new(in_data, in_channels as usize),
+                None,
                 SignalMut::new(out_data, *out_channels as usize),
-            );
+            )
         });
 
-        CUR_STATE = ptr::null_mut();
+        CUR_STATE.set(ptr::null_mut());
 
         match result {
-            Ok(_) => FMOD_OK,
+            Ok(Ok(())) => FMOD_OK,
+            Ok(Err(error)) => fail(&error, dsp_state),
             Err(_) => FMOD_ERR_PLUGIN
         }
     }
@@ -433,47 +706,49 @@ extern "C" fn process_callback<D: Dsp>(
     op: FMOD_DSP_PROCESS_OPERATION,
 ) -> FMOD_RESULT {
     unsafe {
-        CUR_STATE = dsp_state;
-        IN_LENGTH = length as usize;
+        CUR_STATE.set(dsp_state);
 
         let proc = panic::catch_unwind(|| {
-            let data = &mut *((*dsp_state).plugindata as *mut D);
+            let data = &mut *plugin_data::<D>(dsp_state);
             if op == FMOD_DSP_PROCESS_OPERATION::FMOD_DSP_PROCESS_QUERY {
                 if !out_buffers.is_null() {
-                    (*out_buffers).speakermode = FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_STEREO;
                     *(*out_buffers).bufferchannelmask = 0;
                     if let Some(channels) = data.preferred_out_channels() {
                         *(*out_buffers).buffernumchannels = channels as c_int;
                     }
+                    let channels = (*(*out_buffers).buffernumchannels).max(0) as usize;
+                    (*out_buffers).speakermode = ChannelLayout::from_channels(channels).speaker_mode();
                 }
-                match D::ty() {
-                    DspType::Generator => FMOD_OK,
-                    DspType::Effect => match data.should_process(idle != 0, length as usize) {
-                        ProcessResult::Continue => FMOD_OK,
-                        ProcessResult::SkipNoEffect => FMOD_ERR_DSP_DONTPROCESS,
-                        ProcessResult::SkipSilent => FMOD_ERR_DSP_SILENCE,
-                    },
-                }
+                query(data, idle != 0, length as usize)
             } else {
                 let out_chan = (*(*out_buffers).buffernumchannels) as usize;
-                let (in_chan, in_data) = if !in_buffers.is_null() {
+                let input = if !in_buffers.is_null() {
                     let in_chan = (*(*in_buffers).buffernumchannels) as usize;
                     let in_data = &*slice_from_raw_parts_mut(*(*in_buffers).buffers, length as usize * in_chan);
-                    (in_chan, in_data)
+                    let in_layout = ChannelLayout::from_buffer((*in_buffers).speakermode, *(*in_buffers).bufferchannelmask, in_chan);
+                    SignalConst::new(in_data, in_chan).with_layout(in_layout)
                 } else {
-                    (0, &[] as &[f32])
+                    SignalConst::new(&[], 0)
                 };
                 let out_data = &mut *slice_from_raw_parts_mut(*(*out_buffers).buffers, length as usize * out_chan);
-                data.read(
-                    SignalConst::new(in_data, in_chan),
-                    SignalMut::new(out_data, out_chan),
-                );
-                FMOD_OK
+                let out_layout = ChannelLayout::from_buffer((*out_buffers).speakermode, *(*out_buffers).bufferchannelmask, out_chan);
+                // the sidechain is interleaved like any other buffer, and only passed on while the
+                // DSP's sidechain parameter is enabled
+                let (side_ptr, side_chan) = ((*dsp_state).sidechaindata, (*dsp_state).sidechainchannels.max(0) as usize);
+                let enabled = (*((*dsp_state).plugindata as *const Instance<D>)).sidechain_enabled;
+                let sidechain = if enabled && !side_ptr.is_null() && side_chan > 0 {
+                    Some(SignalConst::new(&*slice_from_raw_parts(side_ptr, length as usize * side_chan), side_chan))
+                } else {
+                    None
+                };
+                match data.try_read(input, sidechain, SignalMut::new(out_data, out_chan).with_layout(out_layout)) {
+                    Ok(()) => FMOD_OK,
+                    Err(error) => fail(&error, dsp_state),
+                }
             }
         });
 
-        CUR_STATE = ptr::null_mut();
-        IN_LENGTH = 0;
+        CUR_STATE.set(ptr::null_mut());
 
         proc.unwrap_or_else(|_| {
             FMOD_ERR_PLUGIN
@@ -486,16 +761,10 @@ extern "C" fn set_param_float_callback<D: Dsp>(
     index: c_int,
     value: f32
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Float { setter, .. } = param.ty {
-            setter(value, data);
-            return FMOD_OK;
-        }
+    if let Some(result) = set_param::<D, _>(dsp_state, index, SetFloat(value)) {
+        return result;
     }
-    unsafe { log_err(&format!("Failed to set float parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to set float parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -503,20 +772,19 @@ extern "C" fn get_param_float_callback<D: Dsp>(
     dsp_state: *mut FMOD_DSP_STATE,
     index: c_int,
     value: *mut f32,
-    _: *mut c_char // valuestr is not currently exposed
+    valuestr: *mut c_char
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Float { getter, .. } = param.ty {
+    match get_param::<D, _>(dsp_state, index, GetFloat(valuestr)) {
+        Ok(Some(Some(v))) => {
             unsafe {
-                *value = getter(&*data);
-                return FMOD_OK;
+                *value = v;
             }
+            return FMOD_OK;
         }
+        Err(result) => return result,
+        Ok(_) => {}
     }
-    unsafe { log_err(&format!("Failed to get float parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to get float parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -525,16 +793,10 @@ extern "C" fn set_param_int_callback<D: Dsp>(
     index: c_int,
     value: i32
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Int { setter, .. } = param.ty {
-            setter(value, data);
-            return FMOD_OK;
-        }
+    if let Some(result) = set_param::<D, _>(dsp_state, index, SetInt(value)) {
+        return result;
     }
-    unsafe { log_err(&format!("Failed to set int parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to set int parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -542,20 +804,19 @@ extern "C" fn get_param_int_callback<D: Dsp>(
     dsp_state: *mut FMOD_DSP_STATE,
     index: c_int,
     value: *mut i32,
-    _: *mut c_char // valuestr is not currently exposed
+    valuestr: *mut c_char
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Int { getter, .. } = param.ty {
+    match get_param::<D, _>(dsp_state, index, GetInt(valuestr)) {
+        Ok(Some(Some(v))) => {
             unsafe {
-                *value = getter(&*data);
-                return FMOD_OK;
+                *value = v;
             }
+            return FMOD_OK;
         }
+        Err(result) => return result,
+        Ok(_) => {}
     }
-    unsafe { log_err(&format!("Failed to get int parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to get int parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -564,16 +825,10 @@ extern "C" fn set_param_bool_callback<D: Dsp>(
     index: c_int,
     value: FMOD_BOOL
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Bool { setter, .. } = param.ty {
-            setter(value == 1, data);
-            return FMOD_OK;
-        }
+    if let Some(result) = set_param::<D, _>(dsp_state, index, SetBool(value == 1)) {
+        return result;
     }
-    unsafe { log_err(&format!("Failed to set boolean parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to set boolean parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -581,20 +836,19 @@ extern "C" fn get_param_bool_callback<D: Dsp>(
     dsp_state: *mut FMOD_DSP_STATE,
     index: c_int,
     value: *mut FMOD_BOOL,
-    _: *mut c_char // valuestr is not currently exposed
+    valuestr: *mut c_char
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Bool { getter, .. } = param.ty {
+    match get_param::<D, _>(dsp_state, index, GetBool(valuestr)) {
+        Ok(Some(Some(v))) => {
             unsafe {
-                *value = if getter(&*data) { 1 } else { 0 };
-                return FMOD_OK;
+                *value = if v { 1 } else { 0 };
             }
+            return FMOD_OK;
         }
+        Err(result) => return result,
+        Ok(_) => {}
     }
-    unsafe { log_err(&format!("Failed to get boolean parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to get boolean parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -604,26 +858,14 @@ extern "C" fn set_param_data_callback<D: Dsp>(
     value: *mut c_void,
     length: c_uint
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Data { setter, .. } = param.ty {
-            unsafe {
-                let slc = &*slice_from_raw_parts(value as *mut u8, length as usize);
-                setter(slc, data);
-            }
-            return FMOD_OK;
-        }
-        if let ParameterType::Sidechain { setter, .. } = param.ty {
-            unsafe {
-                let i = (*(value as *mut FMOD_DSP_PARAMETER_SIDECHAIN)).sidechainenable;
-                setter(i == 1, data);
-            }
-            return FMOD_OK;
+    if let Some(result) = set_param::<D, _>(dsp_state, index, SetData { value, length }) {
+        let instance = unsafe { &mut *((*dsp_state).plugindata as *mut Instance<D>) };
+        if result == FMOD_OK && instance.sidechain == Some(index as usize) {
+            instance.sidechain_enabled = unsafe { (*(value as *const FMOD_DSP_PARAMETER_SIDECHAIN)).sidechainenable } == 1;
         }
+        return result;
     }
-    unsafe { log_err(&format!("Failed to set data parameter at index {index} (of {})", params.len()), dsp_state); }
+    unsafe { log_err(&format!("Failed to set data parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
     FMOD_ERR_INVALID_PARAM
 }
 
@@ -634,39 +876,176 @@ extern "C" fn get_param_data_callback<D: Dsp>(
     length: *mut c_uint,
     desc: *mut c_char
 ) -> FMOD_RESULT {
-    let data = unsafe { &mut *((*dsp_state).plugindata as *mut D) };
-    let params = D::parameters();
-    if (index as usize) < params.len() {
-        let param = &params[index as usize];
-        if let ParameterType::Data { getter, .. } = param.ty {
-            let result = getter(&*data);
-            if let Some((c_value, c_desc)) = result {
+    match get_param::<D, _>(dsp_state, index, GetData { value, length, desc }) {
+        Ok(Some(true)) => return FMOD_OK,
+        Err(result) => return result,
+        Ok(_) => {}
+    }
+    unsafe { log_err(&format!("Failed to get data parameter at index {index} (of {})", D::parameter_count()), dsp_state); }
+    FMOD_ERR_INVALID_PARAM
+}
+
+// parameter accesses, which may reach the parameters of wrapped DSPs
+
+struct SetFloat(f32);
+
+impl ParameterVisitor for SetFloat {
+    type Output = bool;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> bool {
+        if let ParameterType::Float { setter, .. } = parameter.ty {
+            setter(self.0, dsp);
+            return true;
+        }
+        false
+    }
+}
+
+// writes the text shown for a parameter's value, if FMOD asked for it
+fn write_value_str(valuestr: *mut c_char, text: &str) {
+    if !valuestr.is_null() {
+        let target = unsafe { &mut *slice_from_raw_parts_mut(valuestr, FMOD_DSP_GETPARAM_VALUESTR_LENGTH as usize) };
+        write_c_str(target, text);
+    }
+}
+
+struct GetFloat(*mut c_char);
+
+impl ParameterVisitor for GetFloat {
+    type Output = Option<f32>;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> Option<f32> {
+        if let ParameterType::Float { getter, .. } = parameter.ty {
+            let value = getter(dsp);
+            write_value_str(self.0, &format!("{value:.2}"));
+            return Some(value);
+        }
+        None
+    }
+}
+
+struct SetInt(i32);
+
+impl ParameterVisitor for SetInt {
+    type Output = bool;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> bool {
+        if let ParameterType::Int { setter, .. } = parameter.ty {
+            setter(self.0, dsp);
+            return true;
+        }
+        false
+    }
+}
+
+struct GetInt(*mut c_char);
+
+impl ParameterVisitor for GetInt {
+    type Output = Option<i32>;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> Option<i32> {
+        if let ParameterType::Int { getter, min, ref names, .. } = parameter.ty {
+            let value = getter(dsp);
+            match names.as_ref().and_then(|it| it.get(value.wrapping_sub(min) as usize)) {
+                Some(name) => write_value_str(self.0, name),
+                None => write_value_str(self.0, &value.to_string()),
+            }
+            return Some(value);
+        }
+        None
+    }
+}
+
+struct SetBool(bool);
+
+impl ParameterVisitor for SetBool {
+    type Output = bool;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> bool {
+        if let ParameterType::Bool { setter, .. } = parameter.ty {
+            setter(self.0, dsp);
+            return true;
+        }
+        false
+    }
+}
+
+struct GetBool(*mut c_char);
+
+impl ParameterVisitor for GetBool {
+    type Output = Option<bool>;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> Option<bool> {
+        if let ParameterType::Bool { getter, names, .. } = parameter.ty {
+            let value = getter(dsp);
+            // names are given to FMOD in the order of their values
+            let (off, on) = names.unwrap_or(("Off", "On"));
+            write_value_str(self.0, if value { on } else { off });
+            return Some(value);
+        }
+        None
+    }
+}
+
+struct SetData {
+    value: *mut c_void,
+    length: c_uint,
+}
+
+impl ParameterVisitor for SetData {
+    type Output = bool;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> bool {
+        if let ParameterType::Data { setter, .. } = parameter.ty {
+            unsafe {
+                let slc = &*slice_from_raw_parts(self.value as *mut u8, self.length as usize);
+                setter(slc, dsp);
+            }
+            return true;
+        }
+        if let ParameterType::Sidechain { setter, .. } = parameter.ty {
+            unsafe {
+                let i = (*(self.value as *mut FMOD_DSP_PARAMETER_SIDECHAIN)).sidechainenable;
+                setter(i == 1, dsp);
+            }
+            return true;
+        }
+        false
+    }
+}
+
+struct GetData {
+    value: *mut *mut c_void,
+    length: *mut c_uint,
+    desc: *mut c_char,
+}
+
+impl ParameterVisitor for GetData {
+    type Output = bool;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> bool {
+        if let ParameterType::Data { getter, .. } = parameter.ty {
+            let result = getter(dsp);
+            write_value_str(self.desc, result.and_then(|(_, desc)| desc).unwrap_or_default());
+            if let Some((c_value, _)) = result {
                 unsafe {
-                    let target = &mut *slice_from_raw_parts_mut(desc, 32);
-                    if let Some(c_desc) = c_desc {
-                        for (i, c) in c_desc[..31].chars().enumerate() {
-                            target[i] = c as c_char;
-                        }
-                    }
-                    *value = c_value.as_ptr() as *mut _;
-                    *length = c_value.len() as c_uint;
+                    *self.value = c_value.as_ptr() as *mut _;
+                    *self.length = c_value.len() as c_uint;
                 }
-                return FMOD_OK;
             } else {
                 unsafe {
-                    *value = ptr::null_mut();
-                    *length = 0;
+                    *self.value = ptr::null_mut();
+                    *self.length = 0;
                 }
-                return FMOD_OK;
             }
+            return true;
         }
-        if let ParameterType::Sidechain { getter, .. } = param.ty {
+        if let ParameterType::Sidechain { getter, .. } = parameter.ty {
             unsafe {
-                (*(value as *mut FMOD_DSP_PARAMETER_SIDECHAIN)).sidechainenable = if getter(data) { 1 } else { 0 };
-                return FMOD_OK;
+                (*(self.value as *mut FMOD_DSP_PARAMETER_SIDECHAIN)).sidechainenable = if getter(dsp) { 1 } else { 0 };
             }
+            return true;
         }
+        false
     }
-    unsafe { log_err(&format!("Failed to get data parameter at index {index} (of {})", params.len()), dsp_state); }
-    FMOD_ERR_INVALID_PARAM
-}
\ No newline at end of file
+}
@@ -0,0 +1,440 @@
+//! Speaker layouts, and mixing signals between them.
+//!
+//! Downmixing follows ITU-R BS.775: centre and surround speakers are folded into the front pair
+//! at -3dB, and the LFE channel is dropped. How speakers missing from a source are filled when
+//! upmixing is configured by [Upmix].
+//!
+//! ```
+//! use scamble::dsp::layout::{ChannelLayout, Upmix};
+//! use scamble::dsp::signal::{Signal, SignalConst};
+//!
+//! // a single frame of 5.1 audio, with only the centre speaker playing
+//! let frame = [0., 0., 1., 0., 0., 0.];
+//! let surround = SignalConst::new(&frame, 6).with_layout(ChannelLayout::FIVE_POINT_ONE);
+//! let stereo: Vec<f32> = surround.mix_to(ChannelLayout::STEREO, Upmix::default()).collect();
+//! assert_eq!(stereo, [std::f32::consts::FRAC_1_SQRT_2; 2]);
+//! ```
+
+use crate::raw_bindings::FMOD_SPEAKERMODE;
+use crate::raw_bindings::{FMOD_CHANNELMASK, FMOD_MAX_CHANNEL_WIDTH};
+use std::f32::consts::FRAC_1_SQRT_2;
+
+/// The maximum number of channels in a layout.
+pub const MAX_CHANNELS: usize = FMOD_MAX_CHANNEL_WIDTH as usize;
+
+/// A speaker position. Channels of a layout are interleaved in the order listed here.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    SurroundLeft,
+    SurroundRight,
+    BackLeft,
+    BackRight,
+    TopFrontLeft,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackRight,
+    BackCenter,
+}
+
+const SPEAKERS: [Speaker; 13] = [
+    Speaker::FrontLeft,
+    Speaker::FrontRight,
+    Speaker::FrontCenter,
+    Speaker::LowFrequency,
+    Speaker::SurroundLeft,
+    Speaker::SurroundRight,
+    Speaker::BackLeft,
+    Speaker::BackRight,
+    Speaker::TopFrontLeft,
+    Speaker::TopFrontRight,
+    Speaker::TopBackLeft,
+    Speaker::TopBackRight,
+    Speaker::BackCenter,
+];
+
+impl Speaker {
+    const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    // where this speaker is mixed to if the target layout doesn't have it, and at what gain
+    fn fold(self) -> (&'static [Speaker], f32) {
+        use Speaker::*;
+        match self {
+            // only reached for mono targets, which take the mean of the front pair
+            FrontLeft | FrontRight => (&[FrontCenter], 0.5),
+            FrontCenter => (&[FrontLeft, FrontRight], FRAC_1_SQRT_2),
+            LowFrequency => (&[], 0.),
+            SurroundLeft => (&[FrontLeft], FRAC_1_SQRT_2),
+            SurroundRight => (&[FrontRight], FRAC_1_SQRT_2),
+            BackLeft => (&[SurroundLeft], 1.),
+            BackRight => (&[SurroundRight], 1.),
+            TopFrontLeft => (&[FrontLeft], FRAC_1_SQRT_2),
+            TopFrontRight => (&[FrontRight], FRAC_1_SQRT_2),
+            TopBackLeft => (&[BackLeft], FRAC_1_SQRT_2),
+            TopBackRight => (&[BackRight], FRAC_1_SQRT_2),
+            BackCenter => (&[BackLeft, BackRight], FRAC_1_SQRT_2),
+        }
+    }
+}
+
+/// The speaker positions of each channel of a signal.
+///
+/// Layouts may have more channels than speakers, in which case the remaining channels have no
+/// position. These are only mixed to unpositioned channels of the same index.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ChannelLayout {
+    // one bit for each speaker present
+    speakers: u16,
+    channels: usize,
+}
+
+impl ChannelLayout {
+    /// A single centre speaker.
+    pub const MONO: ChannelLayout = ChannelLayout::of(&[Speaker::FrontCenter]);
+    /// Front left and right speakers.
+    pub const STEREO: ChannelLayout = ChannelLayout::of(&[Speaker::FrontLeft, Speaker::FrontRight]);
+    /// Front and surround left and right speakers.
+    pub const QUAD: ChannelLayout = ChannelLayout::of(&[Speaker::FrontLeft, Speaker::FrontRight, Speaker::SurroundLeft, Speaker::SurroundRight]);
+    /// Quad with a centre speaker.
+    pub const SURROUND: ChannelLayout = ChannelLayout::of(&[
+        Speaker::FrontLeft,
+        Speaker::FrontRight,
+        Speaker::FrontCenter,
+        Speaker::SurroundLeft,
+        Speaker::SurroundRight,
+    ]);
+    /// Surround with an LFE channel.
+    pub const FIVE_POINT_ONE: ChannelLayout = ChannelLayout::of(&[
+        Speaker::FrontLeft,
+        Speaker::FrontRight,
+        Speaker::FrontCenter,
+        Speaker::LowFrequency,
+        Speaker::SurroundLeft,
+        Speaker::SurroundRight,
+    ]);
+    /// 5.1 with back left and right speakers.
+    pub const SEVEN_POINT_ONE: ChannelLayout = ChannelLayout::of(&[
+        Speaker::FrontLeft,
+        Speaker::FrontRight,
+        Speaker::FrontCenter,
+        Speaker::LowFrequency,
+        Speaker::SurroundLeft,
+        Speaker::SurroundRight,
+        Speaker::BackLeft,
+        Speaker::BackRight,
+    ]);
+    /// 7.1 with four height speakers.
+    pub const SEVEN_POINT_ONE_POINT_FOUR: ChannelLayout = ChannelLayout::of(&[
+        Speaker::FrontLeft,
+        Speaker::FrontRight,
+        Speaker::FrontCenter,
+        Speaker::LowFrequency,
+        Speaker::SurroundLeft,
+        Speaker::SurroundRight,
+        Speaker::BackLeft,
+        Speaker::BackRight,
+        Speaker::TopFrontLeft,
+        Speaker::TopFrontRight,
+        Speaker::TopBackLeft,
+        Speaker::TopBackRight,
+    ]);
+
+    /// Creates a layout with one channel for each of the given speakers.
+    /// The channels are always ordered as in [Speaker], regardless of the order given.
+    pub const fn of(speakers: &[Speaker]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < speakers.len() {
+            bits |= speakers[i].bit();
+            i += 1;
+        }
+        ChannelLayout {
+            speakers: bits,
+            channels: bits.count_ones() as usize,
+        }
+    }
+
+    /// Creates a layout of channels without speaker positions.
+    pub const fn raw(channels: usize) -> Self {
+        ChannelLayout { speakers: 0, channels }
+    }
+
+    /// Guesses a layout from a channel count, using FMOD's speaker mode for that many channels.
+    pub fn from_channels(channels: usize) -> Self {
+        match channels {
+            1 => Self::MONO,
+            2 => Self::STEREO,
+            4 => Self::QUAD,
+            5 => Self::SURROUND,
+            6 => Self::FIVE_POINT_ONE,
+            8 => Self::SEVEN_POINT_ONE,
+            12 => Self::SEVEN_POINT_ONE_POINT_FOUR,
+            _ => Self::raw(channels),
+        }
+    }
+
+    /// Gets the layout of an FMOD speaker mode, if it has fixed speaker positions.
+    pub fn from_speaker_mode(mode: FMOD_SPEAKERMODE) -> Option<Self> {
+        match mode {
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_MONO => Some(Self::MONO),
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_STEREO => Some(Self::STEREO),
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_QUAD => Some(Self::QUAD),
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_SURROUND => Some(Self::SURROUND),
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_5POINT1 => Some(Self::FIVE_POINT_ONE),
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_7POINT1 => Some(Self::SEVEN_POINT_ONE),
+            FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_7POINT1POINT4 => Some(Self::SEVEN_POINT_ONE_POINT_FOUR),
+            _ => None,
+        }
+    }
+
+    /// Gets the layout of an FMOD channel mask for a given number of channels. If the mask has
+    /// more speakers than channels, the layout is guessed from the channel count instead.
+    pub fn from_channel_mask(mask: FMOD_CHANNELMASK, channels: usize) -> Self {
+        // FMOD's mask matches the order of `Speaker`, except for having no height speakers
+        let mut speakers = (mask & 0xff) as u16;
+        if mask & 0x100 != 0 {
+            speakers |= Speaker::BackCenter.bit();
+        }
+        match speakers.count_ones() as usize {
+            // FMOD marks mono as front left
+            1 if channels == 1 => Self::MONO,
+            count if count <= channels => ChannelLayout { speakers, channels },
+            _ => Self::from_channels(channels),
+        }
+    }
+
+    // gets the layout of a buffer passed by FMOD, preferring its channel mask if it has one
+    pub(crate) fn from_buffer(mode: FMOD_SPEAKERMODE, mask: FMOD_CHANNELMASK, channels: usize) -> Self {
+        if mask != 0 {
+            return Self::from_channel_mask(mask, channels);
+        }
+        match Self::from_speaker_mode(mode) {
+            Some(layout) if layout.channels == channels => layout,
+            _ => Self::from_channels(channels),
+        }
+    }
+
+    /// Gets the FMOD speaker mode with this layout, or [FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_RAW]
+    /// if there is none.
+    pub fn speaker_mode(&self) -> FMOD_SPEAKERMODE {
+        match *self {
+            Self::MONO => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_MONO,
+            Self::STEREO => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_STEREO,
+            Self::QUAD => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_QUAD,
+            Self::SURROUND => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_SURROUND,
+            Self::FIVE_POINT_ONE => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_5POINT1,
+            Self::SEVEN_POINT_ONE => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_7POINT1,
+            Self::SEVEN_POINT_ONE_POINT_FOUR => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_7POINT1POINT4,
+            _ => FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_RAW,
+        }
+    }
+
+    /// Gets the number of channels in this layout.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Checks whether this layout has a channel for a speaker.
+    pub fn contains(&self, speaker: Speaker) -> bool {
+        self.speakers & speaker.bit() != 0
+    }
+
+    /// Gets the index of the channel for a speaker, if this layout has one.
+    pub fn position(&self, speaker: Speaker) -> Option<usize> {
+        self.contains(speaker).then(|| (self.speakers & (speaker.bit() - 1)).count_ones() as usize)
+    }
+
+    /// Gets the speaker of a channel, or `None` if it has no position.
+    pub fn speaker(&self, channel: usize) -> Option<Speaker> {
+        SPEAKERS.iter().filter(|it| self.contains(**it)).nth(channel).copied()
+    }
+}
+
+/// How speakers missing from a source are filled when mixing to a layout with more speakers.
+///
+/// Each gain is applied to every source channel contributing to a missing speaker.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Upmix {
+    /// The gain of a mono source on each front speaker, when mixing to a layout without a centre.
+    pub mono: f32,
+    /// The gain of each front speaker on a missing centre speaker.
+    pub center: f32,
+    /// The gain of each front speaker on the surround and back speakers of the same side, when
+    /// the source has none.
+    pub rear: f32,
+}
+
+impl Upmix {
+    /// Only speakers in both layouts are used, and mono sources are panned to the centre at -3dB.
+    pub const DISCRETE: Upmix = Upmix {
+        mono: FRAC_1_SQRT_2,
+        center: 0.,
+        rear: 0.,
+    };
+    /// Like [Upmix::DISCRETE], but mono sources are copied to both front speakers at full level.
+    /// This is the default.
+    pub const DUPLICATE: Upmix = Upmix { mono: 1., ..Self::DISCRETE };
+    /// Like [Upmix::DUPLICATE], but also fills the centre and rear speakers from the front.
+    pub const SPREAD: Upmix = Upmix {
+        mono: 1.,
+        center: 0.5,
+        rear: FRAC_1_SQRT_2,
+    };
+}
+
+impl Default for Upmix {
+    fn default() -> Self {
+        Self::DUPLICATE
+    }
+}
+
+/// The gains from each channel of one layout to each channel of another.
+#[derive(Clone, Debug)]
+pub struct MixMatrix {
+    from: usize,
+    to: usize,
+    // indexed by output channel, then input channel
+    gains: [[f32; MAX_CHANNELS]; MAX_CHANNELS],
+}
+
+impl MixMatrix {
+    /// Computes the gains for mixing between two layouts.
+    /// Panics if either layout has more than [MAX_CHANNELS] channels.
+    pub fn new(from: ChannelLayout, to: ChannelLayout, upmix: Upmix) -> Self {
+        assert!(
+            from.channels <= MAX_CHANNELS && to.channels <= MAX_CHANNELS,
+            "Layouts can have at most {MAX_CHANNELS} channels"
+        );
+        let mut matrix = MixMatrix {
+            from: from.channels,
+            to: to.channels,
+            gains: [[0.; MAX_CHANNELS]; MAX_CHANNELS],
+        };
+
+        // without speaker positions on either side, there's nothing to go on but the channel order
+        if from.speakers == 0 || to.speakers == 0 {
+            for c in 0..from.channels.min(to.channels) {
+                matrix.gains[c][c] = 1.;
+            }
+            return matrix;
+        }
+
+        for input in 0..from.channels {
+            match from.speaker(input) {
+                Some(Speaker::FrontCenter) if from == ChannelLayout::MONO && !to.contains(Speaker::FrontCenter) => {
+                    for speaker in [Speaker::FrontLeft, Speaker::FrontRight] {
+                        matrix.route(to, input, speaker, upmix.mono, 0);
+                    }
+                }
+                Some(speaker) => matrix.route(to, input, speaker, 1., 0),
+                None if to.speaker(input).is_none() && input < to.channels => matrix.gains[input][input] = 1.,
+                None => {}
+            }
+        }
+
+        // upmixing only applies if the front speakers weren't themselves folded away
+        if to.contains(Speaker::FrontLeft) && to.contains(Speaker::FrontRight) {
+            let mut fill = |source: Speaker, target: Speaker, gain: f32| {
+                if let (Some(input), Some(out)) = (from.position(source), to.position(target)) {
+                    matrix.gains[out][input] += gain;
+                }
+            };
+            if !from.contains(Speaker::FrontCenter) {
+                fill(Speaker::FrontLeft, Speaker::FrontCenter, upmix.center);
+                fill(Speaker::FrontRight, Speaker::FrontCenter, upmix.center);
+            }
+            let rears = [Speaker::SurroundLeft, Speaker::SurroundRight, Speaker::BackLeft, Speaker::BackRight];
+            if !rears.iter().any(|it| from.contains(*it)) {
+                fill(Speaker::FrontLeft, Speaker::SurroundLeft, upmix.rear);
+                fill(Speaker::FrontRight, Speaker::SurroundRight, upmix.rear);
+                fill(Speaker::FrontLeft, Speaker::BackLeft, upmix.rear);
+                fill(Speaker::FrontRight, Speaker::BackRight, upmix.rear);
+            }
+        }
+        matrix
+    }
+
+    // mixes an input channel to a speaker, folding it into others if the target doesn't have it
+    fn route(&mut self, to: ChannelLayout, input: usize, speaker: Speaker, gain: f32, depth: usize) {
+        if let Some(out) = to.position(speaker) {
+            self.gains[out][input] += gain;
+            return;
+        }
+        // folding only goes in circles if the target has no front speakers
+        if depth > 4 {
+            return;
+        }
+        let (targets, fold) = speaker.fold();
+        for target in targets {
+            self.route(to, input, *target, gain * fold, depth + 1);
+        }
+    }
+
+    /// Gets the gain from an input channel to an output channel.
+    pub fn gain(&self, output: usize, input: usize) -> f32 {
+        self.gains[output][input]
+    }
+
+    /// Mixes a single frame of input into a frame of output.
+    pub fn mix_frame(&self, input: &[f32], output: &mut [f32]) {
+        for (out, gains) in output.iter_mut().zip(&self.gains) {
+            *out = dot(&gains[..self.from], input);
+        }
+    }
+
+    // the gains onto a single output channel
+    pub(crate) fn row(&self, output: usize) -> [f32; MAX_CHANNELS] {
+        self.gains[output]
+    }
+}
+
+/// Interleaved samples mixed to another layout, returned by
+/// [Signal::mix_to](crate::dsp::signal::Signal::mix_to).
+pub struct Mixed<'a> {
+    samples: &'a [f32],
+    matrix: MixMatrix,
+    // index of the next output sample
+    pos: usize,
+}
+
+impl<'a> Mixed<'a> {
+    pub(crate) fn new(samples: &'a [f32], matrix: MixMatrix) -> Self {
+        Mixed { samples, matrix, pos: 0 }
+    }
+
+    fn len(&self) -> usize {
+        match self.matrix.from {
+            0 => 0,
+            from => self.samples.len() / from * self.matrix.to - self.pos,
+        }
+    }
+}
+
+impl Iterator for Mixed<'_> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.len() == 0 {
+            return None;
+        }
+        let (from, to) = (self.matrix.from, self.matrix.to);
+        let (frame, out) = (self.pos / to, self.pos % to);
+        self.pos += 1;
+        Some(dot(&self.matrix.gains[out][..from], &self.samples[frame * from..(frame + 1) * from]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl ExactSizeIterator for Mixed<'_> {}
+
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
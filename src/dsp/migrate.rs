@@ -0,0 +1,683 @@
+//! Tools for evolving a DSP's parameters between versions.
+//!
+//! Parameter data is saved by index, and integer parameters by value, so reordering, renaming or
+//! removing parameters (or reordering the options of an [crate::enum_param]) breaks data saved by
+//! older versions. A DSP can describe how its parameters changed with [Dsp::migrations], which
+//! brings data that records its version, such as a [Preset], up to date through [migrate_index],
+//! [migrate_name] and [migrate_value].
+//!
+//! FMOD does not tell a plugin which version built a bank, so these changes remain breaking for
+//! FMOD Studio banks. [ParameterTable::diff] flags them; keeping a snapshot of each released
+//! version's table allows checking for them in a test:
+//!
+//! ```
+//! use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+//! use scamble::dsp::migrate::{Migration, ParameterTable};
+//! use scamble::dsp::signal::{SignalConst, SignalMut};
+//! use scamble::float_param;
+//!
+//! struct Gain {
+//!     gain: f32
+//! }
+//!
+//! impl Dsp for Gain {
+//!     fn name() -> &'static str { "Gain" }
+//!     fn version() -> u32 { 2 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!
+//!     fn parameters() -> Vec<Parameter<Self>> {
+//!         vec![Parameter::new("gain", float_param!(gain, range: 0.0..2.0, default: 1.0))]
+//!     }
+//!
+//!     fn migrations() -> Vec<Migration> {
+//!         // version 1 called it "volume"
+//!         vec![Migration::new(1, &["volume"]).rename("volume", "gain")]
+//!     }
+//!
+//!     fn create() -> Self { Gain { gain: 1.0 } }
+//!     fn read(&mut self, _: SignalConst, _: SignalMut) {}
+//! }
+//!
+//! let v1: ParameterTable = "version\t1\nfloat\tvolume\t0\t2\t1\n".parse().unwrap();
+//! let changes = v1.diff(&ParameterTable::of::<Gain>(), &Gain::migrations());
+//! // the rename breaks banks, but versioned data is migrated
+//! assert!(changes.iter().all(|it| it.breaking && it.migrated));
+//! ```
+
+use crate::dsp::{Dsp, Parameter, ParameterType, ParameterValue, ParameterVisitor};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A description of how a DSP's parameters changed since a previous version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    version: u32,
+    names: Vec<String>,
+    renames: Vec<(String, String)>,
+    ordinals: Vec<(String, Vec<(i32, i32)>)>,
+    stage: Stage,
+}
+
+// where the parameters of a migration live in a combined DSP, if it was carried over from one of
+// its stages; empty otherwise
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Stage {
+    // for each pairing of versions from the outermost, whether the stage's version is the second
+    path: Vec<bool>,
+    // the prefix of the stage's parameter names
+    prefix: String,
+}
+
+impl Migration {
+    /// Creates a migration for data saved by `version`, which had parameters named `names`, in order.
+    ///
+    /// Versions between this one and the next migration are assumed to have the same parameters.
+    /// Data saved by older versions passes through their own migrations first.
+    pub fn new(version: u32, names: &[&str]) -> Self {
+        Migration {
+            version,
+            names: names.iter().map(|it| it.to_string()).collect(),
+            renames: vec![],
+            ordinals: vec![],
+            stage: Stage::default(),
+        }
+    }
+
+    /// Records that the parameter `old` was renamed to `new`. Parameters that don't exist in the
+    /// next version, under their old name or a new one, are considered removed.
+    pub fn rename(mut self, old: &str, new: &str) -> Self {
+        self.renames.push((old.to_string(), new.to_string()));
+        self
+    }
+
+    /// Records that the values of the integer parameter `name` (as named in the old version) were
+    /// reordered, as a list of `(old, new)` pairs. Values not present in the list are kept as-is.
+    pub fn remap_ordinals(mut self, name: &str, map: &[(i32, i32)]) -> Self {
+        self.ordinals.push((name.to_string(), map.to_vec()));
+        self
+    }
+
+    /// Gets the version this migration upgrades data from. For a migration carried over from a stage
+    /// of a [combined](crate::dsp::compose) DSP, this is the version of the stage.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Gets the names of the parameters of the version this migration upgrades data from, in order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    // carries this migration over to a stage of a combined DSP, whose version is the first or
+    // second of the pair given by `pair_versions`, and whose parameter names start with `prefix`
+    pub(crate) fn staged(mut self, second: bool, prefix: &str) -> Self {
+        let names = self.names.iter_mut();
+        let renames = self.renames.iter_mut().flat_map(|(old, new)| [old, new]);
+        let ordinals = self.ordinals.iter_mut().map(|(name, _)| name);
+        for name in names.chain(renames).chain(ordinals) {
+            name.insert_str(0, prefix);
+        }
+        self.stage.path.insert(0, second);
+        self.stage.prefix.insert_str(0, prefix);
+        self
+    }
+
+    // gets the version of this migration's stage from that of the DSP it belongs to
+    fn project(&self, version: u32) -> u32 {
+        self.stage.path.iter().fold(version, |version, second| {
+            let (first, other) = unpair_versions(version);
+            if *second { other } else { first }
+        })
+    }
+
+    fn renamed<'a>(&'a self, name: &'a str) -> &'a str {
+        self.renames.iter().find(|(old, _)| old == name).map_or(name, |(_, new)| new)
+    }
+
+    fn remapped(&self, name: &str, value: i32) -> i32 {
+        self.ordinals
+            .iter()
+            .filter(|(param, _)| param == name)
+            .flat_map(|(_, map)| map.iter())
+            .find(|(old, _)| *old == value)
+            .map_or(value, |(_, new)| *new)
+    }
+
+    fn covers(&self, name: &str) -> bool {
+        self.ordinals.iter().any(|(param, _)| param == name)
+    }
+}
+
+/// Combines the versions of two DSPs into the version of a [combination](crate::dsp::compose) of
+/// them, which differs for every pair and grows whenever either version does. Panics if the result
+/// doesn't fit in a `u32`.
+pub fn pair_versions(first: u32, second: u32) -> u32 {
+    // pairs are numbered in order of the product of the versions plus one, then of the first, so
+    // that combining combinations doesn't quickly run out of versions
+    let (x, y) = (first as u64 + 1, second as u64 + 1);
+    let rank = divisors(x * y).iter().position(|it| *it == x).unwrap() as u64;
+    u32::try_from(pairs_up_to(x * y - 1) + rank).expect("Combined versions must fit in a u32")
+}
+
+// splits a version made by `pair_versions`
+fn unpair_versions(version: u32) -> (u32, u32) {
+    let version = version as u64;
+    // the product is the first one with more pairs up to it than the version
+    let (mut low, mut high) = (1, version + 1);
+    while low < high {
+        let mid = (low + high) / 2;
+        if pairs_up_to(mid) > version { high = mid } else { low = mid + 1 }
+    }
+    let x = divisors(low)[(version - pairs_up_to(low - 1)) as usize];
+    ((x - 1) as u32, (low / x - 1) as u32)
+}
+
+// counts the pairs of positive numbers whose product is at most `n`
+fn pairs_up_to(n: u64) -> u64 {
+    let root = n.isqrt();
+    2 * (1..=root).map(|x| n / x).sum::<u64>() - root * root
+}
+
+// lists the divisors of `n`, in ascending order
+fn divisors(n: u64) -> Vec<u64> {
+    let root = n.isqrt();
+    let low: Vec<_> = (1..=root).filter(|it| n.is_multiple_of(*it)).collect();
+    let high = low.iter().rev().map(|it| n / it).filter(|it| *it > root);
+    low.iter().copied().chain(high).collect()
+}
+
+// sorted migrations that upgrade the parameter `name` from `from` to `to`. Each migration upgrades
+// data up to the version of the next one, so data saved between two migrations needs the earlier
+// one too. Parameters of a combined DSP only go through the migrations of their own stage, which
+// is the innermost one whose prefix they have
+fn applicable<'a>(migrations: &'a [Migration], name: &str, from: u32, to: u32) -> Vec<&'a Migration> {
+    let stage = migrations
+        .iter()
+        .map(|it| &it.stage)
+        .filter(|it| name.starts_with(&it.prefix))
+        .max_by_key(|it| it.prefix.len());
+    let staged: Vec<_> = migrations.iter().filter(|it| Some(&it.stage) == stage).collect();
+    let Some(first) = staged.first() else {
+        return vec![];
+    };
+    let (from, to) = (first.project(from), first.project(to));
+    let mut sorted: Vec<_> = staged.into_iter().filter(|it| it.version < to).collect();
+    sorted.sort_by_key(|it| it.version);
+    let targets: Vec<_> = sorted.iter().skip(1).map(|it| it.version).chain([to]).collect();
+    sorted.into_iter().zip(targets).filter(|(_, target)| *target > from).map(|(it, _)| it).collect()
+}
+
+// follows a parameter through a series of migrations, returning its current name
+fn trace(migrations: &[&Migration], current: &[String], name: &str, mut value: Option<&mut ParameterValue>) -> Option<String> {
+    let first = migrations.first().map_or(current, |it| &it.names);
+    if !first.iter().any(|it| it == name) {
+        return None;
+    }
+    let mut name = name.to_string();
+    for (i, migration) in migrations.iter().enumerate() {
+        if let Some(ParameterValue::Int(v)) = value.as_deref_mut() {
+            *v = migration.remapped(&name, *v);
+        }
+        name = migration.renamed(&name).to_string();
+        let next = migrations.get(i + 1).map_or(current, |it| &it.names);
+        if !next.contains(&name) {
+            return None;
+        }
+    }
+    Some(name)
+}
+
+fn current_names<D: Dsp>() -> Vec<String> {
+    D::parameters().iter().map(|it| it.name.to_string()).collect()
+}
+
+// the names of the parameters saved by `from`, in order, as each stage had them then
+fn saved_names(migrations: &[Migration], current: &[String], from: u32, to: u32) -> Vec<String> {
+    let mut names = vec![];
+    let mut listed = vec![];
+    for name in current {
+        match applicable(migrations, name, from, to).first() {
+            Some(first) if !listed.contains(&&first.stage) => {
+                listed.push(&first.stage);
+                names.extend(first.names.iter().cloned());
+            }
+            Some(_) => {}
+            None => names.push(name.clone()),
+        }
+    }
+    names
+}
+
+/// Gets the current name of a parameter saved by `version` as `name`, or [None] if it was removed.
+pub fn migrate_name<D: Dsp>(version: u32, name: &str) -> Option<String> {
+    let migrations = D::migrations();
+    trace(&applicable(&migrations, name, version, D::version()), &current_names::<D>(), name, None)
+}
+
+/// Gets the current index of the parameter saved by `version` at `index`, or [None] if it was removed.
+pub fn migrate_index<D: Dsp>(version: u32, index: usize) -> Option<usize> {
+    let migrations = D::migrations();
+    let current = current_names::<D>();
+    let name = saved_names(&migrations, &current, version, D::version()).into_iter().nth(index)?;
+    let name = trace(&applicable(&migrations, &name, version, D::version()), &current, &name, None)?;
+    current.iter().position(|it| *it == name)
+}
+
+/// Migrates a parameter value saved by `version` under `name`, returning the current index of its
+/// parameter alongside the updated value, or [None] if the parameter was removed.
+pub fn migrate_value<D: Dsp>(version: u32, name: &str, mut value: ParameterValue) -> Option<(usize, ParameterValue)> {
+    let migrations = D::migrations();
+    let current = current_names::<D>();
+    let name = trace(&applicable(&migrations, name, version, D::version()), &current, name, Some(&mut value))?;
+    Some((current.iter().position(|it| *it == name)?, value))
+}
+
+/// A saved set of parameter values, along with the version of the DSP that saved them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    /// The version of the DSP that saved this preset.
+    pub version: u32,
+    /// Parameter values by name.
+    pub values: Vec<(String, ParameterValue)>,
+}
+
+impl Preset {
+    /// Captures the current values of a DSP's parameters. Special parameter types are skipped.
+    pub fn capture<D: Dsp>(dsp: &mut D) -> Self {
+        Preset {
+            version: D::version(),
+            values: D::parameters()
+                .iter()
+                .enumerate()
+                .filter_map(|(index, it)| Some((it.name.to_string(), dsp.visit_parameter(index, GetValue)??)))
+                .collect(),
+        }
+    }
+
+    /// Applies this preset to a DSP, migrating values saved by older versions. Returns the names of
+    /// values that could not be applied, because their parameter was removed or changed type, or
+    /// its setter reported an error.
+    pub fn apply<D: Dsp>(&self, dsp: &mut D) -> Vec<String> {
+        let mut skipped = vec![];
+        for (name, value) in &self.values {
+            match migrate_value::<D>(self.version, name, value.clone()) {
+                Some((index, value)) if dsp.visit_parameter(index, SetValue(&value)) == Some(true) => {}
+                _ => skipped.push(name.clone()),
+            }
+        }
+        skipped
+    }
+}
+
+struct GetValue;
+
+impl ParameterVisitor for GetValue {
+    type Output = Option<ParameterValue>;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> Option<ParameterValue> {
+        parameter.get(dsp)
+    }
+}
+
+struct SetValue<'a>(&'a ParameterValue);
+
+impl ParameterVisitor for SetValue<'_> {
+    type Output = bool;
+
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> bool {
+        parameter.set(dsp, self.0)
+    }
+}
+
+/// The type and range of a parameter, without its callbacks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterKind {
+    /// A [ParameterType::Float].
+    Float { min: f32, max: f32, default: f32 },
+    /// A [ParameterType::Int], including enumerations.
+    Int {
+        min: i32,
+        max: i32,
+        default: i32,
+        names: Option<Vec<String>>,
+    },
+    /// A [ParameterType::Bool].
+    Bool { default: bool },
+    /// A [ParameterType::Data].
+    Data,
+    /// A [ParameterType::Sidechain].
+    Sidechain,
+    /// A [ParameterType::DynamicResponse].
+    DynamicResponse,
+    /// A [ParameterType::OverallGain].
+    OverallGain,
+    /// A [ParameterType::ListenerAttributes].
+    ListenerAttributes,
+    /// A [ParameterType::ListenerAttributesList].
+    ListenerAttributesList,
+    /// A [ParameterType::AttenuationRange].
+    AttenuationRange,
+    /// A [ParameterType::Fft].
+    Fft,
+}
+
+impl ParameterKind {
+    /// Gets the kind of a parameter type.
+    pub fn of<D: ?Sized>(ty: &ParameterType<D>) -> Self {
+        match ty {
+            ParameterType::Float { min, max, default, .. } => ParameterKind::Float {
+                min: *min,
+                max: *max,
+                default: *default,
+            },
+            ParameterType::Int { min, max, default, names, .. } => ParameterKind::Int {
+                min: *min,
+                max: *max,
+                default: *default,
+                names: names.as_ref().map(|it| it.iter().map(|it| it.to_string()).collect()),
+            },
+            ParameterType::Bool { default, .. } => ParameterKind::Bool { default: *default },
+            ParameterType::Data { .. } => ParameterKind::Data,
+            ParameterType::Sidechain { .. } => ParameterKind::Sidechain,
+            ParameterType::DynamicResponse { .. } => ParameterKind::DynamicResponse,
+            ParameterType::OverallGain { .. } => ParameterKind::OverallGain,
+            ParameterType::ListenerAttributes { .. } => ParameterKind::ListenerAttributes,
+            ParameterType::ListenerAttributesList { .. } => ParameterKind::ListenerAttributesList,
+            ParameterType::AttenuationRange { .. } => ParameterKind::AttenuationRange,
+            ParameterType::Fft { .. } => ParameterKind::Fft,
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            ParameterKind::Float { .. } => "float",
+            ParameterKind::Int { .. } => "int",
+            ParameterKind::Bool { .. } => "bool",
+            ParameterKind::Data => "data",
+            ParameterKind::Sidechain => "sidechain",
+            ParameterKind::DynamicResponse => "dynamic_response",
+            ParameterKind::OverallGain => "overall_gain",
+            ParameterKind::ListenerAttributes => "listener_attributes",
+            ParameterKind::ListenerAttributesList => "listener_attributes_list",
+            ParameterKind::AttenuationRange => "attenuation_range",
+            ParameterKind::Fft => "fft",
+        }
+    }
+}
+
+/// A named parameter in a [ParameterTable].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterInfo {
+    /// The parameter's name.
+    pub name: String,
+    /// The parameter's type and range.
+    pub kind: ParameterKind,
+}
+
+/// A snapshot of a version of a DSP's parameters, which can be compared against other versions.
+///
+/// Tables can be written to and parsed from a simple tab-separated text format, so that snapshots
+/// of released versions can be kept alongside a plugin's source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterTable {
+    /// The version of the DSP with these parameters.
+    pub version: u32,
+    /// The DSP's parameters, in order.
+    pub parameters: Vec<ParameterInfo>,
+}
+
+/// A difference between two [ParameterTable]s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// What changed.
+    pub kind: ChangeKind,
+    /// Whether the change breaks data saved by the older version that doesn't record its version,
+    /// such as FMOD Studio banks.
+    pub breaking: bool,
+    /// Whether a [Migration] accounts for this change, so that versioned data (e.g. [Preset]s) is
+    /// still applied correctly.
+    pub migrated: bool,
+}
+
+/// A kind of [Change] between two [ParameterTable]s.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    /// A parameter was added.
+    Added { name: String, index: usize },
+    /// A parameter was removed.
+    Removed { name: String, index: usize },
+    /// A parameter was renamed.
+    Renamed { from: String, to: String },
+    /// A parameter's index changed.
+    Moved { name: String, from: usize, to: usize },
+    /// A parameter's type changed.
+    Retyped { name: String },
+    /// A parameter's range changed.
+    RangeChanged { name: String },
+    /// A parameter's default value changed.
+    DefaultChanged { name: String },
+    /// The options of an enumerated parameter changed.
+    OptionsChanged { name: String },
+}
+
+impl ParameterTable {
+    /// Gets the parameter table of the current version of a DSP.
+    pub fn of<D: Dsp>() -> Self {
+        ParameterTable {
+            version: D::version(),
+            parameters: D::parameters()
+                .iter()
+                .map(|it| ParameterInfo {
+                    name: it.name.to_string(),
+                    kind: ParameterKind::of(&it.ty),
+                })
+                .collect(),
+        }
+    }
+
+    /// Lists the changes between this table and a newer one, taking into account the migrations
+    /// of the newer version.
+    pub fn diff(&self, newer: &ParameterTable, migrations: &[Migration]) -> Vec<Change> {
+        let current: Vec<String> = newer.parameters.iter().map(|it| it.name.clone()).collect();
+        let mut reached = vec![false; newer.parameters.len()];
+        let mut changes = vec![];
+        let mut change = |kind, breaking, migrated| changes.push(Change { kind, breaking, migrated });
+
+        for (index, old) in self.parameters.iter().enumerate() {
+            let name = old.name.clone();
+            let migrations = applicable(migrations, &name, self.version, newer.version);
+            // a migration that lists this parameter is aware of how it changed
+            let known = migrations.first().is_some_and(|it| it.names.contains(&name));
+            let Some(new_name) = trace(&migrations, &current, &name, None) else {
+                change(ChangeKind::Removed { name, index }, true, known);
+                continue;
+            };
+            let new_index = current.iter().position(|it| *it == new_name).unwrap();
+            let new = &newer.parameters[new_index];
+            reached[new_index] = true;
+
+            if new_name != name {
+                change(
+                    ChangeKind::Renamed {
+                        from: name,
+                        to: new_name.clone(),
+                    },
+                    true,
+                    true,
+                );
+            }
+            if new_index != index {
+                let kind = ChangeKind::Moved {
+                    name: new_name.clone(),
+                    from: index,
+                    to: new_index,
+                };
+                change(kind, true, known);
+            }
+
+            let name = new_name;
+            match (&old.kind, &new.kind) {
+                (
+                    ParameterKind::Float { min, max, default },
+                    ParameterKind::Float {
+                        min: n_min,
+                        max: n_max,
+                        default: n_default,
+                    },
+                ) => {
+                    if min != n_min || max != n_max {
+                        change(ChangeKind::RangeChanged { name: name.clone() }, n_min > min || n_max < max, false);
+                    }
+                    if default != n_default {
+                        change(ChangeKind::DefaultChanged { name }, false, false);
+                    }
+                }
+                (
+                    ParameterKind::Int { min, max, default, names },
+                    ParameterKind::Int {
+                        min: n_min,
+                        max: n_max,
+                        default: n_default,
+                        names: n_names,
+                    },
+                ) => {
+                    if min != n_min || max != n_max {
+                        change(ChangeKind::RangeChanged { name: name.clone() }, n_min > min || n_max < max, false);
+                    }
+                    if names != n_names {
+                        // appending options is fine, but anything else changes the meaning of saved values
+                        let appended = match (names, n_names) {
+                            (Some(names), Some(n_names)) => n_names.starts_with(names),
+                            _ => false,
+                        };
+                        let remapped = migrations.iter().any(|it| it.covers(&old.name) || it.covers(&name));
+                        change(ChangeKind::OptionsChanged { name: name.clone() }, !appended, remapped);
+                    }
+                    if default != n_default {
+                        change(ChangeKind::DefaultChanged { name }, false, false);
+                    }
+                }
+                (ParameterKind::Bool { default }, ParameterKind::Bool { default: n_default }) => {
+                    if default != n_default {
+                        change(ChangeKind::DefaultChanged { name }, false, false);
+                    }
+                }
+                (old, new) if old.tag() != new.tag() => change(ChangeKind::Retyped { name }, true, false),
+                _ => {}
+            }
+        }
+
+        for (index, new) in newer.parameters.iter().enumerate() {
+            if !reached[index] {
+                let kind = ChangeKind::Added { name: new.name.clone(), index };
+                change(kind, false, false);
+            }
+        }
+        changes
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ChangeKind::Added { name, index } => write!(f, "added `{name}` at index {index}")?,
+            ChangeKind::Removed { name, index } => write!(f, "removed `{name}` from index {index}")?,
+            ChangeKind::Renamed { from, to } => write!(f, "renamed `{from}` to `{to}`")?,
+            ChangeKind::Moved { name, from, to } => write!(f, "moved `{name}` from index {from} to {to}")?,
+            ChangeKind::Retyped { name } => write!(f, "changed the type of `{name}`")?,
+            ChangeKind::RangeChanged { name } => write!(f, "changed the range of `{name}`")?,
+            ChangeKind::DefaultChanged { name } => write!(f, "changed the default of `{name}`")?,
+            ChangeKind::OptionsChanged { name } => write!(f, "changed the options of `{name}`")?,
+        }
+        match (self.breaking, self.migrated) {
+            (true, true) => write!(f, " (breaking, migrated)"),
+            (true, false) => write!(f, " (breaking)"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for ParameterTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "version\t{}", self.version)?;
+        for param in &self.parameters {
+            write!(f, "{}\t{}", param.kind.tag(), param.name)?;
+            match &param.kind {
+                ParameterKind::Float { min, max, default } => write!(f, "\t{min}\t{max}\t{default}")?,
+                ParameterKind::Int { min, max, default, names } => {
+                    write!(f, "\t{min}\t{max}\t{default}")?;
+                    if let Some(names) = names {
+                        write!(f, "\t{}", names.join(","))?;
+                    }
+                }
+                ParameterKind::Bool { default } => write!(f, "\t{default}")?,
+                _ => {}
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error produced when parsing a [ParameterTable].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ParseTableError {
+    /// The line the error occurred on, starting from 1.
+    pub line: usize,
+}
+
+impl Display for ParseTableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid parameter table on line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseTableError {}
+
+impl FromStr for ParameterTable {
+    type Err = ParseTableError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().filter(|(_, it)| !it.trim().is_empty());
+        let (i, header) = lines.next().ok_or(ParseTableError { line: 1 })?;
+        let version = match header.trim_end_matches('\r').split('\t').collect::<Vec<_>>().as_slice() {
+            ["version", version] => version.parse().map_err(|_| ParseTableError { line: i + 1 })?,
+            _ => return Err(ParseTableError { line: i + 1 }),
+        };
+        let mut parameters = vec![];
+        for (i, line) in lines {
+            let err = ParseTableError { line: i + 1 };
+            let fields: Vec<_> = line.trim_end_matches('\r').split('\t').collect();
+            let kind = match fields.as_slice() {
+                ["float", _, min, max, default] => ParameterKind::Float {
+                    min: min.parse().map_err(|_| err)?,
+                    max: max.parse().map_err(|_| err)?,
+                    default: default.parse().map_err(|_| err)?,
+                },
+                ["int", _, min, max, default, names @ ..] if names.len() <= 1 => ParameterKind::Int {
+                    min: min.parse().map_err(|_| err)?,
+                    max: max.parse().map_err(|_| err)?,
+                    default: default.parse().map_err(|_| err)?,
+                    names: names.first().map(|it| it.split(',').map(|it| it.to_string()).collect()),
+                },
+                ["bool", _, default] => ParameterKind::Bool {
+                    default: default.parse().map_err(|_| err)?,
+                },
+                ["data", _] => ParameterKind::Data,
+                ["sidechain", _] => ParameterKind::Sidechain,
+                ["dynamic_response", _] => ParameterKind::DynamicResponse,
+                ["overall_gain", _] => ParameterKind::OverallGain,
+                ["listener_attributes", _] => ParameterKind::ListenerAttributes,
+                ["listener_attributes_list", _] => ParameterKind::ListenerAttributesList,
+                ["attenuation_range", _] => ParameterKind::AttenuationRange,
+                ["fft", _] => ParameterKind::Fft,
+                _ => return Err(err),
+            };
+            parameters.push(ParameterInfo {
+                name: fields[1].to_string(),
+                kind,
+            });
+        }
+        Ok(ParameterTable { version, parameters })
+    }
+}
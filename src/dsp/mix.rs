@@ -0,0 +1,185 @@
+//! Dry/wet mixing and bypassing for any DSP.
+//!
+//! ```
+//! use scamble::dsp::mix::WetDry;
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//! use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+//! use scamble::float_param;
+//!
+//! struct Gain {
+//!     gain: f32
+//! }
+//!
+//! impl Dsp for Gain {
+//!     fn name() -> &'static str { "Gain" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Gain { gain: 0. } }
+//!
+//!     fn parameters() -> Vec<Parameter<Self>> {
+//!         vec![Parameter::new("gain", float_param!(gain, range: 0.0..2.0, default: 0.0))]
+//!     }
+//!
+//!     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+//!         for (out, x) in output.samples_mut().iter_mut().zip(input.samples()) {
+//!             *out = x * self.gain;
+//!         }
+//!     }
+//! }
+//!
+//! // "gain" keeps its index, and "mix" and "bypass" follow it
+//! let mut host = OfflineHost::<WetDry<Gain>>::new(48000, 4);
+//! host.set_float(0, 2.);
+//! host.set_float(1, 25.);
+//! host.render(SignalConst::new(&[1.; 4], 1), 4); // let the mix settle
+//! let rendered = host.render(SignalConst::new(&[1.; 4], 1), 4);
+//! assert_eq!(rendered.samples, [1.25; 4]);
+//! ```
+
+use crate::dsp::layout::Upmix;
+use crate::dsp::signal::{DelayLine, Signal, SignalBuf, SignalConst, SignalMut};
+use crate::dsp::{Dsp, DspError, Parameter, ParameterType, ProcessResult};
+use crate::{bool_param, float_param};
+
+/// Wraps a DSP to add `mix` and `bypass` parameters after its own.
+///
+/// The dry input is delayed by the wrapped DSP's [Dsp::latency_samples], so that it lines up with
+/// the processed signal. When bypassed, the input is passed through without processing the wrapped
+/// DSP at all.
+pub struct WetDry<D: Dsp> {
+    inner: D,
+    mix: f32,
+    bypass: bool,
+    // mix at the end of the previous block, ramped towards `mix` to avoid clicks
+    last_mix: f32,
+    // the input, delayed to line up with the output of `inner`
+    dry: SignalBuf,
+    delay: DelayLine,
+}
+
+impl<D: Dsp> WetDry<D> {
+    /// Gets the wrapped DSP.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Gets the wrapped DSP mutably.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    fn wrap(inner: D) -> Self {
+        let mut delay = DelayLine::new();
+        delay.reserve(inner.latency_samples());
+        WetDry {
+            inner,
+            mix: 100.,
+            bypass: false,
+            last_mix: 1.,
+            dry: SignalBuf::for_block(),
+            delay,
+        }
+    }
+
+    fn own_parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter {
+                ty: float_param!(mix, range: 0.0..100.0, default: 100.0),
+                name: "mix",
+                unit: "%",
+                desc: "Balance between the processed and unprocessed signal",
+            },
+            Parameter {
+                ty: bool_param!(bypass, default: false),
+                name: "bypass",
+                unit: "",
+                desc: "Whether to pass the signal through unprocessed",
+            },
+        ]
+    }
+}
+
+impl<D: Dsp> Dsp for WetDry<D> {
+    wrapper_methods!(D: branding, own_parameters, create, read);
+
+    fn reset(&mut self) {
+        let _ = self.try_reset();
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.last_mix = self.mix / 100.;
+        self.dry.reserve_block();
+        self.delay.clear();
+        let result = self.inner.try_reset();
+        // the latency may change on reset
+        self.delay.reserve(self.inner.latency_samples());
+        result
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        let result = if self.bypass {
+            ProcessResult::SkipNoEffect
+        } else {
+            match self.inner.should_process(idle, incoming_length) {
+                // the delayed dry signal may still be audible
+                ProcessResult::SkipSilent if self.mix < 100. && !self.delay.is_silent() => ProcessResult::Continue,
+                result => result,
+            }
+        };
+        if result != ProcessResult::Continue {
+            // nothing to ramp from or delay while skipped
+            self.last_mix = if self.bypass { 0. } else { self.mix / 100. };
+            self.delay.clear();
+        }
+        result
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        self.inner.preferred_out_channels()
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.inner.latency_samples()
+    }
+
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+        if self.bypass {
+            // passed through undelayed, as when the query skips processing
+            self.last_mix = 0.;
+            self.delay.clear();
+            output.fill(0.);
+            let layout = output.layout();
+            for (out, x) in output.samples_mut().iter_mut().zip(input.mix_to(layout, Upmix::default())) {
+                *out = x;
+            }
+            return Ok(());
+        }
+
+        self.inner.try_read(input, sidechain, output.reborrow())?;
+        let target = self.mix / 100.;
+        let from = self.last_mix;
+        self.last_mix = target;
+        if input.channels() == 0 {
+            // generators have no dry signal
+            return Ok(());
+        }
+
+        let in_channels = input.channels();
+        self.dry.copy_from(&input);
+        self.delay.process(self.dry.samples_mut(), in_channels, self.inner.latency_samples());
+
+        let out_channels = output.channels();
+        let length = output.length();
+        let frames = output.samples_mut().chunks_exact_mut(out_channels).zip(self.dry.samples().chunks_exact(in_channels));
+        for (i, (frame, dry)) in frames.enumerate() {
+            let mix = from + (target - from) * (i + 1) as f32 / length as f32;
+            let mono = dry.iter().sum::<f32>() / in_channels as f32;
+            for (c, out) in frame.iter_mut().enumerate() {
+                let dry = if in_channels == out_channels { dry[c] } else { mono };
+                *out = *out * mix + dry * (1. - mix);
+            }
+        }
+        Ok(())
+    }
+}
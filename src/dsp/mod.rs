@@ -1,11 +1,120 @@
 //! Tools for creating DSPs.
+//!
+//! DSPs can be wrapped to change how they're processed, as by [mix::WetDry],
+//! [oversample::Oversampled], [reblock::Reblocked] and [record::Recorded]. A wrapper shares the
+//! wrapped DSP's name and version, and exposes its parameters first, so a plugin should only export
+//! one of a DSP and its wrappers. DSPs combined by [compose] get names of their own instead.
 
+use crate::FmodError;
 use crate::data::*;
+use crate::dsp::decode::DecodeError;
 use crate::dsp::signal::*;
+use crate::raw_bindings::FMOD_RESULT;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
+// implements the groups of [Dsp] methods that a wrapper with an `inner` field of type `$inner`
+// shares with the other wrappers:
+// - `branding`: the wrapped DSP's name, version, type and migrations
+// - `parameters`: only the wrapped DSP's parameters
+// - `own_parameters`: the wrapped DSP's parameters followed by the wrapper's `own_parameters()`
+// - `create`: creation through the wrapper's `wrap` function
+// - `read`: reading through `try_read`, ignoring its errors
+macro_rules! wrapper_methods {
+    ($inner:ident: $($group:ident),*) => {
+        $(wrapper_methods!(@$group $inner);)*
+    };
+    (@branding $inner:ident) => {
+        fn name() -> &'static str {
+            $inner::name()
+        }
+
+        fn version() -> u32 {
+            $inner::version()
+        }
+
+        fn ty() -> $crate::dsp::DspType {
+            $inner::ty()
+        }
+
+        fn migrations() -> Vec<$crate::dsp::migrate::Migration> {
+            $inner::migrations()
+        }
+    };
+    (@parameters $inner:ident) => {
+        fn parameters() -> Vec<$crate::dsp::Parameter<Self>> {
+            $inner::parameters().into_iter().map($crate::dsp::Parameter::forwarded).collect()
+        }
+
+        fn parameter_count() -> usize {
+            $inner::parameter_count()
+        }
+
+        fn visit_parameter<V: $crate::dsp::ParameterVisitor>(&mut self, index: usize, visitor: V) -> Option<V::Output> {
+            self.inner.visit_parameter(index, visitor)
+        }
+    };
+    (@own_parameters $inner:ident) => {
+        fn parameters() -> Vec<$crate::dsp::Parameter<Self>> {
+            let mut params: Vec<_> = $inner::parameters().into_iter().map($crate::dsp::Parameter::forwarded).collect();
+            params.extend(Self::own_parameters());
+            params
+        }
+
+        fn parameter_count() -> usize {
+            $inner::parameter_count() + Self::own_parameters().len()
+        }
+
+        fn visit_parameter<V: $crate::dsp::ParameterVisitor>(&mut self, index: usize, visitor: V) -> Option<V::Output> {
+            let inner_count = $inner::parameter_count();
+            if index < inner_count {
+                self.inner.visit_parameter(index, visitor)
+            } else {
+                Self::own_parameters().get(index - inner_count).map(|it| visitor.visit(it, self))
+            }
+        }
+    };
+    (@create $inner:ident) => {
+        fn create() -> Self {
+            Self::wrap($inner::create())
+        }
+
+        fn try_create() -> Result<Self, $crate::dsp::DspError> {
+            Ok(Self::wrap($inner::try_create()?))
+        }
+    };
+    (@read $inner:ident) => {
+        fn read(&mut self, input: $crate::dsp::signal::SignalConst, output: $crate::dsp::signal::SignalMut) {
+            self.read_with_sidechain(input, None, output);
+        }
+
+        fn read_with_sidechain(
+            &mut self,
+            input: $crate::dsp::signal::SignalConst,
+            sidechain: Option<$crate::dsp::signal::SignalConst>,
+            output: $crate::dsp::signal::SignalMut,
+        ) {
+            let _ = self.try_read(input, sidechain, output);
+        }
+    };
+}
+
+pub mod compose;
 pub mod decode;
 pub mod interop;
+pub mod layout;
+pub mod migrate;
+pub mod mix;
+pub mod offline;
+pub mod oversample;
+pub mod reblock;
+pub mod record;
+pub mod resample;
 pub mod signal;
+pub mod spectral;
+pub mod tail;
+pub mod typed;
 
 /// A DSP type, used to distinguish effects from generators/instruments.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -31,11 +140,127 @@ pub enum ProcessResult {
     SkipSilent,
 }
 
+/// The kind of a [DspError], which decides the result code FMOD is given.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DspErrorKind {
+    /// Memory or another resource couldn't be allocated. Reported as `FMOD_ERR_MEMORY`.
+    Memory,
+    /// A parameter was given a value it can't take. Reported as `FMOD_ERR_INVALID_PARAM`.
+    InvalidParam,
+    /// Data or a signal was in a format that isn't supported. Reported as `FMOD_ERR_FORMAT`.
+    Format,
+    /// Any other failure. Reported as `FMOD_ERR_PLUGIN`, like a panic.
+    Plugin,
+    /// An error returned by FMOD itself, reported as-is.
+    Fmod(FmodError),
+}
+
+/// An error from a fallible DSP callback, such as [Dsp::try_create] or a setter that uses
+/// [interop::report]. Its message is logged through FMOD, and its kind decides the result code.
+///
+/// ```
+/// use scamble::dsp::{DspError, DspErrorKind};
+/// use scamble::raw_bindings::FMOD_RESULT;
+///
+/// let error = DspError::format("only mono samples are supported");
+/// assert_eq!(error.kind(), DspErrorKind::Format);
+/// assert_eq!(error.code(), FMOD_RESULT::FMOD_ERR_FORMAT);
+/// assert_eq!(error.to_string(), "only mono samples are supported");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DspError {
+    kind: DspErrorKind,
+    message: Cow<'static, str>,
+}
+
+impl DspError {
+    /// Creates an error of any kind. Static messages don't allocate, so can be used while processing.
+    pub fn new(kind: DspErrorKind, message: impl Into<Cow<'static, str>>) -> Self {
+        DspError { kind, message: message.into() }
+    }
+
+    /// Creates a [DspErrorKind::Memory] error.
+    pub fn memory(message: impl Into<Cow<'static, str>>) -> Self {
+        DspError::new(DspErrorKind::Memory, message)
+    }
+
+    /// Creates a [DspErrorKind::InvalidParam] error.
+    pub fn invalid_param(message: impl Into<Cow<'static, str>>) -> Self {
+        DspError::new(DspErrorKind::InvalidParam, message)
+    }
+
+    /// Creates a [DspErrorKind::Format] error.
+    pub fn format(message: impl Into<Cow<'static, str>>) -> Self {
+        DspError::new(DspErrorKind::Format, message)
+    }
+
+    /// Creates a [DspErrorKind::Plugin] error.
+    pub fn plugin(message: impl Into<Cow<'static, str>>) -> Self {
+        DspError::new(DspErrorKind::Plugin, message)
+    }
+
+    /// Gets the kind of this error.
+    pub fn kind(&self) -> DspErrorKind {
+        self.kind
+    }
+
+    /// Gets the message logged for this error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Gets the result code FMOD is given for this error.
+    pub fn code(&self) -> FMOD_RESULT {
+        match self.kind {
+            DspErrorKind::Memory => FMOD_RESULT::FMOD_ERR_MEMORY,
+            DspErrorKind::InvalidParam => FMOD_RESULT::FMOD_ERR_INVALID_PARAM,
+            DspErrorKind::Format => FMOD_RESULT::FMOD_ERR_FORMAT,
+            DspErrorKind::Plugin => FMOD_RESULT::FMOD_ERR_PLUGIN,
+            DspErrorKind::Fmod(error) => error.code(),
+        }
+    }
+}
+
+impl Display for DspError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for DspError {}
+
+impl From<FmodError> for DspError {
+    fn from(error: FmodError) -> Self {
+        DspError::new(DspErrorKind::Fmod(error), error.to_string())
+    }
+}
+
+impl From<DecodeError> for DspError {
+    fn from(error: DecodeError) -> Self {
+        DspError::format(format!("Couldn't decode audio: {error}"))
+    }
+}
+
+/// A value of a non-special parameter, independent of the parameter itself. Used to save and restore
+/// parameters generically, such as in [migrate::Preset]s.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterValue {
+    /// The value of a [ParameterType::Float] parameter.
+    Float(f32),
+    /// The value of a [ParameterType::Int] parameter.
+    Int(i32),
+    /// The value of a [ParameterType::Bool] parameter.
+    Bool(bool),
+    /// The value of a [ParameterType::Data] parameter.
+    Data(Vec<u8>),
+}
+
 /// A parameter of a DSP.
 pub struct Parameter<Dsp: ?Sized> {
     /// The parameter's type, and parameter-type specific details, including getters, setters, and ranges.
     pub ty: ParameterType<Dsp>,
-    /// The parameter's name.
+    /// The parameter's name. Must be up to 15 characters, and is converted to ASCII with
+    /// [interop::to_ascii] for FMOD.
     pub name: &'static str,
     /// The parameter's unit. FMOD Studio provides preferential treatment for the following units:
     /// - Hz
@@ -103,13 +328,17 @@ pub enum ParameterType<Dsp: ?Sized> {
         setter: fn(&[u8], &mut Dsp),
         /// Callback used to get this parameter. Returning [None] has the effect of returning
         /// a zero-sized buffer. Optionally, a string description may also be returned.
-        getter: fn(&Dsp) -> Option<(&[u8], Option<&str>)>,
+        ///
+        /// The DSP is borrowed mutably, so that data can be computed into storage owned by the
+        /// DSP, such as a [Typed](crate::dsp::typed::Typed) field. FMOD only reads the returned
+        /// data until the parameter is next accessed.
+        getter: fn(&mut Dsp) -> Option<(&[u8], Option<&str>)>,
     },
     // Max of 1 of each of these:
     /// A sidechain parameter. At most one may be present.
     ///
     /// Provides access to an additional signal input. The parameter itself is set to `true` when
-    /// a sidechain input exists; use `interop::with_sidechain` to access the signal itself.
+    /// a sidechain input exists; the signal itself is passed to [Dsp::read_with_sidechain].
     Sidechain {
         /// Callback used to set the parameter's "enable" flag.
         setter: fn(bool, &mut Dsp),
@@ -169,14 +398,27 @@ pub enum ParameterType<Dsp: ?Sized> {
     },
 }
 
+/// An operation on a single parameter of a DSP, applied through [Dsp::visit_parameter]. Since the
+/// parameter may belong to a DSP wrapped by another, the operation is generic over the DSP type.
+pub trait ParameterVisitor {
+    /// The result of the operation.
+    type Output;
+
+    /// Applies the operation to a parameter of a DSP.
+    fn visit<D: Dsp>(self, parameter: &Parameter<D>, dsp: &mut D) -> Self::Output;
+}
+
 /// A DSP, either an effect or generator. Any type that implements this trait can be converted to
 /// an FMOD [crate::raw_bindings::FMOD_DSP_DESCRIPTION] using [interop::into_desc].
 pub trait Dsp {
     // branding
     /// Gets the DSP's name. Must be up to 31 characters, though a plugin script can provide a longer
     /// user-facing one. Bank files and scripts refer to names, so changing them is a breaking change.
+    ///
+    /// Like other names, this is converted to ASCII with [interop::to_ascii] for FMOD.
     fn name() -> &'static str;
-    /// Gets the DSP's version. Should be changed every time the DSP changes.
+    /// Gets the DSP's version. Should be changed every time the DSP changes. [Dsp::migrations] are
+    /// keyed on this value.
     fn version() -> u32;
     /// Gets the type of the DSP, either an effect or generator (plug-in instrument.)
     fn ty() -> DspType;
@@ -187,23 +429,71 @@ pub trait Dsp {
         vec![]
     }
 
+    /// Gets the number of parameters, which must match the length of [Dsp::parameters]. DSPs that
+    /// wrap others should override it to add their own count to the wrapped DSP's, so that finding
+    /// which DSP a parameter belongs to doesn't build every parameter.
+    fn parameter_count() -> usize
+    where
+        Self: Sized,
+    {
+        Self::parameters().len()
+    }
+
+    /// Applies `visitor` to the parameter at `index`, or returns [None] if there is no such
+    /// parameter. All parameter accesses through FMOD go through this method.
+    ///
+    /// The default implementation uses [Dsp::parameters]. DSPs that wrap others should override it
+    /// to reach the wrapped DSP's own parameters (see [Parameter::forwarded]).
+    fn visit_parameter<V: ParameterVisitor>(&mut self, index: usize, visitor: V) -> Option<V::Output>
+    where
+        Self: Sized,
+    {
+        Self::parameters().get(index).map(|it| visitor.visit(it, self))
+    }
+
+    /// Gets the migrations used to bring parameter data saved by previous versions up to date, in
+    /// any order. See [migrate] for details.
+    fn migrations() -> Vec<migrate::Migration> {
+        vec![]
+    }
+
     // lifecycle
     /// Creates an instance of the DSP with default settings.
     fn create() -> Self;
 
+    /// Creates an instance of the DSP like [Dsp::create], but may fail, such as when a resource
+    /// can't be allocated. FMOD creates DSPs through this method, so failures are reported to FMOD
+    /// instead of panicking. By default, this calls [Dsp::create].
+    fn try_create() -> Result<Self, DspError>
+    where
+        Self: Sized,
+    {
+        Ok(Self::create())
+    }
+
     /// Resets the DSP's internal state.
     fn reset(&mut self) {}
 
+    /// Resets the DSP's internal state like [Dsp::reset], but may fail. FMOD resets DSPs through
+    /// this method. By default, this calls [Dsp::reset].
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.reset();
+        Ok(())
+    }
+
     // processing
     /// Checks whether the DSP will produce any input for the given input and internal state.
     /// `idle` is `true` when the input is silent. The default implementation proceeds with
-    /// processing whenever the input is non-silent.
+    /// processing whenever the input is non-silent, and always proceeds for generators.
     ///
     /// FMOD uses the returned value for both optimization and to decide when to end an event,
     /// and returning an incorrect value can both prolong an event indefinitely and cut off a sound
     /// prematurely.
+    ///
+    /// DSPs that keep producing output after their input goes silent can delegate to a
+    /// [tail::TailTracker].
     fn should_process(&mut self, idle: bool, _incoming_length: usize) -> ProcessResult {
-        if idle {
+        if idle && Self::ty() == DspType::Effect {
             ProcessResult::SkipSilent
         } else {
             ProcessResult::Continue
@@ -220,10 +510,39 @@ pub trait Dsp {
         None
     }
 
+    /// Gets the number of samples by which the DSP's output lags behind its input, such as when
+    /// buffering input for block-based processing. Hosts can use this to line up the output with
+    /// other signals, such as the dry input (see [offline::OfflineHost::render_compensated]).
+    ///
+    /// This may depend on state, such as the length of blocks previously passed to [Dsp::read].
+    fn latency_samples(&self) -> usize {
+        0
+    }
+
     /// Process a block of input into a block of output through this DSP.
     ///
     /// The output must be fully written to by the DSP.
     fn read(&mut self, input: SignalConst, output: SignalMut);
+
+    /// Process a block of input into a block of output through this DSP, along with the signal
+    /// from a sidechain input. By default, the sidechain is ignored and [Dsp::read] is called.
+    ///
+    /// The sidechain is only provided when a [ParameterType::Sidechain] is present and enabled. It
+    /// has the same length as the input, but may have a different number of channels.
+    fn read_with_sidechain(&mut self, input: SignalConst, sidechain: Option<SignalConst>, output: SignalMut) {
+        let _ = sidechain;
+        self.read(input, output)
+    }
+
+    /// Process a block like [Dsp::read_with_sidechain], but may fail, such as when the signal is
+    /// in a format the DSP can't handle. FMOD processes DSPs through this method, so failures are
+    /// reported to FMOD instead of panicking. By default, this calls [Dsp::read_with_sidechain].
+    ///
+    /// DSPs that override this should implement [Dsp::read] by calling it, ignoring any error.
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, output: SignalMut) -> Result<(), DspError> {
+        self.read_with_sidechain(input, sidechain, output);
+        Ok(())
+    }
 }
 
 impl<T: ?Sized> Parameter<T> {
@@ -246,6 +565,95 @@ impl<T: ?Sized> Parameter<T> {
             desc: "",
         }
     }
+
+    /// Copies this parameter's name, description and type-specific details onto a DSP that wraps
+    /// `T`. The wrapper should reach this parameter through [Dsp::visit_parameter], as the copy's
+    /// callbacks can't reach `T`: [Parameter::get] gives [None] for the copy, and setting it fails
+    /// with [DspErrorKind::InvalidParam].
+    pub fn forwarded<W: ?Sized>(self) -> Parameter<W> {
+        Parameter {
+            ty: match self.ty {
+                ParameterType::Float { min, max, default, .. } => ParameterType::Float {
+                    min,
+                    max,
+                    default,
+                    setter: |_, _| forwarded(),
+                    getter: |_| forwarded(),
+                },
+                ParameterType::Int { min, max, default, max_is_inf, names, .. } => ParameterType::Int {
+                    min,
+                    max,
+                    default,
+                    max_is_inf,
+                    names,
+                    setter: |_, _| forwarded(),
+                    getter: |_| forwarded(),
+                },
+                ParameterType::Bool { default, names, .. } => ParameterType::Bool {
+                    default,
+                    names,
+                    setter: |_, _| forwarded(),
+                    getter: |_| forwarded(),
+                },
+                ParameterType::Data { .. } => ParameterType::Data { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::Sidechain { .. } => ParameterType::Sidechain { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::DynamicResponse { .. } => ParameterType::DynamicResponse { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::OverallGain { .. } => ParameterType::OverallGain { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::ListenerAttributes { .. } => ParameterType::ListenerAttributes { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::ListenerAttributesList { .. } => ParameterType::ListenerAttributesList { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::AttenuationRange { .. } => ParameterType::AttenuationRange { setter: |_, _| forwarded(), getter: |_| forwarded() },
+                ParameterType::Fft { .. } => ParameterType::Fft { setter: |_, _| forwarded(), getter: |_| forwarded() },
+            },
+            name: self.name,
+            unit: self.unit,
+            desc: self.desc,
+        }
+    }
+
+    /// Gets the current value of this parameter from a DSP, or [None] for special parameter types
+    /// and [forwarded](Parameter::forwarded) parameters.
+    pub fn get(&self, dsp: &mut T) -> Option<ParameterValue> {
+        let (value, result) = interop::reporting(|| match &self.ty {
+            ParameterType::Float { getter, .. } => Some(ParameterValue::Float(getter(dsp))),
+            ParameterType::Int { getter, .. } => Some(ParameterValue::Int(getter(dsp))),
+            ParameterType::Bool { getter, .. } => Some(ParameterValue::Bool(getter(dsp))),
+            ParameterType::Data { getter, .. } => Some(ParameterValue::Data(getter(dsp).map(|(data, _)| data.to_vec()).unwrap_or_default())),
+            _ => None,
+        });
+        value.filter(|_| result.is_ok())
+    }
+
+    /// Sets the value of this parameter on a DSP. Returns `false` if the value doesn't match the
+    /// parameter's type, or the setter reported an error; see [Parameter::try_set].
+    pub fn set(&self, dsp: &mut T, value: &ParameterValue) -> bool {
+        self.try_set(dsp, value).is_ok()
+    }
+
+    /// Sets the value of this parameter on a DSP, returning the error its setter reported through
+    /// [interop::report], if any. Fails with [DspErrorKind::InvalidParam] if the value doesn't match
+    /// the parameter's type.
+    pub fn try_set(&self, dsp: &mut T, value: &ParameterValue) -> Result<(), DspError> {
+        let (matched, result) = interop::reporting(|| {
+            match (&self.ty, value) {
+                (ParameterType::Float { setter, .. }, ParameterValue::Float(v)) => setter(*v, dsp),
+                (ParameterType::Int { setter, .. }, ParameterValue::Int(v)) => setter(*v, dsp),
+                (ParameterType::Bool { setter, .. }, ParameterValue::Bool(v)) => setter(*v, dsp),
+                (ParameterType::Data { setter, .. }, ParameterValue::Data(v)) => setter(v, dsp),
+                _ => return false,
+            }
+            true
+        });
+        if !matched {
+            return Err(DspError::invalid_param(format!("{} can't be set to {value:?}", self.name)));
+        }
+        result
+    }
+}
+
+// the callbacks of forwarded parameters, which can't reach the wrapped DSP
+fn forwarded<T: Default>() -> T {
+    interop::report(Err(DspError::invalid_param("Forwarded parameters must be accessed through Dsp::visit_parameter")));
+    T::default()
 }
 
 // float_param!(note, range: 0.0..1.0, default: 0.0)
@@ -366,10 +774,12 @@ macro_rules! bool_param {
 ///
 /// The field can be of any type with named constants, but must be specified. The options list is the
 /// canonical (saved) order of those constants, so modifications are not backwards compatible, though
-/// additions are. Changing the default is backwards compatible.
+/// additions are. Changing the default is backwards compatible. Reordered options can be mapped with
+/// [crate::dsp::migrate::Migration::remap_ordinals] for data that carries its version.
 ///
 /// Default names are given by the names of constants, but plugin scripts can change the name displayed
-/// in the FMOD Studio UI.
+/// in the FMOD Studio UI. Values outside the options are reported as invalid through
+/// [interop::report](crate::dsp::interop::report), leaving the field unchanged.
 #[macro_export]
 macro_rules! enum_param {
     ($name:ident: $t:ty, options: [$($opt:ident $(,)?)*], default: $default:ident) => {
@@ -388,14 +798,82 @@ macro_rules! enum_param {
                 $(
                     ${index()} => <$t>::$opt,
                 )*
-                _ => panic!(concat!("Unknown variant {} for field ", stringify!($name)), value)
+                _ => return $crate::dsp::interop::report(Err($crate::dsp::DspError::invalid_param(
+                    format!(concat!("Unknown variant {} for field ", stringify!($name)), value)
+                ))),
             },
             getter: |dsp| match dsp.$name {
                 $(
                     <$t>::$opt => ${index()},
                 )*
+                // reachable if only some variants are listed
+                #[allow(unreachable_patterns)]
                 _ => panic!()
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// loader_param!(samples)
+/// Convenience macro for quickly creating a data parameter that decodes audio in the background,
+/// based on a [Loader](crate::dsp::decode::Loader) field.
+///
+/// Example:
+/// ```
+/// use scamble::dsp::decode::Loader;
+/// use scamble::dsp::{Parameter, ParameterType};
+/// use scamble::loader_param;
+///
+/// struct MyDsp {
+///     samples: Loader
+/// }
+///
+/// let _: Parameter<MyDsp> = Parameter::new("samples", loader_param!(samples));
+/// ```
+///
+/// The parameter can't be read back, so FMOD sees it as empty.
+#[macro_export]
+macro_rules! loader_param {
+    ($name:ident) => {
+        ParameterType::Data {
+            setter: |data, dsp| dsp.$name.load(data),
+            getter: |_| None,
+        }
+    };
+}
+
+// typed_param!(envelope)
+/// Convenience macro for quickly creating a data parameter based on a [Typed](crate::dsp::typed::Typed)
+/// field, which is encoded when read and decoded when written.
+///
+/// Example:
+/// ```
+/// use scamble::dsp::typed::{Json, Typed};
+/// use scamble::dsp::{Parameter, ParameterType};
+/// use scamble::typed_param;
+///
+/// struct MyDsp {
+///     steps: Typed<Vec<f32>, Json>
+/// }
+///
+/// let _: Parameter<MyDsp> = Parameter::new("steps", typed_param!(steps));
+/// ```
+///
+/// Data that can't be decoded is reported as a [DspErrorKind::Format](crate::dsp::DspErrorKind::Format)
+/// error through [interop::report](crate::dsp::interop::report), and leaves the field unchanged.
+#[macro_export]
+macro_rules! typed_param {
+    ($name:ident) => {
+        ParameterType::Data {
+            setter: |data, dsp| {
+                if !dsp.$name.decode(data) {
+                    $crate::dsp::interop::report(Err($crate::dsp::DspError::format(concat!(
+                        "Couldn't decode data for field ",
+                        stringify!($name)
+                    ))));
+                }
+            },
+            getter: |dsp| Some((dsp.$name.encode(), None)),
+        }
+    };
+}
@@ -0,0 +1,412 @@
+//! An offline host for running DSPs outside of FMOD, such as in tests and tools.
+//!
+//! [OfflineHost] drives a DSP through the same callbacks that FMOD uses (see [interop::into_desc]),
+//! so parameter handling and process queries behave as they would in FMOD.
+//!
+//! ```
+//! use scamble::dsp::{Dsp, DspType};
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//!
+//! // delays its input by one sample
+//! struct Delay {
+//!     prev: f32
+//! }
+//!
+//! impl Dsp for Delay {
+//!     fn name() -> &'static str { "Delay" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Delay { prev: 0. } }
+//!
+//!     fn latency_samples(&self) -> usize { 1 }
+//!
+//!     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+//!         for (i, x) in input.read_mono().enumerate() {
+//!             output.write_sample(i, self.prev);
+//!             self.prev = x;
+//!         }
+//!     }
+//! }
+//!
+//! let dry: Vec<f32> = (0..1000).map(|it| (it as f32 / 10.).sin()).collect();
+//! let mut host = OfflineHost::<Delay>::new(48000, 256);
+//! let wet = host.render_compensated(SignalConst::new(&dry, 1), dry.len());
+//! assert_eq!(wet.samples, dry);
+//! ```
+
+use crate::dsp::layout::ChannelLayout;
+use crate::dsp::signal::{Signal, SignalBuf, SignalConst};
+use crate::dsp::{Dsp, DspType, ParameterType, interop};
+use crate::raw_bindings::FMOD_RESULT::{FMOD_ERR_DSP_DONTPROCESS, FMOD_ERR_INVALID_PARAM, FMOD_OK};
+use crate::raw_bindings::*;
+use crate::result;
+use std::cell::RefCell;
+use std::ffi::{CStr, c_char, c_int, c_uint, c_void};
+use std::marker::PhantomData;
+use std::{ptr, slice};
+
+thread_local! {
+    // messages logged through FMOD on this thread
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+struct HostConfig {
+    sample_rate: u32,
+    block_size: usize,
+}
+
+/// The output of [OfflineHost::render].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rendered {
+    /// The interleaved output samples.
+    pub samples: Vec<f32>,
+    /// The number of output channels.
+    pub channels: usize,
+    /// The result of the process query made for each block.
+    pub queries: Vec<FMOD_RESULT>,
+}
+
+/// Hosts a single instance of a DSP, processing audio in fixed-size blocks like FMOD's mixer.
+pub struct OfflineHost<D: Dsp> {
+    desc: FMOD_DSP_DESCRIPTION,
+    state: Box<FMOD_DSP_STATE>,
+    _functions: Box<FMOD_DSP_STATE_FUNCTIONS>,
+    config: Box<HostConfig>,
+    _dsp: PhantomData<D>,
+}
+
+impl<D: Dsp> OfflineHost<D> {
+    /// Creates an instance of a DSP in a host with the given sample rate and block size.
+    ///
+    /// Panics if the DSP can't be created; see [OfflineHost::try_new].
+    pub fn new(sample_rate: u32, block_size: usize) -> Self {
+        Self::try_new(sample_rate, block_size).unwrap_or_else(|error| panic!("Couldn't create {}: {error}", D::name()))
+    }
+
+    /// Creates an instance of a DSP in a host with the given sample rate and block size, returning
+    /// the result code given by [Dsp::try_create] on failure.
+    pub fn try_new(sample_rate: u32, block_size: usize) -> result::Result<Self> {
+        let desc = interop::into_desc::<D>();
+        let mut config = Box::new(HostConfig { sample_rate, block_size });
+        let mut functions = Box::new(FMOD_DSP_STATE_FUNCTIONS {
+            alloc: None,
+            realloc: None,
+            free: None,
+            getsamplerate: Some(get_sample_rate),
+            getblocksize: Some(get_block_size),
+            dft: ptr::null_mut(),
+            pan: ptr::null_mut(),
+            getspeakermode: None,
+            getclock: None,
+            getlistenerattributes: None,
+            log: Some(log),
+            getuserdata: None,
+        });
+        let mut state = Box::new(FMOD_DSP_STATE {
+            instance: &mut *config as *mut HostConfig as *mut c_void,
+            plugindata: ptr::null_mut(),
+            channelmask: 0,
+            source_speakermode: FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_DEFAULT,
+            sidechaindata: ptr::null_mut(),
+            sidechainchannels: 0,
+            functions: &mut *functions,
+            systemobject: 0,
+        });
+        result::check(unsafe { desc.create.unwrap()(&mut *state) })?;
+        Ok(OfflineHost {
+            desc,
+            state,
+            _functions: functions,
+            config,
+            _dsp: PhantomData,
+        })
+    }
+
+    /// Gets the hosted DSP.
+    pub fn dsp(&self) -> &D {
+        unsafe { &*interop::plugin_data::<D>(&*self.state) }
+    }
+
+    /// Gets the hosted DSP mutably.
+    pub fn dsp_mut(&mut self) -> &mut D {
+        unsafe { &mut *interop::plugin_data::<D>(&*self.state) }
+    }
+
+    /// Gets the latency of the hosted DSP, as reported by [Dsp::latency_samples].
+    pub fn latency_samples(&self) -> usize {
+        self.dsp().latency_samples()
+    }
+
+    /// Gets the sample rate of this host.
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate
+    }
+
+    /// Gets the block size of this host.
+    pub fn block_size(&self) -> usize {
+        self.config.block_size
+    }
+
+    /// Resets the hosted DSP.
+    pub fn reset(&mut self) -> FMOD_RESULT {
+        unsafe { self.desc.reset.unwrap()(&mut *self.state) }
+    }
+
+    /// Asks the DSP whether it would process a block of `length` samples, through the separate
+    /// query callback that FMOD uses alongside read callbacks. [OfflineHost::process] queries
+    /// through the process callback instead.
+    pub fn should_process(&mut self, idle: bool, length: usize) -> FMOD_RESULT {
+        unsafe {
+            self.desc.shouldiprocess.unwrap()(
+                &mut *self.state,
+                idle as FMOD_BOOL,
+                length as c_uint,
+                0,
+                0,
+                FMOD_SPEAKERMODE::FMOD_SPEAKERMODE_DEFAULT,
+            )
+        }
+    }
+
+    /// Sets a float parameter by index.
+    pub fn set_float(&mut self, index: usize, value: f32) -> FMOD_RESULT {
+        unsafe { self.desc.setparameterfloat.unwrap()(&mut *self.state, index as c_int, value) }
+    }
+
+    /// Sets an integer parameter by index.
+    pub fn set_int(&mut self, index: usize, value: i32) -> FMOD_RESULT {
+        unsafe { self.desc.setparameterint.unwrap()(&mut *self.state, index as c_int, value) }
+    }
+
+    /// Sets a boolean parameter by index.
+    pub fn set_bool(&mut self, index: usize, value: bool) -> FMOD_RESULT {
+        unsafe { self.desc.setparameterbool.unwrap()(&mut *self.state, index as c_int, value as FMOD_BOOL) }
+    }
+
+    /// Sets a data parameter by index.
+    pub fn set_data(&mut self, index: usize, value: &[u8]) -> FMOD_RESULT {
+        unsafe { self.desc.setparameterdata.unwrap()(&mut *self.state, index as c_int, value.as_ptr() as *mut c_void, value.len() as c_uint) }
+    }
+
+    /// Enables or disables the DSP's [ParameterType::Sidechain] parameter, as FMOD does when a
+    /// sidechain is connected. Sidechain input is only passed to the DSP while it's enabled. Fails
+    /// with `FMOD_ERR_INVALID_PARAM` if the DSP has no sidechain parameter.
+    pub fn enable_sidechain(&mut self, enabled: bool) -> FMOD_RESULT {
+        let Some(index) = D::parameters().iter().position(|it| matches!(it.ty, ParameterType::Sidechain { .. })) else {
+            return FMOD_ERR_INVALID_PARAM;
+        };
+        let mut value = FMOD_DSP_PARAMETER_SIDECHAIN {
+            sidechainenable: enabled as FMOD_BOOL,
+        };
+        let length = size_of::<FMOD_DSP_PARAMETER_SIDECHAIN>() as c_uint;
+        unsafe { self.desc.setparameterdata.unwrap()(&mut *self.state, index as c_int, &mut value as *mut _ as *mut c_void, length) }
+    }
+
+    /// Gets a data parameter by index, copying the data out.
+    pub fn get_data(&mut self, index: usize) -> (FMOD_RESULT, Vec<u8>) {
+        let mut value: *mut c_void = ptr::null_mut();
+        let mut length: c_uint = 0;
+        let mut desc = [0 as c_char; 32];
+        let result = unsafe { self.desc.getparameterdata.unwrap()(&mut *self.state, index as c_int, &mut value, &mut length, desc.as_mut_ptr()) };
+        if value.is_null() {
+            return (result, vec![]);
+        }
+        (result, unsafe { slice::from_raw_parts(value as *const u8, length as usize) }.to_vec())
+    }
+
+    /// Gets the text FMOD would display for a parameter's value, by index. For data parameters, this
+    /// is their description.
+    pub fn get_value_str(&mut self, index: usize) -> (FMOD_RESULT, String) {
+        let mut text = [0 as c_char; FMOD_DSP_GETPARAM_VALUESTR_LENGTH as usize];
+        let (state, index, valuestr) = (&mut *self.state, index as c_int, text.as_mut_ptr());
+        let result = unsafe {
+            match D::parameters().get(index as usize).map(|it| &it.ty) {
+                Some(ParameterType::Float { .. }) => self.desc.getparameterfloat.unwrap()(state, index, &mut 0., valuestr),
+                Some(ParameterType::Int { .. }) => self.desc.getparameterint.unwrap()(state, index, &mut 0, valuestr),
+                Some(ParameterType::Bool { .. }) => self.desc.getparameterbool.unwrap()(state, index, &mut 0, valuestr),
+                _ => self.desc.getparameterdata.unwrap()(state, index, &mut ptr::null_mut(), &mut 0, valuestr),
+            }
+        };
+        (result, unsafe { CStr::from_ptr(text.as_ptr()) }.to_string_lossy().into_owned())
+    }
+
+    /// Runs a single block through the DSP, as FMOD's mixer would: a process query is made first,
+    /// and the DSP is only processed if the query succeeds. Skipped blocks produce silence, or the
+    /// input itself if the DSP reports having no effect.
+    ///
+    /// Returns the result of the query. `output` is resized to the block, with the channel count
+    /// and layout the DSP chose.
+    pub fn process(&mut self, input: SignalConst, output: &mut SignalBuf) -> FMOD_RESULT {
+        self.process_with_sidechain(input, None, output)
+    }
+
+    /// Runs a single block through the DSP like [OfflineHost::process], with a sidechain input
+    /// connected. The sidechain must be at least as long as the block, and is only passed to the
+    /// DSP while its sidechain parameter is enabled; see [OfflineHost::enable_sidechain].
+    pub fn process_with_sidechain(&mut self, input: SignalConst, sidechain: Option<SignalConst>, output: &mut SignalBuf) -> FMOD_RESULT {
+        let length = if D::ty() == DspType::Generator {
+            self.config.block_size
+        } else {
+            input.length()
+        };
+        let idle = input.samples().iter().all(|it| *it == 0.);
+
+        let mut in_channels = input.channels() as c_int;
+        let mut in_mask: FMOD_CHANNELMASK = 0;
+        let mut in_ptr = input.samples().as_ptr() as *mut f32;
+        let in_buffers = FMOD_DSP_BUFFER_ARRAY {
+            numbuffers: 1,
+            buffernumchannels: &mut in_channels,
+            bufferchannelmask: &mut in_mask,
+            buffers: &mut in_ptr,
+            speakermode: input.layout().speaker_mode(),
+        };
+        let in_buffers: *const FMOD_DSP_BUFFER_ARRAY = if D::ty() == DspType::Generator { ptr::null() } else { &in_buffers };
+
+        // query, letting the DSP decide on its output channel count
+        let mut out_channels = input.channels() as c_int;
+        let mut out_mask: FMOD_CHANNELMASK = 0;
+        let mut out_ptr: *mut f32 = ptr::null_mut();
+        let mut out_buffers = FMOD_DSP_BUFFER_ARRAY {
+            numbuffers: 1,
+            buffernumchannels: &mut out_channels,
+            bufferchannelmask: &mut out_mask,
+            buffers: &mut out_ptr,
+            speakermode: input.layout().speaker_mode(),
+        };
+        if let Some(sidechain) = sidechain {
+            assert!(sidechain.length() >= length, "Sidechain is shorter than the block");
+            self.state.sidechaindata = sidechain.samples().as_ptr() as *mut f32;
+            self.state.sidechainchannels = sidechain.channels() as c_int;
+        }
+
+        let process = self.desc.process.unwrap();
+        let query = unsafe {
+            process(
+                &mut *self.state,
+                length as c_uint,
+                in_buffers,
+                &mut out_buffers,
+                idle as FMOD_BOOL,
+                FMOD_DSP_PROCESS_OPERATION::FMOD_DSP_PROCESS_QUERY,
+            )
+        };
+
+        let channels = out_channels.max(0) as usize;
+        output.resize_to_layout(length, ChannelLayout::from_buffer(out_buffers.speakermode, out_mask, channels));
+        let result = match query {
+            FMOD_OK => unsafe {
+                out_ptr = output.samples_mut().as_mut_ptr();
+                out_buffers.buffers = &mut out_ptr;
+                process(
+                    &mut *self.state,
+                    length as c_uint,
+                    in_buffers,
+                    &mut out_buffers,
+                    idle as FMOD_BOOL,
+                    FMOD_DSP_PROCESS_OPERATION::FMOD_DSP_PROCESS_PERFORM,
+                )
+            },
+            FMOD_ERR_DSP_DONTPROCESS if channels == input.channels() => {
+                output.copy_from(&input);
+                query
+            }
+            _ => query,
+        };
+
+        self.state.sidechaindata = ptr::null_mut();
+        self.state.sidechainchannels = 0;
+        result
+    }
+
+    /// Runs `frames` samples of input through the DSP in blocks. The input is padded with silence
+    /// if it is shorter than `frames`, and is ignored by generators besides its length.
+    pub fn render(&mut self, input: SignalConst, frames: usize) -> Rendered {
+        self.render_blocks(input, None, frames)
+    }
+
+    /// Renders like [OfflineHost::render], with a sidechain input connected as in
+    /// [OfflineHost::process_with_sidechain]. The sidechain is padded with silence like the input.
+    pub fn render_with_sidechain(&mut self, input: SignalConst, sidechain: SignalConst, frames: usize) -> Rendered {
+        self.render_blocks(input, Some(sidechain), frames)
+    }
+
+    fn render_blocks(&mut self, input: SignalConst, sidechain: Option<SignalConst>, frames: usize) -> Rendered {
+        let in_channels = input.channels();
+        let mut padded = input.samples().to_vec();
+        padded.resize(frames * in_channels, 0.);
+        let side_channels = sidechain.map_or(0, |it| it.channels());
+        let mut side_padded = sidechain.map_or(vec![], |it| it.samples().to_vec());
+        // generators process whole blocks regardless of the number of frames left
+        let block_size = self.config.block_size;
+        side_padded.resize((frames + block_size) * side_channels, 0.);
+
+        let mut rendered = Rendered {
+            samples: Vec::with_capacity(padded.len()),
+            channels: 0,
+            queries: vec![],
+        };
+        let mut block = SignalBuf::with_capacity(block_size, in_channels.max(2));
+        let mut start = 0;
+        while start < frames {
+            let len = self.config.block_size.min(frames - start);
+            let input = SignalConst::new(&padded[start * in_channels..(start + len) * in_channels], in_channels).with_layout(input.layout());
+            let side_block = &side_padded[start * side_channels..(start + block_size) * side_channels];
+            let sidechain = sidechain.map(|_| SignalConst::new(side_block, side_channels));
+            let result = self.process_with_sidechain(input, sidechain, &mut block);
+            // only the frames left are kept from a generator's whole block
+            rendered.channels = block.channels();
+            rendered.samples.extend_from_slice(&block.samples()[..len * block.channels()]);
+            rendered.queries.push(result);
+            start += len;
+        }
+        rendered
+    }
+
+    /// Renders like [OfflineHost::render], but compensates for the DSP's latency so that the output
+    /// lines up with the input. The input is extended with silence to flush out delayed output.
+    ///
+    /// The latency is read after rendering, so DSPs whose latency depends on the block length
+    /// report it correctly.
+    pub fn render_compensated(&mut self, input: SignalConst, frames: usize) -> Rendered {
+        let flush = self.latency_samples().max(self.config.block_size * 2);
+        let mut rendered = self.render(input, frames + flush);
+        let latency = self.latency_samples().min(flush);
+        rendered.samples.drain(..latency * rendered.channels);
+        rendered.samples.truncate(frames * rendered.channels);
+        rendered
+    }
+
+    /// Takes all messages logged through FMOD by DSPs on the current thread.
+    pub fn take_log() -> Vec<String> {
+        LOG.with_borrow_mut(std::mem::take)
+    }
+}
+
+impl<D: Dsp> Drop for OfflineHost<D> {
+    fn drop(&mut self) {
+        unsafe {
+            self.desc.release.unwrap()(&mut *self.state);
+        }
+    }
+}
+
+unsafe extern "C" fn get_sample_rate(state: *mut FMOD_DSP_STATE, rate: *mut c_int) -> FMOD_RESULT {
+    unsafe {
+        *rate = (*((*state).instance as *const HostConfig)).sample_rate as c_int;
+    }
+    FMOD_OK
+}
+
+unsafe extern "C" fn get_block_size(state: *mut FMOD_DSP_STATE, size: *mut c_uint) -> FMOD_RESULT {
+    unsafe {
+        *size = (*((*state).instance as *const HostConfig)).block_size as c_uint;
+    }
+    FMOD_OK
+}
+
+unsafe extern "C" fn log(_: FMOD_DEBUG_FLAGS, _: *const c_char, _: c_int, _: *const c_char, message: *const c_char, _: ...) {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned();
+    LOG.with_borrow_mut(|it| it.push(message));
+}
@@ -0,0 +1,341 @@
+//! Oversampling for nonlinear DSPs.
+//!
+//! Nonlinear processing such as waveshaping produces harmonics above the Nyquist frequency, which
+//! fold back down as aliasing. [Oversampled] runs a DSP at a multiple of the mixer's sample rate,
+//! filtering out those harmonics before returning to the mixer's rate.
+//!
+//! ```
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::oversample::{Oversampled, Quality};
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//! use scamble::dsp::{Dsp, DspType};
+//!
+//! struct Clip;
+//!
+//! impl Dsp for Clip {
+//!     fn name() -> &'static str { "Clip" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Clip }
+//!
+//!     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+//!         for (out, x) in output.samples_mut().iter_mut().zip(input.samples()) {
+//!             *out = x.clamp(-0.5, 0.5);
+//!         }
+//!     }
+//! }
+//!
+//! let mut host = OfflineHost::<Oversampled<Clip, 4>>::new(48000, 256);
+//! assert_eq!(host.latency_samples(), Quality::Medium.latency());
+//! let rendered = host.render_compensated(SignalConst::new(&[0.25; 1024], 1), 1024);
+//! // away from the edges of the input, the signal passes through the filters unchanged
+//! assert!(rendered.samples[64..960].iter().all(|it| (it - 0.25).abs() < 1e-3));
+//! ```
+
+use crate::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
+use crate::dsp::{Dsp, DspError, Parameter, ParameterType, ProcessResult, interop};
+use crate::enum_param;
+use std::f32::consts::PI;
+
+/// The quality of the filters used by [Oversampled] and [sinc resampling](crate::dsp::resample::Interpolation::Sinc).
+/// Higher qualities filter out more aliasing, at the cost of more processing and latency.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Quality {
+    /// Short filters, for previewing or subtle nonlinearities.
+    Low,
+    /// Balanced filters.
+    Medium,
+    /// Long filters, for heavy distortion.
+    High,
+}
+
+impl Quality {
+    /// Gets the latency added by oversampling at this quality, in samples at the mixer's rate.
+    pub const fn latency(self) -> usize {
+        // the up- and downsampling filters each delay by half their length
+        2 * self.half_taps()
+    }
+
+    // half the length of each filter, in samples at the mixer's rate
+    pub(crate) const fn half_taps(self) -> usize {
+        match self {
+            Quality::Low => 4,
+            Quality::Medium => 8,
+            Quality::High => 16,
+        }
+    }
+}
+
+/// Wraps a DSP to run it at `FACTOR` times the mixer's sample rate, which must be 2, 4 or 8.
+///
+/// Input is upsampled and output is downsampled by polyphase lowpass filters, whose length is
+/// controlled by a `quality` parameter added after the wrapped DSP's own. The wrapped DSP sees
+/// blocks `FACTOR` times as long, and [interop::sample_rate] reports the oversampled rate to it.
+pub struct Oversampled<D: Dsp, const FACTOR: usize> {
+    inner: D,
+    // how `inner` should be processed, as decided by the last query
+    inner_state: ProcessResult,
+    quality: Quality,
+    filters: Filters,
+    up: Vec<History>,
+    side: Vec<History>,
+    down: Vec<History>,
+    // buffers at the oversampled rate
+    high_in: SignalBuf,
+    high_side: SignalBuf,
+    high_out: SignalBuf,
+}
+
+// windowed-sinc lowpass filters for a quality and factor, with taps reversed for use with `History`
+struct Filters {
+    quality: Quality,
+    // for upsampling, one per phase, scaled by the factor to make up for zero-stuffing
+    up: Vec<Vec<f32>>,
+    // for downsampling, at the oversampled rate
+    down: Vec<f32>,
+}
+
+impl Filters {
+    fn new(quality: Quality, factor: usize) -> Self {
+        let half = quality.half_taps() * factor;
+        let len = 2 * half + 1;
+        // cut off below the mixer's Nyquist frequency, leaving room for the transition band
+        let cutoff = 0.4 / factor as f32;
+        let mut taps: Vec<f32> = (0..len)
+            .map(|n| {
+                let x = n as f32 - half as f32;
+                let sinc = if x == 0. { 2. * cutoff } else { (2. * PI * cutoff * x).sin() / (PI * x) };
+                let phase = 2. * PI * n as f32 / (len - 1) as f32;
+                let blackman = 0.42 - 0.5 * phase.cos() + 0.08 * (2. * phase).cos();
+                sinc * blackman
+            })
+            .collect();
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|it| *it /= sum);
+
+        let phase_len = len.div_ceil(factor);
+        let up = (0..factor)
+            .map(|p| {
+                (0..phase_len)
+                    .rev()
+                    .map(|k| taps.get(k * factor + p).map_or(0., |it| it * factor as f32))
+                    .collect()
+            })
+            .collect();
+        taps.reverse();
+        Filters { quality, up, down: taps }
+    }
+}
+
+// the most recent samples of a signal, oldest first
+#[derive(Clone)]
+pub(crate) struct History {
+    buffer: Vec<f32>,
+    pos: usize,
+}
+
+impl History {
+    pub(crate) fn new(len: usize) -> Self {
+        History {
+            buffer: vec![0.; len * 2],
+            pos: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, x: f32) {
+        // written twice, so that the window is always contiguous
+        let len = self.buffer.len() / 2;
+        self.pos = (self.pos + 1) % len;
+        self.buffer[self.pos] = x;
+        self.buffer[self.pos + len] = x;
+    }
+
+    pub(crate) fn window(&self) -> &[f32] {
+        let len = self.buffer.len() / 2;
+        &self.buffer[self.pos + 1..self.pos + 1 + len]
+    }
+
+    fn is_silent(&self) -> bool {
+        self.buffer.iter().all(|it| *it == 0.)
+    }
+}
+
+// upsamples each input sample into FACTOR samples, one per filter phase
+fn upsample<const FACTOR: usize>(filters: &Filters, histories: &mut [History], input: SignalConst, out: &mut SignalBuf) {
+    // generators have no input channels
+    let frames = input.samples().len() / input.channels().max(1);
+    out.resize_to_layout(frames * FACTOR, input.layout());
+    let mut high = out.as_signal_mut();
+    for ((channel, mut high), history) in high.zip_channels(&input).zip(histories) {
+        for (i, x) in channel.iter().enumerate() {
+            history.push(x);
+            for (p, phase) in filters.up.iter().enumerate() {
+                high[i * FACTOR + p] = dot(phase, history.window());
+            }
+        }
+    }
+}
+
+pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+impl<D: Dsp, const FACTOR: usize> Oversampled<D, FACTOR> {
+    /// Gets the wrapped DSP.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Gets the wrapped DSP mutably.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Gets the quality of the filters.
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// Sets the quality of the filters. This resets the filters, so should not be changed during playback.
+    pub fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+    }
+
+    fn wrap(inner: D) -> Self {
+        const { assert!(FACTOR == 2 || FACTOR == 4 || FACTOR == 8, "Oversampling factor must be 2, 4 or 8") };
+        Oversampled {
+            inner,
+            inner_state: ProcessResult::Continue,
+            quality: Quality::Medium,
+            filters: Filters::new(Quality::Medium, FACTOR),
+            up: vec![],
+            side: vec![],
+            down: vec![],
+            // blocks are processed at the oversampled rate
+            high_in: interop::with_rate_scale(FACTOR as u32, SignalBuf::for_block),
+            high_side: interop::with_rate_scale(FACTOR as u32, SignalBuf::for_block),
+            high_out: interop::with_rate_scale(FACTOR as u32, SignalBuf::for_block),
+        }
+    }
+
+    fn own_parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter {
+            ty: enum_param!(quality: Quality, options: [Low, Medium, High], default: Medium),
+            name: "quality",
+            unit: "",
+            desc: "Quality of the oversampling filters",
+        }]
+    }
+
+    // rebuilds the filters and their state if the quality or channel counts changed
+    fn prepare(&mut self, in_channels: usize, side_channels: usize, out_channels: usize) {
+        if self.filters.quality != self.quality {
+            self.filters = Filters::new(self.quality, FACTOR);
+            self.up.clear();
+            self.side.clear();
+            self.down.clear();
+        }
+        let up_len = self.filters.up[0].len();
+        for (histories, channels) in [(&mut self.up, in_channels), (&mut self.side, side_channels)] {
+            if histories.len() != channels {
+                *histories = (0..channels).map(|_| History::new(up_len)).collect();
+            }
+        }
+        let down_len = self.filters.down.len();
+        if self.down.len() != out_channels {
+            self.down = (0..out_channels).map(|_| History::new(down_len)).collect();
+        }
+    }
+
+    fn is_silent(histories: &[History]) -> bool {
+        histories.iter().all(History::is_silent)
+    }
+}
+
+impl<D: Dsp, const FACTOR: usize> Dsp for Oversampled<D, FACTOR> {
+    wrapper_methods!(D: branding, own_parameters, read);
+
+    fn create() -> Self {
+        Oversampled::wrap(interop::with_rate_scale(FACTOR as u32, D::create))
+    }
+
+    fn try_create() -> Result<Self, DspError> {
+        Ok(Oversampled::wrap(interop::with_rate_scale(FACTOR as u32, D::try_create)?))
+    }
+
+    fn reset(&mut self) {
+        let _ = self.try_reset();
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.up.clear();
+        self.side.clear();
+        self.down.clear();
+        interop::with_rate_scale(FACTOR as u32, || {
+            for buf in [&mut self.high_in, &mut self.high_side, &mut self.high_out] {
+                buf.reserve_block();
+            }
+            self.inner.try_reset()
+        })
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        // input still held by the upsampling filter isn't silent yet
+        let idle = idle && Self::is_silent(&self.up) && Self::is_silent(&self.side);
+        self.inner_state = interop::with_rate_scale(FACTOR as u32, || self.inner.should_process(idle, incoming_length * FACTOR));
+        match self.inner_state {
+            // the downsampling filter may still be ringing
+            ProcessResult::SkipSilent if !Self::is_silent(&self.down) => ProcessResult::Continue,
+            // passing through would skip the filters, changing the latency
+            ProcessResult::SkipNoEffect => ProcessResult::Continue,
+            result => result,
+        }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        self.inner.preferred_out_channels()
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.quality.latency() + self.inner.latency_samples().div_ceil(FACTOR)
+    }
+
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+        let in_channels = input.channels();
+        let side_channels = sidechain.map_or(0, |it| it.channels());
+        let out_channels = output.channels();
+        let length = output.length();
+        self.prepare(in_channels, side_channels, out_channels);
+
+        upsample::<FACTOR>(&self.filters, &mut self.up, input, &mut self.high_in);
+        if let Some(sidechain) = sidechain {
+            upsample::<FACTOR>(&self.filters, &mut self.side, sidechain, &mut self.high_side);
+        }
+
+        self.high_out.resize_to_layout(length * FACTOR, output.layout());
+        // the output is still filtered on failure, keeping the filter state in step
+        let result = match self.inner_state {
+            ProcessResult::Continue => interop::with_rate_scale(FACTOR as u32, || {
+                let sidechain = sidechain.map(|_| self.high_side.as_signal());
+                self.inner.try_read(self.high_in.as_signal(), sidechain, self.high_out.as_signal_mut())
+            }),
+            ProcessResult::SkipNoEffect if in_channels == out_channels => {
+                self.high_out.as_signal_mut().copy(&self.high_in);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+
+        // filter, keeping only the first of every FACTOR samples
+        let high = self.high_out.as_signal();
+        for ((high, mut out), history) in output.zip_channels(&high).zip(&mut self.down) {
+            for (n, x) in high.iter().enumerate() {
+                history.push(x);
+                if n % FACTOR == 0 {
+                    out[n / FACTOR] = dot(&self.filters.down, history.window());
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,205 @@
+//! Processing in fixed-size blocks, independent of the mixer's block size.
+//!
+//! ```
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::reblock::Reblocked;
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//! use scamble::dsp::{Dsp, DspType};
+//!
+//! struct Blocky;
+//!
+//! impl Dsp for Blocky {
+//!     fn name() -> &'static str { "Blocky" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Blocky }
+//!
+//!     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+//!         assert_eq!(input.length(), 100);
+//!         output.samples_mut().copy_from_slice(input.samples());
+//!     }
+//! }
+//!
+//! let mut host = OfflineHost::<Reblocked<Blocky, 100>>::new(48000, 256);
+//! let input: Vec<f32> = (0..1000).map(|it| it as f32).collect();
+//! let rendered = host.render_compensated(SignalConst::new(&input, 1), 1000);
+//! assert_eq!(rendered.samples, input);
+//! ```
+
+use crate::dsp::layout::ChannelLayout;
+use crate::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
+use crate::dsp::{Dsp, DspError, ProcessResult, interop};
+
+/// Wraps a DSP so that its [Dsp::read] is always called with exactly `N` frames.
+///
+/// Input is collected until a full block is available, and output is buffered until the mixer asks
+/// for it, which adds `N - 1` frames of latency on top of the wrapped DSP's own. If the channel
+/// counts change, any buffered audio is dropped.
+///
+/// The wrapped DSP is queried once per mixer block rather than once per block of `N` frames, with
+/// the mixer's block length. It may then be read any number of times, including none. Sidechain
+/// input is buffered alongside the main input.
+pub struct Reblocked<D: Dsp, const N: usize> {
+    inner: D,
+    // how `inner` should be processed, as decided by the last query
+    inner_state: ProcessResult,
+    // interleaved input and sidechain not yet processed, less than a block
+    pending: Vec<f32>,
+    pending_side: Vec<f32>,
+    pending_frames: usize,
+    // interleaved output not yet handed to the mixer
+    ready: Vec<f32>,
+    in_channels: usize,
+    // zero if there is no sidechain
+    side_channels: usize,
+    out_channels: usize,
+    // layouts of the input, sidechain and output in the last read
+    layouts: [ChannelLayout; 3],
+    block_out: SignalBuf,
+}
+
+impl<D: Dsp, const N: usize> Reblocked<D, N> {
+    /// Gets the wrapped DSP.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Gets the wrapped DSP mutably.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    fn wrap(inner: D) -> Self {
+        const { assert!(N > 0, "Block size must be positive") };
+        let mut reblocked = Reblocked {
+            inner,
+            inner_state: ProcessResult::Continue,
+            pending: vec![],
+            pending_side: vec![],
+            pending_frames: 0,
+            ready: vec![],
+            in_channels: 0,
+            side_channels: 0,
+            out_channels: 0,
+            layouts: [ChannelLayout::raw(0); 3],
+            block_out: SignalBuf::new(),
+        };
+        reblocked.reserve();
+        reblocked
+    }
+
+    // reserves space for the mixer's largest block, so that reading doesn't allocate
+    fn reserve(&mut self) {
+        if let Some((frames, channels)) = interop::max_block() {
+            self.pending.reserve(N * channels);
+            self.pending_side.reserve(N * channels);
+            self.ready.reserve((N + frames) * channels);
+            self.block_out.reserve(N, channels);
+        }
+    }
+
+    // drops buffered audio, filling the output with silence for the latency
+    fn clear(&mut self, in_channels: usize, side_channels: usize, out_channels: usize) {
+        self.in_channels = in_channels;
+        self.side_channels = side_channels;
+        self.out_channels = out_channels;
+        self.pending.clear();
+        self.pending_side.clear();
+        self.pending_frames = 0;
+        self.ready.clear();
+        // a block is processed as soon as its last frame arrives, so one frame less than a block is enough
+        self.ready.resize((N - 1) * out_channels, 0.);
+    }
+
+    // processes the pending input as one block, keeping its output even if the wrapped DSP fails
+    fn process_block(&mut self) -> Result<(), DspError> {
+        let [in_layout, side_layout, out_layout] = self.layouts;
+        self.block_out.resize_to_layout(N, out_layout);
+        let input = SignalConst::new(&self.pending, self.in_channels).with_layout(in_layout);
+        let sidechain = (self.side_channels > 0)
+            .then(|| SignalConst::new(&self.pending_side, self.side_channels).with_layout(side_layout));
+        let mut output = self.block_out.as_signal_mut();
+        let result = match self.inner_state {
+            ProcessResult::Continue => self.inner.try_read(input, sidechain, output),
+            ProcessResult::SkipNoEffect if self.in_channels == self.out_channels => {
+                output.samples_mut().copy_from_slice(input.samples());
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        self.ready.extend_from_slice(self.block_out.samples());
+        self.pending.clear();
+        self.pending_side.clear();
+        self.pending_frames = 0;
+        result
+    }
+}
+
+impl<D: Dsp, const N: usize> Dsp for Reblocked<D, N> {
+    wrapper_methods!(D: branding, parameters, create, read);
+
+    fn reset(&mut self) {
+        let _ = self.try_reset();
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.clear(self.in_channels, self.side_channels, self.out_channels);
+        self.reserve();
+        self.inner.try_reset()
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        // input waiting for a full block isn't silent yet
+        let idle = idle && self.pending.iter().chain(&self.pending_side).all(|it| *it == 0.);
+        self.inner_state = self.inner.should_process(idle, incoming_length);
+        match self.inner_state {
+            // buffered output may still be audible
+            ProcessResult::SkipSilent if self.ready.iter().any(|it| *it != 0.) => ProcessResult::Continue,
+            // passing through would skip the buffering, changing the latency
+            ProcessResult::SkipNoEffect => ProcessResult::Continue,
+            result => result,
+        }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        self.inner.preferred_out_channels()
+    }
+
+    fn latency_samples(&self) -> usize {
+        N - 1 + self.inner.latency_samples()
+    }
+
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+        let in_channels = input.channels();
+        let side_channels = sidechain.map_or(0, |it| it.channels());
+        let out_channels = output.channels();
+        if (in_channels, side_channels, out_channels) != (self.in_channels, self.side_channels, self.out_channels) {
+            self.clear(in_channels, side_channels, out_channels);
+        }
+        let side_layout = sidechain.map_or(ChannelLayout::raw(0), |it| it.layout());
+        self.layouts = [input.layout(), side_layout, output.layout()];
+
+        let length = output.length();
+        let mut result = Ok(());
+        let mut taken = 0;
+        while taken < length {
+            let frames = (N - self.pending_frames).min(length - taken);
+            self.pending
+                .extend_from_slice(&input.samples()[taken * in_channels..(taken + frames) * in_channels]);
+            if let Some(sidechain) = sidechain {
+                self.pending_side
+                    .extend_from_slice(&sidechain.samples()[taken * side_channels..(taken + frames) * side_channels]);
+            }
+            self.pending_frames += frames;
+            taken += frames;
+            if self.pending_frames == N {
+                // the rest of the input is still buffered, so the latency stays the same
+                result = result.and(self.process_block());
+            }
+        }
+
+        output.samples_mut().copy_from_slice(&self.ready[..length * out_channels]);
+        self.ready.drain(..length * out_channels);
+        result
+    }
+}
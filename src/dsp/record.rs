@@ -0,0 +1,368 @@
+//! Recording what passes through any DSP to a WAV file, for debugging.
+//!
+//! ```
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::record::Recorded;
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//! use scamble::dsp::{Dsp, DspType};
+//!
+//! struct Invert;
+//!
+//! impl Dsp for Invert {
+//!     fn name() -> &'static str { "Invert" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Invert }
+//!
+//!     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+//!         output.map(&input, |x: f32| -x);
+//!     }
+//! }
+//!
+//! let path = std::env::temp_dir().join("scamble_record_example.wav");
+//! // "record" is first, as Invert has no parameters of its own
+//! let mut host = OfflineHost::<Recorded<Invert>>::new(48000, 4);
+//! host.set_data(2, path.to_str().unwrap().as_bytes());
+//! host.set_bool(0, true);
+//! host.render(SignalConst::new(&[0.5; 8], 1), 8);
+//! host.set_bool(0, false);
+//!
+//! let samples: Vec<f32> = hound::WavReader::open(&path).unwrap().samples().map(Result::unwrap).collect();
+//! assert_eq!(samples, [-0.5; 8]);
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+
+use crate::dsp::signal::{Signal, SignalConst, SignalMut};
+use crate::dsp::{Dsp, DspError, Parameter, ParameterType, ProcessResult, interop};
+use crate::enum_param;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// enough for a few seconds of audio, in case the writer falls behind
+const RING_SAMPLES: usize = 1 << 20;
+// how long the writer waits for more audio when the ring is empty
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Which signals a [Recorded] DSP writes to its file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// The input of the wrapped DSP.
+    Input,
+    /// The output of the wrapped DSP.
+    Output,
+    /// The input followed by the output, as separate channels of each frame.
+    Both,
+}
+
+/// Wraps a DSP to add `record`, `record_source` and `record_file` parameters after its own, which
+/// record what passes through it to a 32-bit float WAV file.
+///
+/// Audio is copied into a lock-free ring buffer on the mixer thread, and written to the file by a
+/// background thread, so that recording never blocks processing. If the writer falls behind far
+/// enough for the ring to fill, frames are dropped, and counted by [Recorded::dropped_frames].
+///
+/// The file's channel count is set by the first block recorded. Blocks with a different channel
+/// count, such as after changing the source, are dropped. Blocks that FMOD skips, as determined by
+/// the wrapped DSP's [Dsp::should_process], aren't recorded. With [Source::Both], a DSP without
+/// input, such as a generator, records only its output.
+///
+/// Toggling the `record` parameter calls [Recorded::start] or [Recorded::stop] from wherever FMOD
+/// sets parameters, which may be the mixer thread. Starting creates the file and allocates the ring,
+/// and stopping waits for the writer to finish the file, so either can block for as long as the
+/// file system takes. Recording is meant for debugging, where that's rarely a concern.
+pub struct Recorded<D: Dsp> {
+    inner: D,
+    source: Source,
+    file: String,
+    session: Option<Session>,
+    dropped: usize,
+}
+
+// a recording in progress
+struct Session {
+    ring: Arc<Ring>,
+    // the channel count of the file, once the first block is recorded
+    channels: Option<usize>,
+    writer: JoinHandle<hound::Result<()>>,
+}
+
+// a single-producer single-consumer queue of samples, shared between the mixer and writer threads
+struct Ring {
+    // samples are stored as bits, to share them without locking
+    samples: Box<[AtomicU32]>,
+    // total samples pushed by the mixer thread, and popped by the writer
+    written: AtomicUsize,
+    read: AtomicUsize,
+    channels: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+impl Ring {
+    fn new() -> Self {
+        Ring {
+            samples: (0..RING_SAMPLES).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            channels: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    // pushes all `len` samples, or none if there isn't enough space
+    fn push(&self, len: usize, samples: impl Iterator<Item = f32>) -> bool {
+        let written = self.written.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        if RING_SAMPLES - (written - read) < len {
+            return false;
+        }
+        for (i, sample) in samples.take(len).enumerate() {
+            self.samples[(written + i) % RING_SAMPLES].store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.written.store(written + len, Ordering::Release);
+        true
+    }
+
+    // pops all available samples onto the end of `out`
+    fn pop(&self, out: &mut Vec<f32>) {
+        let written = self.written.load(Ordering::Acquire);
+        let read = self.read.load(Ordering::Relaxed);
+        out.extend((read..written).map(|i| f32::from_bits(self.samples[i % RING_SAMPLES].load(Ordering::Relaxed))));
+        self.read.store(written, Ordering::Release);
+    }
+}
+
+// runs on the writer thread until the ring is stopped
+fn write(ring: &Ring, file: BufWriter<File>, sample_rate: u32) -> hound::Result<()> {
+    // the channel count isn't known until the first block arrives
+    let mut block = vec![];
+    let mut stopped = false;
+    while block.is_empty() && !stopped {
+        thread::sleep(POLL_INTERVAL);
+        // anything pushed before stopping is still written, as the flag is checked first
+        stopped = ring.stopped.load(Ordering::Acquire);
+        ring.pop(&mut block);
+    }
+
+    let spec = WavSpec {
+        channels: ring.channels.load(Ordering::Relaxed).max(1) as u16,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer = WavWriter::new(file, spec)?;
+    loop {
+        for sample in block.drain(..) {
+            writer.write_sample(sample)?;
+        }
+        if stopped {
+            return writer.finalize();
+        }
+        thread::sleep(POLL_INTERVAL);
+        stopped = ring.stopped.load(Ordering::Acquire);
+        ring.pop(&mut block);
+    }
+}
+
+impl<D: Dsp> Recorded<D> {
+    /// Gets the wrapped DSP.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Gets the wrapped DSP mutably.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    fn wrap(inner: D) -> Self {
+        Recorded {
+            inner,
+            source: Source::Output,
+            file: format!("{}.wav", D::name()),
+            session: None,
+            dropped: 0,
+        }
+    }
+
+    /// Gets the path that recordings are written to.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Sets the path that recordings are written to. Takes effect from the next [Recorded::start].
+    pub fn set_file(&mut self, file: impl Into<String>) {
+        self.file = file.into();
+    }
+
+    /// Gets which signals are recorded.
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Sets which signals are recorded.
+    pub fn set_source(&mut self, source: Source) {
+        self.source = source;
+    }
+
+    /// Checks whether a recording is in progress.
+    pub fn is_recording(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Gets the number of frames dropped from the current or last recording, because the writer
+    /// fell behind or the channel count changed.
+    pub fn dropped_frames(&self) -> usize {
+        self.dropped
+    }
+
+    /// Starts recording to [Recorded::file], replacing its contents. Any recording in progress is
+    /// stopped first.
+    pub fn start(&mut self) -> hound::Result<()> {
+        self.stop()?;
+        let file = BufWriter::new(File::create(&self.file)?);
+        let sample_rate = interop::sample_rate().unwrap_or(48000);
+        let ring = Arc::new(Ring::new());
+        let shared = ring.clone();
+        let writer = thread::Builder::new()
+            .name("scamble recorder".into())
+            .spawn(move || write(&shared, file, sample_rate))?;
+        self.session = Some(Session { ring, channels: None, writer });
+        self.dropped = 0;
+        Ok(())
+    }
+
+    /// Stops recording, waiting for the rest of the recording to be written. Returns any error
+    /// encountered while writing, or `Ok` if nothing was being recorded.
+    pub fn stop(&mut self) -> hound::Result<()> {
+        let Some(session) = self.session.take() else {
+            return Ok(());
+        };
+        session.ring.stopped.store(true, Ordering::Release);
+        // a panicking writer has nothing more to report
+        session.writer.join().unwrap_or(Ok(()))
+    }
+
+    fn set_recording(&mut self, recording: bool) {
+        let result = if recording {
+            if self.is_recording() { Ok(()) } else { self.start() }
+        } else {
+            self.stop()
+        };
+        if let Err(error) = result {
+            interop::log_error(&format!("Couldn't record to {}: {error}", self.file));
+        }
+    }
+
+    fn record(&mut self, input: &SignalConst, output: &SignalMut) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        let (ic, oc) = (input.channels(), output.channels());
+        let frames = output.length();
+        // there's no input to interleave for generators
+        let source = if ic == 0 && self.source == Source::Both {
+            Source::Output
+        } else {
+            self.source
+        };
+        let channels = match source {
+            Source::Input => ic,
+            Source::Output => oc,
+            Source::Both => ic + oc,
+        };
+        if channels == 0 {
+            return;
+        }
+        let fixed = *session.channels.get_or_insert_with(|| {
+            session.ring.channels.store(channels, Ordering::Relaxed);
+            channels
+        });
+        let len = frames * channels;
+        let pushed = fixed == channels
+            && match source {
+                Source::Input => session.ring.push(len, input.samples().iter().copied()),
+                Source::Output => session.ring.push(len, output.samples().iter().copied()),
+                Source::Both => {
+                    let both = input.samples().chunks_exact(ic).zip(output.samples().chunks_exact(oc));
+                    session.ring.push(len, both.flat_map(|(i, o)| i.iter().chain(o)).copied())
+                }
+            };
+        if !pushed {
+            self.dropped += frames;
+        }
+    }
+
+    fn own_parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter {
+                ty: ParameterType::Bool {
+                    default: false,
+                    names: None,
+                    setter: |value, dsp| dsp.set_recording(value),
+                    getter: |dsp| dsp.is_recording(),
+                },
+                name: "record",
+                unit: "",
+                desc: "Whether to record to the file, which blocks while starting or stopping",
+            },
+            Parameter {
+                ty: enum_param!(source: Source, options: [Input, Output, Both], default: Output),
+                name: "record_source",
+                unit: "",
+                desc: "Which signals to record",
+            },
+            Parameter {
+                ty: ParameterType::Data {
+                    setter: |data, dsp| dsp.file = String::from_utf8_lossy(data).into_owned(),
+                    getter: |dsp| Some((dsp.file.as_bytes(), None)),
+                },
+                name: "record_file",
+                unit: "",
+                desc: "The path to record to, as UTF-8",
+            },
+        ]
+    }
+}
+
+impl<D: Dsp> Drop for Recorded<D> {
+    fn drop(&mut self) {
+        // there's nowhere left to report errors to
+        let _ = self.stop();
+    }
+}
+
+impl<D: Dsp> Dsp for Recorded<D> {
+    wrapper_methods!(D: branding, own_parameters, create, read);
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        self.inner.try_reset()
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.inner.should_process(idle, incoming_length)
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        self.inner.preferred_out_channels()
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.inner.latency_samples()
+    }
+
+    fn try_read(&mut self, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+        // whatever was written is recorded, so the recording stays in time
+        let result = self.inner.try_read(input, sidechain, output.reborrow());
+        self.record(&input, &output);
+        result
+    }
+}
@@ -0,0 +1,214 @@
+//! Sample rate conversion, for playing audio recorded at a different rate to the mixer.
+//!
+//! [resample] converts a whole signal at once, such as a decoded sample. [Resampler] converts a
+//! stream block by block, and allows the rate to change between blocks, e.g. to vary playback speed.
+//!
+//! ```
+//! use scamble::dsp::resample::{Interpolation, resample};
+//! use scamble::dsp::signal::{Signal, SignalConst};
+//!
+//! let input = [0.5; 441];
+//! let output = resample(SignalConst::new(&input, 1), 44100, 48000, Interpolation::default());
+//! assert_eq!(output.length(), 480);
+//! // away from the edges of the input, constant signals are unchanged
+//! assert!(output.samples()[16..464].iter().all(|it| (it - 0.5).abs() < 1e-3));
+//! ```
+
+use crate::dsp::oversample::{History, Quality, dot};
+use crate::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
+use std::f32::consts::PI;
+
+/// How values between input samples are estimated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Interpolation {
+    /// Straight lines between neighbouring samples. Cheapest, but dulls high frequencies and
+    /// aliases when the rate is lowered.
+    Linear,
+    /// Catmull-Rom splines through the nearest four samples. Smoother than [Interpolation::Linear],
+    /// but still aliases when the rate is lowered.
+    Cubic,
+    /// Windowed-sinc lowpass filtering, which avoids aliasing in either direction. The quality
+    /// decides the length of the filter.
+    Sinc(Quality),
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation::Sinc(Quality::Medium)
+    }
+}
+
+impl Interpolation {
+    /// Gets the number of input frames used on either side of each output frame.
+    pub const fn half_width(self) -> usize {
+        match self {
+            Interpolation::Linear => 1,
+            Interpolation::Cubic => 2,
+            Interpolation::Sinc(quality) => quality.half_taps(),
+        }
+    }
+
+    // the weight of each input frame in the window for an output frame `frac` frames after the
+    // middle of the window, where `scale` is the cutoff relative to the input's Nyquist frequency
+    fn weights(self, frac: f32, scale: f32, weights: &mut [f32]) {
+        match self {
+            Interpolation::Linear => weights.copy_from_slice(&[1. - frac, frac]),
+            Interpolation::Cubic => {
+                let (t, t2, t3) = (frac, frac * frac, frac * frac * frac);
+                weights.copy_from_slice(&[
+                    (-t3 + 2. * t2 - t) / 2.,
+                    (3. * t3 - 5. * t2 + 2.) / 2.,
+                    (-3. * t3 + 4. * t2 + t) / 2.,
+                    (t3 - t2) / 2.,
+                ]);
+            }
+            Interpolation::Sinc(_) => {
+                let half = self.half_width() as f32;
+                for (k, weight) in weights.iter_mut().enumerate() {
+                    let x = k as f32 - (half - 1.) - frac;
+                    let sinc = if x == 0. { 1. } else { (PI * scale * x).sin() / (PI * scale * x) };
+                    let phase = PI * x / half;
+                    let blackman = 0.42 + 0.5 * phase.cos() + 0.08 * (2. * phase).cos();
+                    *weight = sinc * blackman;
+                }
+                // keep constant signals at the same level, whatever the phase
+                let sum: f32 = weights.iter().sum();
+                weights.iter_mut().for_each(|it| *it /= sum);
+            }
+        }
+    }
+}
+
+// the longest window any interpolation uses
+const MAX_WIDTH: usize = 2 * Quality::High.half_taps();
+
+/// Converts a stream of audio between sample rates, block by block.
+///
+/// The resampler reads [Resampler::ratio] input frames for every output frame, which can be changed
+/// between blocks. The first output frame lines up with the first input frame, but producing any
+/// output frame needs [Resampler::latency] input frames after it, so output lags behind input.
+#[derive(Clone)]
+pub struct Resampler {
+    interpolation: Interpolation,
+    ratio: f64,
+    // the most recent input frames of each channel
+    histories: Vec<History>,
+    // position of the next output frame, in input frames after the start of the histories' windows
+    offset: f64,
+}
+
+impl Resampler {
+    /// Creates a resampler that reads `ratio` input frames for every output frame.
+    pub fn new(interpolation: Interpolation, ratio: f64) -> Self {
+        assert!(ratio > 0., "Resampling ratio must be positive");
+        Resampler {
+            interpolation,
+            ratio,
+            histories: vec![],
+            offset: 0.,
+        }
+    }
+
+    /// Creates a resampler that converts from a sample rate of `from` to a sample rate of `to`.
+    pub fn between(interpolation: Interpolation, from: u32, to: u32) -> Self {
+        Self::new(interpolation, from as f64 / to as f64)
+    }
+
+    /// Gets the interpolation used.
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Gets the number of input frames read for every output frame.
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Sets the number of input frames read for every output frame, from the next output frame on.
+    /// A ratio above 1 speeds up playback, and below 1 slows it down.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        assert!(ratio > 0., "Resampling ratio must be positive");
+        self.ratio = ratio;
+    }
+
+    /// Gets the number of input frames needed after an output frame to produce it.
+    pub fn latency(&self) -> usize {
+        self.interpolation.half_width()
+    }
+
+    /// Forgets all previous input, so that the next output frame lines up with the next input frame.
+    pub fn reset(&mut self) {
+        self.histories.clear();
+    }
+
+    /// Converts as much of `input` as fits into `output`, returning the number of frames read from
+    /// `input` and the number written to `output`.
+    ///
+    /// Input frames that are read are kept for later output, so the rest of `input` should be
+    /// passed to the next call. If the number of channels changes, previous input is forgotten.
+    pub fn process(&mut self, input: SignalConst, mut output: SignalMut) -> (usize, usize) {
+        let channels = input.channels();
+        assert_eq!(channels, output.channels(), "Resampled input and output must have the same channels");
+        if channels == 0 {
+            return (0, 0);
+        }
+        let half = self.interpolation.half_width();
+        if self.histories.len() != channels {
+            self.histories = (0..channels).map(|_| History::new(2 * half)).collect();
+            // before any input, the first output frame is after the whole window
+            self.offset = (2 * half) as f64;
+        }
+
+        // lower the cutoff when lowering the rate, to filter out frequencies that can't be kept
+        let scale = (1. / self.ratio).min(1.) as f32;
+        let mut weights = [0.; MAX_WIDTH];
+        let weights = &mut weights[..2 * half];
+        let (mut read, mut written) = (0, 0);
+        let samples = input.samples();
+        while written < output.length() {
+            // take input until the output frame is in the middle of the window
+            while self.offset >= half as f64 {
+                if read == input.length() {
+                    return (read, written);
+                }
+                for (c, history) in self.histories.iter_mut().enumerate() {
+                    history.push(samples[read * channels + c]);
+                }
+                read += 1;
+                self.offset -= 1.;
+            }
+            self.interpolation.weights((self.offset - (half - 1) as f64) as f32, scale, weights);
+            let frame = &mut output.samples_mut()[written * channels..(written + 1) * channels];
+            for (out, history) in frame.iter_mut().zip(&self.histories) {
+                *out = dot(weights, history.window());
+            }
+            written += 1;
+            self.offset += self.ratio;
+        }
+        (read, written)
+    }
+}
+
+/// Converts a whole signal from a sample rate of `from` to a sample rate of `to`, keeping its layout.
+///
+/// The output is as long as the input in seconds, rounded up to a whole frame. Input past either
+/// end is taken to be silent.
+pub fn resample(input: SignalConst, from: u32, to: u32, interpolation: Interpolation) -> SignalBuf {
+    let mut output = SignalBuf::new();
+    let channels = input.channels();
+    if channels == 0 {
+        return output;
+    }
+    let frames = (input.length() as u64 * to as u64).div_ceil(from as u64) as usize;
+    output.resize_to_layout(frames, input.layout());
+
+    let mut resampler = Resampler::between(interpolation, from, to);
+    let (_, mut written) = resampler.process(input, output.as_signal_mut());
+    // the last frames need input past the end
+    let silence = vec![0.; (resampler.latency() + 1) * channels];
+    while written < frames {
+        let rest = SignalMut::new(&mut output.samples_mut()[written * channels..], channels);
+        written += resampler.process(SignalConst::new(&silence, channels), rest).1;
+    }
+    output
+}
@@ -1,5 +1,23 @@
 //! Types for handling interleaved multi-channel sample buffers.
 
+use crate::dsp::interop;
+use crate::dsp::layout::{ChannelLayout, MixMatrix, Mixed, Upmix, dot};
+use std::collections::VecDeque;
+use std::iter::{Copied, Skip, StepBy};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+use std::slice;
+#[cfg(feature = "simd")]
+use std::simd::num::SimdFloat;
+
+/// The number of samples processed at once by [Kernel::vector].
+#[cfg(feature = "simd")]
+pub const LANES: usize = 16;
+
+/// A vector of samples, processed at once by [Kernel::vector].
+#[cfg(feature = "simd")]
+pub type Vector = std::simd::Simd<f32, LANES>;
+
 /// A sample buffer with an associated channel count.
 pub trait Signal {
     /// Gets the number of channels of this buffer.
@@ -21,28 +39,206 @@ pub trait Signal {
             .map(move |slc| slc.iter().sum::<f32>() / ch)
     }
 
-    // TODO: get channel layout from FMOD - panning is potentially heavy?
-    /// Provides a stereo view of this buffer, down- or upmixing as necessary.
+    /// Provides a stereo view of this buffer, down- or upmixing from its [layout](Signal::layout)
+    /// as necessary.
     fn read_stereo(&self) -> (impl Iterator<Item = f32>, impl Iterator<Item = f32>) {
+        self.read_stereo_with(self.layout())
+    }
+
+    /// Provides a stereo view of this buffer as if it had the given layout, down- or upmixing as
+    /// described in [layout](crate::dsp::layout).
+    /// Panics if the layout has a different channel count.
+    fn read_stereo_with(&self, layout: ChannelLayout) -> (impl Iterator<Item = f32>, impl Iterator<Item = f32>) {
+        assert_eq!(layout.channels(), self.channels(), "Layout must have the same number of channels");
+        let matrix = MixMatrix::new(layout, ChannelLayout::STEREO, Upmix::default());
+        let (left, right) = (matrix.row(0), matrix.row(1));
         (
-            self.samples().chunks_exact(self.channels()).map(|slc| {
-                if self.channels() <= 2 {
-                    slc[0]
-                } else {
-                    slc.iter().sum::<f32>() / self.channels() as f32
-                }
-            }),
-            self.samples().chunks_exact(self.channels()).map(|slc| {
-                if self.channels() == 1 {
-                    slc[0]
-                } else if self.channels() == 2 {
-                    slc[1]
-                } else {
-                    slc.iter().sum::<f32>() / self.channels() as f32
-                }
-            }),
+            self.samples().chunks_exact(self.channels()).map(move |slc| dot(&left, slc)),
+            self.samples().chunks_exact(self.channels()).map(move |slc| dot(&right, slc)),
         )
     }
+
+    /// Gets the speaker layout of this buffer. Unless provided by FMOD, this is guessed from the
+    /// channel count.
+    fn layout(&self) -> ChannelLayout {
+        ChannelLayout::from_channels(self.channels())
+    }
+
+    /// Mixes this buffer from its [layout](Signal::layout) to another, producing interleaved samples.
+    fn mix_to(&self, layout: ChannelLayout, upmix: Upmix) -> Mixed<'_> {
+        Mixed::new(self.samples(), MixMatrix::new(self.layout(), layout, upmix))
+    }
+
+    /// Gets a view of a single channel of this buffer.
+    /// Panics if `channel` is not less than the channel count.
+    fn channel(&self, channel: usize) -> Channel<'_> {
+        assert!(channel < self.channels(), "Channel {channel} out of range for {} channels", self.channels());
+        Channel {
+            data: self.samples(),
+            channel,
+            channels: self.channels(),
+        }
+    }
+
+    /// Iterates over views of each channel of this buffer, in order.
+    fn iter_channels(&self) -> impl Iterator<Item = Channel<'_>> {
+        (0..self.channels()).map(|c| self.channel(c))
+    }
+
+    /// Gets the largest absolute value of any sample in this buffer, across all channels.
+    fn peak(&self) -> f32 {
+        kernels::peak(self.samples())
+    }
+
+    /// Gets the root mean square of all samples in this buffer, across all channels.
+    fn rms(&self) -> f32 {
+        let samples = self.samples();
+        if samples.is_empty() {
+            return 0.;
+        }
+        (kernels::sum_squares(samples) / samples.len() as f32).sqrt()
+    }
+}
+
+/// An element-wise operation on samples, used by [SignalMut::map] and [SignalMut::apply].
+///
+/// Any `Fn(f32) -> f32` is a kernel. With the `simd` feature, kernels can also implement
+/// [Kernel::vector] to process many samples at once; otherwise, each lane is processed separately.
+pub trait Kernel {
+    /// Applies the operation to a single sample.
+    fn scalar(&self, x: f32) -> f32;
+
+    /// Applies the operation to a vector of samples.
+    #[cfg(feature = "simd")]
+    fn vector(&self, x: Vector) -> Vector {
+        Vector::from_array(x.to_array().map(|it| self.scalar(it)))
+    }
+}
+
+impl<F: Fn(f32) -> f32> Kernel for F {
+    fn scalar(&self, x: f32) -> f32 {
+        self(x)
+    }
+}
+
+struct Gain(f32);
+
+impl Kernel for Gain {
+    fn scalar(&self, x: f32) -> f32 {
+        x * self.0
+    }
+
+    #[cfg(feature = "simd")]
+    fn vector(&self, x: Vector) -> Vector {
+        x * Vector::splat(self.0)
+    }
+}
+
+struct Clamp(f32, f32);
+
+impl Kernel for Clamp {
+    fn scalar(&self, x: f32) -> f32 {
+        x.clamp(self.0, self.1)
+    }
+
+    #[cfg(feature = "simd")]
+    fn vector(&self, x: Vector) -> Vector {
+        x.simd_clamp(Vector::splat(self.0), Vector::splat(self.1))
+    }
+}
+
+struct Abs;
+
+impl Kernel for Abs {
+    fn scalar(&self, x: f32) -> f32 {
+        x.abs()
+    }
+
+    #[cfg(feature = "simd")]
+    fn vector(&self, x: Vector) -> Vector {
+        x.abs()
+    }
+}
+
+// each operation runs on whole vectors first with the `simd` feature, and then on any samples
+// left over one at a time
+mod kernels {
+    use super::Kernel;
+    #[cfg(feature = "simd")]
+    use super::{LANES, SimdFloat, Vector};
+
+    pub(super) fn apply(data: &mut [f32], kernel: &impl Kernel) {
+        #[cfg(feature = "simd")]
+        let data = {
+            let (chunks, rest) = data.as_chunks_mut::<LANES>();
+            for chunk in chunks {
+                *chunk = kernel.vector(Vector::from_array(*chunk)).to_array();
+            }
+            rest
+        };
+        for x in data {
+            *x = kernel.scalar(*x);
+        }
+    }
+
+    pub(super) fn map(out: &mut [f32], input: &[f32], kernel: &impl Kernel) {
+        #[cfg(feature = "simd")]
+        let (out, input) = {
+            let (out_chunks, out_rest) = out.as_chunks_mut::<LANES>();
+            let (in_chunks, in_rest) = input.as_chunks::<LANES>();
+            for (out, x) in out_chunks.iter_mut().zip(in_chunks) {
+                *out = kernel.vector(Vector::from_array(*x)).to_array();
+            }
+            (out_rest, in_rest)
+        };
+        for (out, x) in out.iter_mut().zip(input) {
+            *out = kernel.scalar(*x);
+        }
+    }
+
+    pub(super) fn add_scaled(out: &mut [f32], input: &[f32], gain: f32) {
+        #[cfg(feature = "simd")]
+        let (out, input) = {
+            let (out_chunks, out_rest) = out.as_chunks_mut::<LANES>();
+            let (in_chunks, in_rest) = input.as_chunks::<LANES>();
+            for (out, x) in out_chunks.iter_mut().zip(in_chunks) {
+                *out = (Vector::from_array(*out) + Vector::from_array(*x) * Vector::splat(gain)).to_array();
+            }
+            (out_rest, in_rest)
+        };
+        for (out, x) in out.iter_mut().zip(input) {
+            *out += x * gain;
+        }
+    }
+
+    pub(super) fn peak(data: &[f32]) -> f32 {
+        #[cfg(feature = "simd")]
+        let (peak, data) = {
+            let (chunks, rest) = data.as_chunks::<LANES>();
+            let peak = chunks
+                .iter()
+                .fold(Vector::splat(0.), |peak, x| peak.simd_max(Vector::from_array(*x).abs()));
+            (peak.reduce_max(), rest)
+        };
+        #[cfg(not(feature = "simd"))]
+        let peak = 0.;
+        data.iter().fold(peak, |peak, x| peak.max(x.abs()))
+    }
+
+    pub(super) fn sum_squares(data: &[f32]) -> f32 {
+        #[cfg(feature = "simd")]
+        let (sum, data) = {
+            let (chunks, rest) = data.as_chunks::<LANES>();
+            let sum = chunks.iter().fold(Vector::splat(0.), |sum, x| {
+                let x = Vector::from_array(*x);
+                sum + x * x
+            });
+            (sum.reduce_sum(), rest)
+        };
+        #[cfg(not(feature = "simd"))]
+        let sum = 0.;
+        data.iter().fold(sum, |sum, x| sum + x * x)
+    }
 }
 
 /// A read-only sample buffer.
@@ -51,12 +247,14 @@ pub trait Signal {
 pub struct SignalConst<'a> {
     data: &'a [f32],
     channels: usize,
+    layout: Option<ChannelLayout>,
 }
 
 /// A read-write sample buffer.
 pub struct SignalMut<'a> {
     data: &'a mut [f32],
     channels: usize,
+    layout: Option<ChannelLayout>,
 }
 
 impl<'a> SignalConst<'a> {
@@ -69,7 +267,15 @@ impl<'a> SignalConst<'a> {
         } else {
             assert_eq!(data.len(), 0);
         }
-        SignalConst { data, channels }
+        SignalConst { data, channels, layout: None }
+    }
+
+    /// Sets the speaker layout of this buffer.
+    /// Panics if the layout has a different channel count.
+    pub fn with_layout(mut self, layout: ChannelLayout) -> Self {
+        assert_eq!(layout.channels(), self.channels, "Layout must have the same number of channels");
+        self.layout = Some(layout);
+        self
     }
 }
 
@@ -81,6 +287,10 @@ impl<'a> Signal for SignalConst<'a> {
     fn samples(&self) -> &[f32] {
         self.data
     }
+
+    fn layout(&self) -> ChannelLayout {
+        self.layout.unwrap_or_else(|| ChannelLayout::from_channels(self.channels))
+    }
 }
 
 impl<'a> SignalMut<'a> {
@@ -93,7 +303,15 @@ impl<'a> SignalMut<'a> {
         } else {
             assert_eq!(data.len(), 0);
         }
-        SignalMut { data, channels }
+        SignalMut { data, channels, layout: None }
+    }
+
+    /// Sets the speaker layout of this buffer.
+    /// Panics if the layout has a different channel count.
+    pub fn with_layout(mut self, layout: ChannelLayout) -> Self {
+        assert_eq!(layout.channels(), self.channels, "Layout must have the same number of channels");
+        self.layout = Some(layout);
+        self
     }
 
     /// Writes a sample to the buffer across all channels.
@@ -103,15 +321,103 @@ impl<'a> SignalMut<'a> {
         }
     }
 
+    /// Writes a frame to the buffer, with one sample for each channel.
+    /// Panics if `frame.len()` is not the channel count.
+    pub fn write_frame(&mut self, n: usize, frame: &[f32]) {
+        assert_eq!(frame.len(), self.channels, "Frame must have one sample per channel");
+        self.data[n * self.channels..(n + 1) * self.channels].copy_from_slice(frame);
+    }
+
+    /// Gets a mutable view of a single channel of this buffer.
+    /// Panics if `channel` is not less than the channel count.
+    pub fn channel_mut(&mut self, channel: usize) -> ChannelMut<'_> {
+        assert!(channel < self.channels, "Channel {channel} out of range for {} channels", self.channels);
+        ChannelMut::new(self.data, channel, self.channels)
+    }
+
+    /// Iterates over mutable views of each channel of this buffer, in order.
+    pub fn iter_channels_mut(&mut self) -> impl Iterator<Item = ChannelMut<'_>> {
+        let (data, channels) = (self.data.as_mut_ptr(), self.channels);
+        let len = self.data.len();
+        // each view only touches its own channel, so they never overlap
+        (0..channels).map(move |c| ChannelMut {
+            ptr: data.wrapping_add(c),
+            len: len / channels,
+            channels,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Iterates over each channel of `input` together with the same channel of this buffer, stopping
+    /// at whichever has fewer channels.
+    pub fn zip_channels<'s>(&'s mut self, input: &'s impl Signal) -> impl Iterator<Item = (Channel<'s>, ChannelMut<'s>)> {
+        input.iter_channels().zip(self.iter_channels_mut())
+    }
+
     /// Fills the channel with a constant value.
     pub fn fill(&mut self, v: f32) {
         self.data.fill(v);
     }
 
+    /// Applies a kernel to every sample of this buffer in place.
+    pub fn apply(&mut self, kernel: impl Kernel) {
+        kernels::apply(self.data, &kernel);
+    }
+
+    /// Writes the result of applying a kernel to every sample of `input`.
+    /// Panics if `input` has a different number of samples.
+    pub fn map(&mut self, input: &impl Signal, kernel: impl Kernel) {
+        assert_eq!(input.samples().len(), self.data.len(), "Input must have the same number of samples");
+        kernels::map(self.data, input.samples(), &kernel);
+    }
+
+    /// Multiplies every sample of this buffer by a gain.
+    pub fn gain(&mut self, gain: f32) {
+        self.apply(Gain(gain));
+    }
+
+    /// Limits every sample of this buffer to a range.
+    pub fn clamp(&mut self, min: f32, max: f32) {
+        self.apply(Clamp(min, max));
+    }
+
+    /// Replaces every sample of this buffer with its absolute value.
+    pub fn abs(&mut self) {
+        self.apply(Abs);
+    }
+
+    /// Copies the samples of `input` into this buffer.
+    /// Panics if `input` has a different number of samples.
+    pub fn copy(&mut self, input: &impl Signal) {
+        self.data.copy_from_slice(input.samples());
+    }
+
+    /// Adds the samples of `input` onto this buffer.
+    /// Panics if `input` has a different number of samples.
+    pub fn add(&mut self, input: &impl Signal) {
+        self.add_scaled(input, 1.);
+    }
+
+    /// Adds the samples of `input` onto this buffer, multiplied by a gain.
+    /// Panics if `input` has a different number of samples.
+    pub fn add_scaled(&mut self, input: &impl Signal, gain: f32) {
+        assert_eq!(input.samples().len(), self.data.len(), "Input must have the same number of samples");
+        kernels::add_scaled(self.data, input.samples(), gain);
+    }
+
     /// Gets the underlying sample data of this buffer mutably.
     pub fn samples_mut(&mut self) -> &mut [f32] {
         self.data
     }
+
+    /// Reborrows this buffer for a shorter lifetime, such as to pass it on to a wrapped DSP.
+    pub fn reborrow(&mut self) -> SignalMut<'_> {
+        SignalMut {
+            data: self.data,
+            channels: self.channels,
+            layout: self.layout,
+        }
+    }
 }
 
 impl<'a> Signal for SignalMut<'a> {
@@ -122,4 +428,326 @@ impl<'a> Signal for SignalMut<'a> {
     fn samples(&self) -> &[f32] {
         self.data
     }
+
+    fn layout(&self) -> ChannelLayout {
+        self.layout.unwrap_or_else(|| ChannelLayout::from_channels(self.channels))
+    }
+}
+
+/// An owned sample buffer with a channel count, for scratch audio.
+///
+/// Resizing never shrinks the allocation, so once capacity for the largest block has been reserved
+/// with [SignalBuf::for_block] or [SignalBuf::reserve_block], it can be resized in
+/// [Dsp::read](crate::dsp::Dsp::read) without allocating. Growing beyond that capacity allocates.
+#[derive(Clone, Debug, Default)]
+pub struct SignalBuf {
+    data: Vec<f32>,
+    channels: usize,
+    layout: Option<ChannelLayout>,
+}
+
+impl SignalBuf {
+    /// Creates an empty buffer with no channels.
+    pub fn new() -> Self {
+        SignalBuf::default()
+    }
+
+    /// Creates an empty buffer with space for `frames` samples of `channels` channels.
+    pub fn with_capacity(frames: usize, channels: usize) -> Self {
+        SignalBuf {
+            data: Vec::with_capacity(frames * channels),
+            channels,
+            layout: None,
+        }
+    }
+
+    /// Creates an empty buffer with space for the largest block the current DSP processes, as given
+    /// by [interop::max_block]. Should be called from [Dsp::create](crate::dsp::Dsp::create),
+    /// otherwise no space is reserved.
+    pub fn for_block() -> Self {
+        let mut buf = SignalBuf::new();
+        buf.reserve_block();
+        buf
+    }
+
+    /// Creates a buffer holding interleaved samples with a channel count.
+    /// Panics if `samples.len()` is not a multiple of `channels`, or if `channels` is `0` and
+    /// `samples.len() != 0`.
+    pub fn from_samples(samples: Vec<f32>, channels: usize) -> Self {
+        if channels != 0 {
+            assert_eq!(samples.len() % channels, 0);
+        } else {
+            assert_eq!(samples.len(), 0);
+        }
+        SignalBuf {
+            data: samples,
+            channels,
+            layout: None,
+        }
+    }
+
+    /// Reserves space for at least `frames` samples of `channels` channels in total.
+    pub fn reserve(&mut self, frames: usize, channels: usize) {
+        self.data.reserve((frames * channels).saturating_sub(self.data.len()));
+    }
+
+    /// Reserves space for the largest block the current DSP processes, as given by
+    /// [interop::max_block]. Should be called from [Dsp::reset](crate::dsp::Dsp::reset), in case the
+    /// mixer's block size changed, otherwise nothing is reserved.
+    pub fn reserve_block(&mut self) {
+        if let Some((frames, channels)) = interop::max_block() {
+            self.reserve(frames, channels);
+        }
+    }
+
+    /// Resizes this buffer to `frames` samples of `channels` channels, filled with silence.
+    /// The layout is guessed from the channel count.
+    pub fn resize(&mut self, frames: usize, channels: usize) {
+        self.data.clear();
+        self.data.resize(frames * channels, 0.);
+        self.channels = channels;
+        self.layout = None;
+    }
+
+    /// Resizes this buffer to `frames` samples of a layout, filled with silence.
+    pub fn resize_to_layout(&mut self, frames: usize, layout: ChannelLayout) {
+        self.resize(frames, layout.channels());
+        self.layout = Some(layout);
+    }
+
+    /// Replaces the contents of this buffer with a copy of `signal`, including its layout.
+    pub fn copy_from(&mut self, signal: &impl Signal) {
+        self.data.clear();
+        self.data.extend_from_slice(signal.samples());
+        self.channels = signal.channels();
+        self.layout = Some(signal.layout());
+    }
+
+    /// Gets the number of samples this buffer can hold without allocating, across all channels.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    /// Gets the underlying sample data of this buffer mutably.
+    pub fn samples_mut(&mut self) -> &mut [f32] {
+        &mut self.data
+    }
+
+    /// Borrows this buffer as a [SignalConst].
+    pub fn as_signal(&self) -> SignalConst<'_> {
+        SignalConst {
+            data: &self.data,
+            channels: self.channels,
+            layout: self.layout,
+        }
+    }
+
+    /// Borrows this buffer as a [SignalMut].
+    pub fn as_signal_mut(&mut self) -> SignalMut<'_> {
+        SignalMut {
+            data: &mut self.data,
+            channels: self.channels,
+            layout: self.layout,
+        }
+    }
+}
+
+impl Signal for SignalBuf {
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn samples(&self) -> &[f32] {
+        &self.data
+    }
+
+    fn layout(&self) -> ChannelLayout {
+        self.layout.unwrap_or_else(|| ChannelLayout::from_channels(self.channels))
+    }
+}
+
+/// A read-only view of one channel of an interleaved buffer.
+#[derive(Copy, Clone)]
+pub struct Channel<'a> {
+    data: &'a [f32],
+    channel: usize,
+    channels: usize,
+}
+
+impl<'a> Channel<'a> {
+    /// Gets the number of samples in this channel.
+    pub fn len(&self) -> usize {
+        self.data.len() / self.channels
+    }
+
+    /// Checks whether this channel has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the `n`th sample of this channel, if it exists.
+    pub fn get(&self, n: usize) -> Option<f32> {
+        self.data.get(n * self.channels + self.channel).copied()
+    }
+
+    /// Iterates over the samples of this channel.
+    pub fn iter(&self) -> impl Iterator<Item = f32> + 'a {
+        self.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for Channel<'a> {
+    type Item = f32;
+    type IntoIter = Copied<StepBy<Skip<slice::Iter<'a, f32>>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter().skip(self.channel).step_by(self.channels).copied()
+    }
+}
+
+impl Index<usize> for Channel<'_> {
+    type Output = f32;
+
+    fn index(&self, n: usize) -> &f32 {
+        assert!(n < self.len(), "Sample {n} out of range for {} samples", self.len());
+        &self.data[n * self.channels + self.channel]
+    }
+}
+
+/// A mutable view of one channel of an interleaved buffer.
+pub struct ChannelMut<'a> {
+    // the first sample of the channel, with each next sample `channels` further along
+    ptr: *mut f32,
+    len: usize,
+    channels: usize,
+    _marker: PhantomData<&'a mut [f32]>,
+}
+
+impl<'a> ChannelMut<'a> {
+    fn new(data: &'a mut [f32], channel: usize, channels: usize) -> Self {
+        ChannelMut {
+            ptr: data.as_mut_ptr().wrapping_add(channel),
+            len: data.len() / channels,
+            channels,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Gets the number of samples in this channel.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether this channel has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets the `n`th sample of this channel, if it exists.
+    pub fn get(&self, n: usize) -> Option<f32> {
+        (n < self.len).then(|| self[n])
+    }
+
+    /// Iterates over the samples of this channel.
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.len).map(|n| self[n])
+    }
+
+    /// Iterates over the samples of this channel mutably.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut f32> + '_ {
+        let (ptr, channels) = (self.ptr, self.channels);
+        // every index is in bounds and distinct, and `self` is borrowed for as long as they live
+        (0..self.len).map(move |n| unsafe { &mut *ptr.add(n * channels) })
+    }
+
+    /// Fills the channel with a constant value.
+    pub fn fill(&mut self, v: f32) {
+        self.iter_mut().for_each(|it| *it = v);
+    }
+
+    /// Overwrites the start of this channel with samples from an iterator, stopping at whichever
+    /// ends first.
+    pub fn copy_from(&mut self, samples: impl IntoIterator<Item = f32>) {
+        for (out, x) in self.iter_mut().zip(samples) {
+            *out = x;
+        }
+    }
+}
+
+impl Index<usize> for ChannelMut<'_> {
+    type Output = f32;
+
+    fn index(&self, n: usize) -> &f32 {
+        assert!(n < self.len, "Sample {n} out of range for {} samples", self.len);
+        // the index is in bounds of the buffer this was created from
+        unsafe { &*self.ptr.add(n * self.channels) }
+    }
+}
+
+impl IndexMut<usize> for ChannelMut<'_> {
+    fn index_mut(&mut self, n: usize) -> &mut f32 {
+        assert!(n < self.len, "Sample {n} out of range for {} samples", self.len);
+        // as above, and `self` is borrowed mutably
+        unsafe { &mut *self.ptr.add(n * self.channels) }
+    }
+}
+
+/// A delay for interleaved signals, whose length may change between blocks.
+pub(crate) struct DelayLine {
+    buffer: VecDeque<f32>,
+    channels: usize,
+}
+
+impl DelayLine {
+    pub(crate) const fn new() -> Self {
+        DelayLine {
+            buffer: VecDeque::new(),
+            channels: 0,
+        }
+    }
+
+    /// Delays a block of interleaved samples in place by `delay` frames. Changing the delay
+    /// drops or inserts silence at the start of the block, and changing the channel count
+    /// discards anything buffered.
+    pub(crate) fn process(&mut self, samples: &mut [f32], channels: usize, delay: usize) {
+        if channels == 0 {
+            return;
+        }
+        if channels != self.channels {
+            self.buffer.clear();
+            self.channels = channels;
+        }
+        let buffered = self.buffer.len() / channels;
+        if buffered < delay {
+            for _ in 0..(delay - buffered) * channels {
+                self.buffer.push_front(0.);
+            }
+        } else {
+            self.buffer.drain(..(buffered - delay) * channels);
+        }
+        let len = samples.len();
+        self.buffer.extend(samples.iter());
+        for (sample, delayed) in samples.iter_mut().zip(self.buffer.drain(..len)) {
+            *sample = delayed;
+        }
+    }
+
+    /// Reserves space for a delay of `delay` frames over the largest block the current DSP
+    /// processes, as given by [interop::max_block], so that processing with up to that delay
+    /// doesn't allocate. Does nothing outside of a DSP's callbacks.
+    pub(crate) fn reserve(&mut self, delay: usize) {
+        if let Some((frames, channels)) = interop::max_block() {
+            let needed = (frames + delay) * channels;
+            self.buffer.reserve(needed.saturating_sub(self.buffer.len()));
+        }
+    }
+
+    /// Checks whether everything buffered is silent.
+    pub(crate) fn is_silent(&self) -> bool {
+        self.buffer.iter().all(|it| *it == 0.)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buffer.clear();
+    }
 }
@@ -0,0 +1,269 @@
+//! Short-time Fourier transform processing, with windowed overlap-add resynthesis.
+//!
+//! An [Stft] splits a signal into overlapping windowed frames, hands the spectrum of each frame to
+//! a callback, and sums the modified frames back together. DSPs keep one as a field and call it
+//! from [Dsp::read](crate::dsp::Dsp::read).
+//!
+//! ```
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+//! use scamble::dsp::spectral::{Stft, Window};
+//! use scamble::dsp::{Dsp, DspType};
+//!
+//! // removes everything but the lowest frequencies
+//! struct Lowpass {
+//!     stft: Stft,
+//! }
+//!
+//! impl Dsp for Lowpass {
+//!     fn name() -> &'static str { "Lowpass" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Lowpass { stft: Stft::new(256, 64, Window::Hann) } }
+//!     fn latency_samples(&self) -> usize { self.stft.latency() }
+//!
+//!     fn read(&mut self, input: SignalConst, output: SignalMut) {
+//!         self.stft.process(input, output, |_channel, bins| bins[4..].fill(Default::default()));
+//!     }
+//! }
+//!
+//! let mut host = OfflineHost::<Lowpass>::new(48000, 100);
+//! let rendered = host.render_compensated(SignalConst::new(&[0.5; 1000], 1), 1000);
+//! // away from the edges of the input, constant signals pass through unchanged
+//! assert!(rendered.samples[256..700].iter().all(|it| (it - 0.5).abs() < 1e-4));
+//! ```
+
+use crate::dsp::interop;
+use crate::dsp::signal::{Signal, SignalConst, SignalMut};
+pub use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// A window applied to each frame, both before analysis and after resynthesis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Window {
+    /// No windowing. Frames are processed as independent blocks, which may cause clicks at their edges.
+    Rectangular,
+    /// A Hann window, suited to hop sizes of a quarter of the FFT size or less.
+    Hann,
+    /// The square root of a Hann window, suited to hop sizes of half the FFT size.
+    SqrtHann,
+}
+
+impl Window {
+    /// Computes the coefficients of this window for a given frame length.
+    pub fn coefficients(self, len: usize) -> Vec<f32> {
+        // periodic windows, which overlap-add evenly
+        let hann = |n: usize| 0.5 - 0.5 * (2. * PI * n as f32 / len as f32).cos();
+        (0..len)
+            .map(|n| match self {
+                Window::Rectangular => 1.,
+                Window::Hann => hann(n),
+                Window::SqrtHann => hann(n).sqrt(),
+            })
+            .collect()
+    }
+}
+
+/// Streaming short-time Fourier transform processing.
+///
+/// Every `hop_size` samples, the last `fft_size` samples of each channel are windowed and transformed,
+/// and the resulting `fft_size / 2 + 1` bins are passed to a callback to be modified in place. Bins are
+/// the output of an unnormalized forward transform; normalization and the gain of overlapping windows
+/// are compensated for on resynthesis.
+///
+/// Output is delayed by [Stft::latency] samples. If the input has a different channel count from the
+/// output, it's downmixed to mono for every output channel.
+///
+/// Buffers for each channel are allocated when the output's channel count changes. To keep processing
+/// from allocating, they can be allocated up front with [Stft::reserve] or [Stft::reserve_block].
+pub struct Stft {
+    fft_size: usize,
+    hop_size: usize,
+    window: Vec<f32>,
+    // gain applied to resynthesized frames
+    scale: f32,
+    fwd: Arc<dyn RealToComplex<f32>>,
+    bwd: Arc<dyn ComplexToReal<f32>>,
+    channels: Vec<ChannelState>,
+    bins: Vec<Vec<Complex<f32>>>,
+    // position in the input buffers of the next sample, from `fft_size - hop_size` up to the FFT size
+    pos: usize,
+    time: Vec<f32>,
+    scratch: Vec<Complex<f32>>,
+}
+
+struct ChannelState {
+    // the last `fft_size` input samples
+    input: Vec<f32>,
+    // overlapping resynthesized frames
+    accum: Vec<f32>,
+    // completed output, read out over the next hop
+    ready: Vec<f32>,
+}
+
+impl Stft {
+    /// Creates an STFT processor for a given FFT size, hop size, and window.
+    ///
+    /// The FFT size must be even, and the hop size must be between one sample and the FFT size.
+    pub fn new(fft_size: usize, hop_size: usize, window: Window) -> Self {
+        assert!(fft_size > 0 && fft_size.is_multiple_of(2), "FFT size must be even");
+        assert!((1..=fft_size).contains(&hop_size), "Hop size must be between 1 and the FFT size");
+        let mut planner = RealFftPlanner::new();
+        let fwd = planner.plan_fft_forward(fft_size);
+        let bwd = planner.plan_fft_inverse(fft_size);
+        let window = window.coefficients(fft_size);
+        // each output sample is the sum of `fft_size / hop_size` squared windows
+        let overlap = window.iter().map(|it| it * it).sum::<f32>() / hop_size as f32;
+        let scratch_len = fwd.get_scratch_len().max(bwd.get_scratch_len());
+        Stft {
+            fft_size,
+            hop_size,
+            window,
+            scale: 1. / (overlap * fft_size as f32),
+            fwd,
+            bwd,
+            channels: vec![],
+            bins: vec![],
+            pos: fft_size - hop_size,
+            time: vec![0.; fft_size],
+            scratch: vec![Complex::default(); scratch_len],
+        }
+    }
+
+    /// Gets the number of samples in each frame.
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Gets the number of samples between the starts of consecutive frames.
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Gets the number of frequency bins passed to the callback for each frame.
+    pub fn bins(&self) -> usize {
+        self.fft_size / 2 + 1
+    }
+
+    /// Gets the delay between input and output, in samples.
+    pub fn latency(&self) -> usize {
+        // the first sample of a frame is only complete once the whole frame has been processed
+        self.fft_size - 1
+    }
+
+    /// Clears all buffered audio.
+    pub fn reset(&mut self) {
+        for state in &mut self.channels {
+            state.input.fill(0.);
+            state.accum.fill(0.);
+            state.ready.fill(0.);
+        }
+        self.pos = self.fft_size - self.hop_size;
+    }
+
+    /// Allocates buffers for `channels` output channels, so that processing that many doesn't
+    /// allocate. Buffered audio is cleared if the channel count changes.
+    pub fn reserve(&mut self, channels: usize) {
+        if self.channels.len() == channels {
+            return;
+        }
+        self.reset();
+        self.channels = (0..channels)
+            .map(|_| ChannelState {
+                input: vec![0.; self.fft_size],
+                accum: vec![0.; self.fft_size],
+                ready: vec![0.; self.hop_size],
+            })
+            .collect();
+        self.bins = vec![vec![Complex::default(); self.bins()]; channels];
+    }
+
+    /// Allocates buffers for the channel count of the mixer, as given by [interop::max_block].
+    /// Should be called from [Dsp::create](crate::dsp::Dsp::create), otherwise nothing is allocated.
+    pub fn reserve_block(&mut self) {
+        if let Some((_, channels)) = interop::max_block() {
+            self.reserve(channels);
+        }
+    }
+
+    /// Returns whether all buffered input and output is silent.
+    pub fn is_silent(&self) -> bool {
+        self.channels
+            .iter()
+            .all(|it| it.input.iter().chain(&it.accum).chain(&it.ready).all(|it| *it == 0.))
+    }
+
+    /// Processes a block of audio, modifying the spectrum of each channel independently.
+    ///
+    /// `process_frame` is called with the channel index and its bins for every channel of every frame.
+    pub fn process(&mut self, input: SignalConst, output: SignalMut, mut process_frame: impl FnMut(usize, &mut [Complex<f32>])) {
+        self.process_linked(input, output, |channels| {
+            for (c, bins) in channels.iter_mut().enumerate() {
+                process_frame(c, bins);
+            }
+        });
+    }
+
+    /// Processes a block of audio, modifying the spectra of all channels together.
+    ///
+    /// `process_frame` is called once per frame with the bins of every channel, for effects that
+    /// compare or combine channels.
+    pub fn process_linked(&mut self, input: SignalConst, mut output: SignalMut, mut process_frame: impl FnMut(&mut [Vec<Complex<f32>>])) {
+        let channels = output.channels();
+        self.reserve(channels);
+        if channels == 0 {
+            return;
+        }
+
+        let start = self.fft_size - self.hop_size;
+        let in_channels = input.channels();
+        for (i, frame) in output.samples_mut().chunks_exact_mut(channels).enumerate() {
+            let in_frame = input.samples().get(i * in_channels..(i + 1) * in_channels).unwrap_or(&[]);
+            let mono = in_frame.iter().sum::<f32>() / in_channels.max(1) as f32;
+            for (c, state) in self.channels.iter_mut().enumerate() {
+                // input past its end is silent
+                state.input[self.pos] = match in_frame.get(c) {
+                    Some(x) if in_channels == channels => *x,
+                    _ => mono,
+                };
+            }
+            self.pos += 1;
+            if self.pos == self.fft_size {
+                self.pos = start;
+                self.process_frame(&mut process_frame);
+            }
+            for (out, state) in frame.iter_mut().zip(&self.channels) {
+                *out = state.ready[self.pos - start];
+            }
+        }
+    }
+
+    fn process_frame(&mut self, process_frame: &mut impl FnMut(&mut [Vec<Complex<f32>>])) {
+        for (state, bins) in self.channels.iter_mut().zip(&mut self.bins) {
+            for ((time, x), w) in self.time.iter_mut().zip(&state.input).zip(&self.window) {
+                *time = x * w;
+            }
+            self.fwd.process_with_scratch(&mut self.time, bins, &mut self.scratch).unwrap();
+            state.input.copy_within(self.hop_size.., 0);
+        }
+
+        process_frame(&mut self.bins);
+
+        let hop = self.hop_size;
+        for (state, bins) in self.channels.iter_mut().zip(&mut self.bins) {
+            // the inverse transform requires these to be real
+            bins[0].im = 0.;
+            bins[self.fft_size / 2].im = 0.;
+            self.bwd.process_with_scratch(bins, &mut self.time, &mut self.scratch).unwrap();
+            for ((acc, x), w) in state.accum.iter_mut().zip(&self.time).zip(&self.window) {
+                *acc += x * w * self.scale;
+            }
+            state.ready.copy_from_slice(&state.accum[..hop]);
+            state.accum.copy_within(hop.., 0);
+            let len = state.accum.len();
+            state.accum[len - hop..].fill(0.);
+        }
+    }
+}
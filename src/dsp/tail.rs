@@ -0,0 +1,132 @@
+//! Tracking of effect tails, for deciding when a DSP can stop processing after its input goes silent.
+//!
+//! ```
+//! use scamble::dsp::{Dsp, DspType, ProcessResult};
+//! use scamble::dsp::signal::{SignalConst, SignalMut};
+//! use scamble::dsp::tail::TailTracker;
+//!
+//! struct Echo {
+//!     tail: TailTracker,
+//!     // ...
+//! }
+//!
+//! impl Dsp for Echo {
+//!     fn name() -> &'static str { "Echo" }
+//!     fn version() -> u32 { 1 }
+//!     fn ty() -> DspType { DspType::Effect }
+//!     fn create() -> Self { Echo { tail: TailTracker::seconds(2.) } }
+//!
+//!     fn reset(&mut self) {
+//!         self.tail.reset();
+//!     }
+//!
+//!     fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+//!         self.tail.should_process(idle, incoming_length)
+//!     }
+//!
+//!     fn read(&mut self, input: SignalConst, output: SignalMut) {
+//!         // ...
+//!     }
+//! }
+//!
+//! // outside of FMOD, the tail is converted at 48 kHz
+//! let mut echo = Echo::create();
+//! assert_eq!(echo.tail.tail_samples(), 96000);
+//! assert_eq!(echo.should_process(false, 1024), ProcessResult::Continue);
+//! // the echo rings on for 2 seconds of silence, then stops
+//! let ringing = (0..).take_while(|_| echo.should_process(true, 1024) == ProcessResult::Continue).count();
+//! assert_eq!(ringing, 93);
+//! assert!(echo.tail.finished());
+//! ```
+
+use crate::dsp::{ProcessResult, interop};
+
+// used when the tail is given in seconds but no sample rate is available, e.g. outside FMOD
+const FALLBACK_SAMPLE_RATE: u32 = 48000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Tail {
+    Samples(usize),
+    Seconds(f32),
+}
+
+/// Counts how long a DSP's input has been silent, so that it keeps processing for the length of
+/// its tail and then reports silence.
+///
+/// Stopping too early cuts off the tail of a sound, and never stopping prolongs events
+/// indefinitely (see [crate::dsp::Dsp::should_process]).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TailTracker {
+    tail: Tail,
+    silence: usize,
+}
+
+impl TailTracker {
+    /// Creates a tail tracker with a tail of `samples` samples.
+    pub const fn samples(samples: usize) -> Self {
+        TailTracker {
+            tail: Tail::Samples(samples),
+            silence: 0,
+        }
+    }
+
+    /// Creates a tail tracker with a tail of `seconds` seconds, converted to samples using the
+    /// mixer's sample rate (see [interop::sample_rate]).
+    pub const fn seconds(seconds: f32) -> Self {
+        TailTracker {
+            tail: Tail::Seconds(seconds),
+            silence: 0,
+        }
+    }
+
+    /// Gets the length of the tail in samples.
+    pub fn tail_samples(&self) -> usize {
+        match self.tail {
+            Tail::Samples(samples) => samples,
+            Tail::Seconds(seconds) => {
+                let rate = interop::sample_rate().unwrap_or(FALLBACK_SAMPLE_RATE);
+                (seconds.max(0.) * rate as f32).ceil() as usize
+            }
+        }
+    }
+
+    /// Gets the number of samples of silent input seen since the last non-silent input.
+    pub fn silence(&self) -> usize {
+        self.silence
+    }
+
+    /// Checks whether the tail has finished playing out.
+    pub fn finished(&self) -> bool {
+        self.silence >= self.tail_samples()
+    }
+
+    /// Updates the tracker for a block of input, and decides whether to continue processing.
+    /// Processing continues until the input has been silent for the length of the tail.
+    ///
+    /// Arguments are as in [crate::dsp::Dsp::should_process].
+    pub fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.should_process_while(idle, incoming_length, || false)
+    }
+
+    /// Like [TailTracker::should_process], but also continues processing while `ringing` returns
+    /// `true`, such as when the DSP holds on to state beyond a fixed tail. `ringing` is only called
+    /// once the tail has finished.
+    pub fn should_process_while(&mut self, idle: bool, incoming_length: usize, ringing: impl FnOnce() -> bool) -> ProcessResult {
+        if idle {
+            self.silence = self.silence.saturating_add(incoming_length);
+            if self.finished() && !ringing() {
+                ProcessResult::SkipSilent
+            } else {
+                ProcessResult::Continue
+            }
+        } else {
+            self.silence = 0;
+            ProcessResult::Continue
+        }
+    }
+
+    /// Forgets any previous silence, such as when the DSP is reset.
+    pub fn reset(&mut self) {
+        self.silence = 0;
+    }
+}
@@ -0,0 +1,622 @@
+//! Data parameters holding structured values, encoded in a choice of formats.
+//!
+//! Values convert to and from a [Value] tree through [Structured], which [Codec]s encode to bytes.
+//! A [Typed] field stores a value along with its encoding, and is exposed with
+//! [typed_param](crate::typed_param).
+//!
+//! ```
+//! use scamble::dsp::typed::{Binary, Codec, Json, Structured, Value};
+//!
+//! struct Envelope {
+//!     attack: f32,
+//!     points: Vec<f32>,
+//! }
+//!
+//! impl Structured for Envelope {
+//!     fn to_value(&self) -> Value {
+//!         Value::Map(vec![("attack".into(), self.attack.to_value()), ("points".into(), self.points.to_value())])
+//!     }
+//!
+//!     fn from_value(value: &Value) -> Option<Self> {
+//!         Some(Envelope {
+//!             attack: value.get("attack").and_then(f32::from_value)?,
+//!             points: value.get("points").and_then(Vec::from_value)?,
+//!         })
+//!     }
+//! }
+//!
+//! let envelope = Envelope { attack: 0.5, points: vec![1., 0.25] };
+//! let mut json = vec![];
+//! Json::encode(&envelope.to_value(), &mut json);
+//! assert_eq!(json, br#"{"attack":0.5,"points":[1.0,0.25]}"#);
+//!
+//! let mut binary = vec![];
+//! Binary::encode(&envelope.to_value(), &mut binary);
+//! let decoded = Envelope::from_value(&Binary::decode(&binary).unwrap()).unwrap();
+//! assert_eq!(decoded.points, [1., 0.25]);
+//!
+//! // JSON is easier to write by hand
+//! let decoded = Envelope::from_value(&Json::decode(br#"{ "points": [], "attack": 2 }"#).unwrap()).unwrap();
+//! assert_eq!(decoded.attack, 2.);
+//! ```
+
+use std::fmt::Write;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// A structured value, as encoded by a [Codec].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// No value.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    Int(i64),
+    /// A floating-point number.
+    Float(f64),
+    /// A UTF-8 string.
+    String(String),
+    /// An ordered list of values.
+    List(Vec<Value>),
+    /// Named values, in order.
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Gets the value of a key in a [Value::Map], or [None] if the key is missing or this isn't a map.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// A type that can be converted to and from a [Value].
+///
+/// Integers are stored as [Value::Int]s, so unsigned values above [i64::MAX] saturate to it when
+/// converted, and don't convert back to their original value.
+pub trait Structured: Sized {
+    /// Converts this to a value.
+    fn to_value(&self) -> Value;
+
+    /// Converts a value back, or returns [None] if it doesn't have the expected shape.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl Structured for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl Structured for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! structured_int {
+    ($($t:ty),*) => {
+        $(
+            impl Structured for $t {
+                fn to_value(&self) -> Value {
+                    // only the largest unsigned values don't fit, and saturate as documented on `Structured`
+                    Value::Int(i64::try_from(*self).unwrap_or(i64::MAX))
+                }
+
+                fn from_value(value: &Value) -> Option<Self> {
+                    match value {
+                        Value::Int(i) => <$t>::try_from(*i).ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+structured_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! structured_float {
+    ($($t:ty),*) => {
+        $(
+            impl Structured for $t {
+                fn to_value(&self) -> Value {
+                    Value::Float(*self as f64)
+                }
+
+                fn from_value(value: &Value) -> Option<Self> {
+                    // integers are accepted too, as text formats may not tell them apart
+                    match value {
+                        Value::Float(f) => Some(*f as $t),
+                        Value::Int(i) => Some(*i as $t),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+structured_float!(f32, f64);
+
+impl Structured for String {
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Structured> Structured for Option<T> {
+    fn to_value(&self) -> Value {
+        self.as_ref().map_or(Value::Null, T::to_value)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: Structured> Structured for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::List(items) => items.iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// The deepest nesting of lists and maps that [Codec]s decode. Deeper data is rejected rather than
+/// risking a stack overflow in the host while decoding it.
+pub const MAX_DEPTH: usize = 128;
+
+/// A format for encoding [Value]s as bytes.
+pub trait Codec {
+    /// Appends the encoding of a value to `out`.
+    fn encode(value: &Value, out: &mut Vec<u8>);
+
+    /// Decodes a value, or returns [None] if the data is invalid, nests lists and maps deeper than
+    /// [MAX_DEPTH], or has anything after the value.
+    fn decode(data: &[u8]) -> Option<Value>;
+}
+
+/// A compact binary format. Each value is a tag byte followed by its contents in little-endian
+/// order, with lengths as 32-bit integers.
+pub struct Binary;
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const FLOAT: u8 = 4;
+const STRING: u8 = 5;
+const LIST: u8 = 6;
+const MAP: u8 = 7;
+
+impl Binary {
+    fn encode_str(s: &str, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    fn take<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {
+        let (taken, rest) = data.split_first_chunk()?;
+        *data = rest;
+        Some(*taken)
+    }
+
+    fn decode_len(data: &mut &[u8]) -> Option<usize> {
+        let len = u32::from_le_bytes(Self::take(data)?) as usize;
+        // every entry takes at least a byte, so larger lengths can't be valid
+        (len <= data.len()).then_some(len)
+    }
+
+    fn decode_str(data: &mut &[u8]) -> Option<String> {
+        let len = Self::decode_len(data)?;
+        let (s, rest) = data.split_at(len);
+        *data = rest;
+        String::from_utf8(s.to_vec()).ok()
+    }
+
+    // `depth` counts the lists and maps this value is inside
+    fn decode_value(data: &mut &[u8], depth: usize) -> Option<Value> {
+        let [tag] = Self::take(data)?;
+        if matches!(tag, LIST | MAP) && depth >= MAX_DEPTH {
+            return None;
+        }
+        Some(match tag {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            INT => Value::Int(i64::from_le_bytes(Self::take(data)?)),
+            FLOAT => Value::Float(f64::from_le_bytes(Self::take(data)?)),
+            STRING => Value::String(Self::decode_str(data)?),
+            LIST => {
+                let len = Self::decode_len(data)?;
+                Value::List((0..len).map(|_| Self::decode_value(data, depth + 1)).collect::<Option<_>>()?)
+            }
+            MAP => {
+                let len = Self::decode_len(data)?;
+                Value::Map(
+                    (0..len)
+                        .map(|_| Some((Self::decode_str(data)?, Self::decode_value(data, depth + 1)?)))
+                        .collect::<Option<_>>()?,
+                )
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl Codec for Binary {
+    fn encode(value: &Value, out: &mut Vec<u8>) {
+        match value {
+            Value::Null => out.push(NULL),
+            Value::Bool(b) => out.push(if *b { TRUE } else { FALSE }),
+            Value::Int(i) => {
+                out.push(INT);
+                out.extend_from_slice(&i.to_le_bytes());
+            }
+            Value::Float(f) => {
+                out.push(FLOAT);
+                out.extend_from_slice(&f.to_le_bytes());
+            }
+            Value::String(s) => {
+                out.push(STRING);
+                Self::encode_str(s, out);
+            }
+            Value::List(items) => {
+                out.push(LIST);
+                out.extend_from_slice(&(items.len() as u32).to_le_bytes());
+                for item in items {
+                    Self::encode(item, out);
+                }
+            }
+            Value::Map(entries) => {
+                out.push(MAP);
+                out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+                for (key, value) in entries {
+                    Self::encode_str(key, out);
+                    Self::encode(value, out);
+                }
+            }
+        }
+    }
+
+    fn decode(mut data: &[u8]) -> Option<Value> {
+        let value = Self::decode_value(&mut data, 0)?;
+        data.is_empty().then_some(value)
+    }
+}
+
+/// A JSON format, readable and editable by hand. Maps are written as objects, keeping their order.
+///
+/// Numbers with a fraction or exponent are read as [Value::Float]s, and others as [Value::Int]s.
+/// Non-finite floats, which JSON can't represent, are written as `null`.
+pub struct Json;
+
+impl Json {
+    fn encode_str(s: &str, out: &mut String) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if c < ' ' => _ = write!(out, "\\u{:04x}", c as u32),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    fn encode_value(value: &Value, out: &mut String) {
+        match value {
+            Value::Null => out.push_str("null"),
+            Value::Bool(b) => _ = write!(out, "{b}"),
+            Value::Int(i) => _ = write!(out, "{i}"),
+            // debug formatting keeps the fraction of whole numbers, so they read back as floats
+            Value::Float(f) if f.is_finite() => _ = write!(out, "{f:?}"),
+            Value::Float(_) => out.push_str("null"),
+            Value::String(s) => Self::encode_str(s, out),
+            Value::List(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    Self::encode_value(item, out);
+                }
+                out.push(']');
+            }
+            Value::Map(entries) => {
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    Self::encode_str(key, out);
+                    out.push(':');
+                    Self::encode_value(value, out);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl Codec for Json {
+    fn encode(value: &Value, out: &mut Vec<u8>) {
+        let mut text = String::new();
+        Self::encode_value(value, &mut text);
+        out.extend_from_slice(text.as_bytes());
+    }
+
+    fn decode(data: &[u8]) -> Option<Value> {
+        let mut parser = JsonParser {
+            text: str::from_utf8(data).ok()?.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        (parser.pos == parser.text.len()).then_some(value)
+    }
+}
+
+struct JsonParser<'a> {
+    // valid UTF-8, so slices between ASCII delimiters are too
+    text: &'a [u8],
+    pos: usize,
+    // the number of lists and maps being parsed
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.text[self.pos..].starts_with(token.as_bytes());
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    // parses a comma-separated sequence up to `end`, after its opening bracket
+    fn sequence<T>(&mut self, end: &str, mut item: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let mut items = vec![];
+        if self.eat(end) {
+            return Some(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(end) {
+                return Some(items);
+            }
+            if !self.eat(",") {
+                return None;
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        match self.peek()? {
+            b'n' if self.eat("null") => Some(Value::Null),
+            b't' if self.eat("true") => Some(Value::Bool(true)),
+            b'f' if self.eat("false") => Some(Value::Bool(false)),
+            b'"' => self.string().map(Value::String),
+            b'[' => self.nested(|parser| parser.sequence("]", Self::value).map(Value::List)),
+            b'{' => self.nested(|parser| {
+                let entry = |parser: &mut Self| {
+                    let key = parser.string()?;
+                    parser.eat(":").then_some(())?;
+                    Some((key, parser.value()?))
+                };
+                parser.sequence("}", entry).map(Value::Map)
+            }),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => None,
+        }
+    }
+
+    // parses a list or map after its opening bracket, limiting how deeply they nest
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Option<Value>) -> Option<Value> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        self.pos += 1;
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.pos;
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|c| matches!(c, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+        {
+            self.pos += 1;
+        }
+        let number = str::from_utf8(&self.text[start..self.pos]).ok()?;
+        if !number.contains(['.', 'e', 'E'])
+            && let Ok(i) = number.parse()
+        {
+            return Some(Value::Int(i));
+        }
+        // integers too large for an i64 are still numbers
+        number.parse().ok().map(Value::Float)
+    }
+
+    fn hex_escape(&mut self) -> Option<u32> {
+        let hex = self.text.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        u32::from_str_radix(str::from_utf8(hex).ok()?, 16).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if !self.eat("\"") {
+            return None;
+        }
+        let mut s = String::new();
+        loop {
+            let start = self.pos;
+            while !matches!(self.text.get(self.pos), Some(b'"' | b'\\') | None) {
+                self.pos += 1;
+            }
+            s.push_str(str::from_utf8(&self.text[start..self.pos]).ok()?);
+            let c = *self.text.get(self.pos)?;
+            self.pos += 1;
+            if c == b'"' {
+                return Some(s);
+            }
+            let escape = *self.text.get(self.pos)?;
+            self.pos += 1;
+            s.push(match escape {
+                b'"' => '"',
+                b'\\' => '\\',
+                b'/' => '/',
+                b'b' => '\u{8}',
+                b'f' => '\u{c}',
+                b'n' => '\n',
+                b'r' => '\r',
+                b't' => '\t',
+                b'u' => {
+                    let mut code = self.hex_escape()?;
+                    // characters outside the BMP are written as a pair of UTF-16 surrogates
+                    if (0xd800..0xdc00).contains(&code) && self.text[self.pos..].starts_with(b"\\u") {
+                        self.pos += 2;
+                        let low = self.hex_escape()?;
+                        if !(0xdc00..0xe000).contains(&low) {
+                            return None;
+                        }
+                        code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                    }
+                    char::from_u32(code)?
+                }
+                _ => return None,
+            });
+        }
+    }
+}
+
+/// A value stored in a DSP, exposed as a data parameter encoded with a [Codec].
+///
+/// The value is accessed through [Deref] and [DerefMut]. The parameter's getter encodes the
+/// current value into storage kept alongside it, so that the DSP can expose data it computes.
+/// Use [typed_param](crate::typed_param) to create the parameter.
+///
+/// ```
+/// use scamble::dsp::typed::{Json, Typed};
+///
+/// let mut steps: Typed<Vec<i32>, Json> = Typed::new(vec![1, 2]);
+/// steps.push(3);
+/// assert_eq!(steps.encode(), b"[1,2,3]");
+/// assert!(steps.decode(b"[4]"));
+/// assert_eq!(*steps, [4]);
+///
+/// // invalid data is ignored
+/// assert!(!steps.decode(b"[4.5]"));
+/// assert_eq!(*steps, [4]);
+/// ```
+pub struct Typed<T, C = Binary> {
+    value: T,
+    encoded: Vec<u8>,
+    codec: PhantomData<C>,
+}
+
+impl<T: Structured, C: Codec> Typed<T, C> {
+    /// Creates a typed value.
+    pub fn new(value: T) -> Self {
+        Typed {
+            value,
+            encoded: vec![],
+            codec: PhantomData,
+        }
+    }
+
+    /// Takes the value out.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Encodes the current value. The encoding is kept until the next call.
+    pub fn encode(&mut self) -> &[u8] {
+        self.encoded.clear();
+        C::encode(&self.value.to_value(), &mut self.encoded);
+        &self.encoded
+    }
+
+    /// Replaces the value with one decoded from `data`, returning whether it was valid. Invalid
+    /// data leaves the value unchanged.
+    pub fn decode(&mut self, data: &[u8]) -> bool {
+        match C::decode(data).as_ref().and_then(T::from_value) {
+            Some(value) => {
+                self.value = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Structured + Default, C: Codec> Default for Typed<T, C> {
+    fn default() -> Self {
+        Typed::new(T::default())
+    }
+}
+
+impl<T, C> Deref for Typed<T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, C> DerefMut for Typed<T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
@@ -1,8 +1,11 @@
 #![feature(panic_payload_as_str)]
 #![feature(macro_metavar_expr)]
-#![feature(macro_metavar_expr_concat)]
+#![feature(c_variadic)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
 pub mod dsp;
 pub mod raw_bindings;
 pub mod result;
-pub mod data;
\ No newline at end of file
+pub mod data;
+
+pub use result::{FmodError, Result};
\ No newline at end of file
@@ -1,6 +1,100 @@
 //! Helpers for working with [FMOD_RESULT]s.
+//!
+//! Raw result codes can be turned into [Result]s with [check], so that FMOD errors propagate with `?`:
+//!
+//! ```
+//! use scamble::dsp::offline::OfflineHost;
+//! use scamble::result::{FmodError, check};
+//! # use scamble::dsp::{Dsp, DspType};
+//! # use scamble::dsp::signal::{SignalConst, SignalMut};
+//! # struct Silence;
+//! # impl Dsp for Silence {
+//! #     fn name() -> &'static str { "Silence" }
+//! #     fn version() -> u32 { 1 }
+//! #     fn ty() -> DspType { DspType::Effect }
+//! #     fn create() -> Self { Silence }
+//! #     fn read(&mut self, _: SignalConst, _: SignalMut) {}
+//! # }
+//!
+//! fn configure(host: &mut OfflineHost<Silence>) -> scamble::Result<()> {
+//!     check(host.reset())?;
+//!     // Silence has no parameters
+//!     check(host.set_float(0, 1.))?;
+//!     Ok(())
+//! }
+//!
+//! let error = configure(&mut OfflineHost::new(48000, 256)).unwrap_err();
+//! assert_eq!(error, FmodError::INVALID_PARAM);
+//! assert_eq!(error.to_string(), "An invalid parameter was passed to this function. (FMOD_ERR_INVALID_PARAM)");
+//! ```
 
 use crate::raw_bindings::FMOD_RESULT;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// A result whose error is an [FmodError].
+pub type Result<T, E = FmodError> = std::result::Result<T, E>;
+
+/// An error reported by FMOD, wrapping any [FMOD_RESULT] other than `FMOD_OK`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FmodError(FMOD_RESULT);
+
+impl FmodError {
+    /// An invalid parameter was passed, such as an out-of-range parameter index.
+    pub const INVALID_PARAM: FmodError = FmodError(FMOD_RESULT::FMOD_ERR_INVALID_PARAM);
+    /// Not enough memory or resources.
+    pub const MEMORY: FmodError = FmodError(FMOD_RESULT::FMOD_ERR_MEMORY);
+    /// An error occurred in a plugin.
+    pub const PLUGIN: FmodError = FmodError(FMOD_RESULT::FMOD_ERR_PLUGIN);
+
+    /// Wraps a raw result code, or returns [None] if it's `FMOD_OK`.
+    pub fn new(result: FMOD_RESULT) -> Option<Self> {
+        (result != FMOD_RESULT::FMOD_OK).then_some(FmodError(result))
+    }
+
+    /// Gets the raw result code.
+    pub fn code(self) -> FMOD_RESULT {
+        self.0
+    }
+
+    /// Gets a simple description of this error, as given by [error_string_raw].
+    pub fn message(self) -> &'static str {
+        error_string_raw(self.0)
+    }
+}
+
+impl Display for FmodError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?})", self.message(), self.0)
+    }
+}
+
+impl Error for FmodError {}
+
+impl From<FmodError> for FMOD_RESULT {
+    fn from(error: FmodError) -> Self {
+        error.0
+    }
+}
+
+impl TryFrom<FMOD_RESULT> for FmodError {
+    type Error = ();
+
+    /// Wraps a raw result code, failing if it's `FMOD_OK`.
+    fn try_from(result: FMOD_RESULT) -> std::result::Result<Self, ()> {
+        FmodError::new(result).ok_or(())
+    }
+}
+
+/// Converts a raw result code into a [Result], which is `Ok` only for `FMOD_OK`.
+pub fn check(result: FMOD_RESULT) -> Result<()> {
+    FmodError::new(result).map_or(Ok(()), Err)
+}
+
+/// Converts a [Result] back into a raw result code, such as to return from an FMOD callback.
+pub fn into_raw(result: Result<()>) -> FMOD_RESULT {
+    result.map_or_else(FmodError::code, |()| FMOD_RESULT::FMOD_OK)
+}
 
 /// Returns a simple description of an FMOD error code.
 pub fn error_string_raw(result: FMOD_RESULT) -> &'static str {
@@ -1,7 +1,7 @@
 use scamble::dsp::{Dsp, DspType};
-use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::signal::{Kernel, SignalConst, SignalMut, Vector};
+use std::simd::StdFloat;
 use std::simd::num::SimdFloat;
-use std::simd::{StdFloat, f32x16};
 
 pub struct LocalDynamics;
 
@@ -23,21 +23,19 @@ impl Dsp for LocalDynamics {
     }
 
     fn read(&mut self, input: SignalConst, mut output: SignalMut) {
-        // vector loop
-        let in_data = input.samples();
-        let out_data = output.samples_mut();
-        let l = in_data.len();
-        for i in 0..l / 16 {
-            let x = f32x16::load_or(&in_data[i * 16..], f32x16::splat(0.));
-            x.abs()
-                .sqrt()
-                .copysign(x)
-                .copy_to_slice(&mut out_data[i * 16..]);
-        }
-        // leftovers
-        for i in l - ((l / 16) * 16)..l {
-            let x = in_data[i];
-            out_data[i] = x.abs().sqrt().copysign(x);
-        }
+        output.map(&input, SqrtCompress);
+    }
+}
+
+// compresses by taking the square root of the magnitude
+struct SqrtCompress;
+
+impl Kernel for SqrtCompress {
+    fn scalar(&self, x: f32) -> f32 {
+        x.abs().sqrt().copysign(x)
+    }
+
+    fn vector(&self, x: Vector) -> Vector {
+        x.abs().sqrt().copysign(x)
     }
 }
@@ -1,5 +1,5 @@
 use scamble::dsp::Parameter;
-use scamble::dsp::{Dsp, DspType, ParameterType, interop::with_sidechain};
+use scamble::dsp::{Dsp, DspType, ParameterType};
 use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
 
 pub struct ExactOut {
@@ -37,10 +37,19 @@ impl Dsp for ExactOut {
         }
     }
 
-    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
-        with_sidechain(|it| match it {
-            None => {}
-            Some(sidechain) => output.samples_mut().copy_from_slice(sidechain.samples()),
-        });
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.read_with_sidechain(input, None, output);
+    }
+
+    fn read_with_sidechain(&mut self, _: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) {
+        match sidechain {
+            None => output.fill(0.),
+            Some(sidechain) if sidechain.channels() == output.channels() => output.samples_mut().copy_from_slice(sidechain.samples()),
+            Some(sidechain) => {
+                for (i, x) in sidechain.read_mono().enumerate() {
+                    output.write_sample(i, x);
+                }
+            }
+        }
     }
 }
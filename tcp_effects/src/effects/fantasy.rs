@@ -1,27 +1,20 @@
 use scamble::dsp::Parameter;
-use scamble::dsp::{Dsp, DspType, ParameterType, ProcessResult};
+use scamble::dsp::{Dsp, DspType, ParameterType, ProcessResult, interop};
 use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
-use circular_buffer::CircularBuffer;
-use realfft::num_complex::Complex;
-use realfft::num_traits::Zero;
-use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
-use std::sync::Arc;
+use scamble::dsp::spectral::{Complex, Stft, Window};
+use scamble::dsp::tail::TailTracker;
 
 const BUFLEN: usize = 4096;
 const HBUFLEN: usize = BUFLEN / 2 + 1;
 
 pub struct Fantasy {
-    delay: CircularBuffer<BUFLEN, f32>,
-    silence: usize,
+    stft: Stft,
+    tail: TailTracker,
 
-    persistent_freqs: [f32; HBUFLEN],
+    // per channel
+    persistent_freqs: Vec<[f32; HBUFLEN]>,
 
-    scratch: [Complex<f32>; BUFLEN],
-    out: [Complex<f32>; HBUFLEN],
-    copy: [f32; BUFLEN],
-    residual: [f32; BUFLEN / 2],
-    dft_fwd: Arc<dyn RealToComplex<f32>>,
-    dft_bwd: Arc<dyn ComplexToReal<f32>>,
+    scratch: [Complex<f32>; HBUFLEN],
 
     detune_window: usize,
     detune_factor: f32,
@@ -31,6 +24,16 @@ pub struct Fantasy {
     echo_support: f32,
 }
 
+impl Fantasy {
+    // allocates for the mixer's channel count, so that reading doesn't allocate
+    fn reserve(&mut self) {
+        if let Some((_, channels)) = interop::max_block() {
+            self.stft.reserve(channels);
+            self.persistent_freqs.resize(channels, [0.; HBUFLEN]);
+        }
+    }
+}
+
 impl Dsp for Fantasy {
     fn name() -> &'static str {
         "Fantasy"
@@ -111,19 +114,13 @@ impl Dsp for Fantasy {
     }
 
     fn create() -> Self {
-        let mut planner = RealFftPlanner::new();
-        Fantasy {
-            delay: Default::default(),
-            silence: 0,
+        let mut fantasy = Fantasy {
+            stft: Stft::new(BUFLEN, BUFLEN / 4, Window::Hann),
+            tail: TailTracker::samples(BUFLEN),
 
-            persistent_freqs: [0.; HBUFLEN],
+            persistent_freqs: vec![],
 
-            scratch: [Complex::zero(); BUFLEN],
-            out: [Complex::zero(); HBUFLEN],
-            copy: [0.; BUFLEN],
-            residual: [0.; BUFLEN / 2],
-            dft_fwd: planner.plan_fft_forward(BUFLEN),
-            dft_bwd: planner.plan_fft_inverse(BUFLEN),
+            scratch: [Complex::default(); HBUFLEN],
 
             detune_factor: 0.7,
             detune_window: 8,
@@ -131,107 +128,74 @@ impl Dsp for Fantasy {
 
             echo_decay: 0.95,
             echo_support: 0.1,
-        }
+        };
+        fantasy.reserve();
+        fantasy
     }
 
     fn reset(&mut self) {
-        self.delay.fill(0.);
-        self.silence = 0;
-        self.persistent_freqs.fill(0.);
-        self.residual.fill(0.);
+        self.stft.reset();
+        self.tail.reset();
+        self.persistent_freqs.fill([0.; HBUFLEN]);
+        self.reserve();
     }
 
     fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
-        if idle {
-            self.silence += incoming_length;
-            if self.silence >= BUFLEN && self.persistent_freqs.iter().all(|it| *it <= 0.0001) {
-                ProcessResult::SkipSilent
-            } else {
-                ProcessResult::Continue
-            }
-        } else {
-            self.silence = 0;
-            ProcessResult::Continue
-        }
+        // keep going while persistent frequencies are still decaying
+        self.tail.should_process_while(idle, incoming_length, || {
+            self.persistent_freqs.iter().flatten().any(|it| *it > 0.0001)
+        })
     }
 
-    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
-        output.fill(0.);
-
-        self.delay.extend(input.read_mono());
+    fn latency_samples(&self) -> usize {
+        self.stft.latency()
+    }
 
-        if self.delay.is_full() {
-            copy_contiguous(&self.delay, &mut self.copy);
-            self.dft_fwd
-                .process_with_scratch(&mut self.copy, &mut self.out, &mut self.scratch)
-                .unwrap();
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        // only allocates if the channel count differs from the mixer's
+        self.persistent_freqs.resize(output.channels(), [0.; HBUFLEN]);
+        let Fantasy { stft, persistent_freqs, scratch, .. } = self;
+        let (detune_window, detune_factor, detune_bias) = (self.detune_window, self.detune_factor, self.detune_bias);
+        let (echo_decay, echo_support) = (self.echo_decay, self.echo_support);
 
-            self.scratch[..HBUFLEN].copy_from_slice(&self.out);
+        stft.process(input, output, |channel, out| {
+            let persistent_freqs = &mut persistent_freqs[channel];
+            scratch.copy_from_slice(out);
             for i in 8..HBUFLEN - 8 {
-                let orig_norm = self.out[i].norm();
+                let orig_norm = out[i].norm();
                 let mut target_norm = orig_norm;
 
-                let window: usize = self.detune_window;
+                let window: usize = detune_window;
                 if window > 0 {
                     let start = window.saturating_sub(i);
                     let end = (2 * window + 1).min(HBUFLEN - 2 - i);
                     let range = start..=end;
-                    if self.detune_bias {
+                    if detune_bias {
                         for j in range {
                             // the world if you could `let it: impl Iterator<...>`
-                            target_norm = (1. - self.detune_factor) * target_norm
-                                + self.detune_factor * self.scratch[i + j - window].norm();
+                            target_norm = (1. - detune_factor) * target_norm
+                                + detune_factor * scratch[i + j - window].norm();
                         }
                     } else {
                         for j in range.rev() {
-                            target_norm = (1. - self.detune_factor) * target_norm
-                                + self.detune_factor * self.scratch[i + j - window].norm();
+                            target_norm = (1. - detune_factor) * target_norm
+                                + detune_factor * scratch[i + j - window].norm();
                         }
                     }
                     //target_norm = (i-range..=i+range).map(|i| self.scratch[i].norm()).sum::<f32>() / (range * 2 + 1) as f32;
                 }
 
-                target_norm += self.persistent_freqs[i];
+                target_norm += persistent_freqs[i];
 
                 if orig_norm < 0.000001 {
                     //self.out[i] = Complex::from(target_norm);
                 } else {
-                    self.out[i] *= target_norm / orig_norm;
+                    out[i] *= target_norm / orig_norm;
                 }
 
-                self.persistent_freqs[i] += orig_norm * self.echo_support;
-                self.persistent_freqs[i] *= self.echo_decay;
+                persistent_freqs[i] += orig_norm * echo_support;
+                persistent_freqs[i] *= echo_decay;
             }
-
-            self.dft_bwd
-                .process_with_scratch(&mut self.out, &mut self.copy, &mut self.scratch)
-                .unwrap();
-        }
-
-        let out_len = output.length();
-        for i in 0..BUFLEN {
-            self.copy[i] /= BUFLEN as f32;
-        }
-
-        // write to outputs
-        for i in 0..out_len {
-            let new = self.copy[BUFLEN - (out_len * 2) + i];
-            let old = self.residual[i];
-            output.write_sample(i, interp(old, new, i, out_len));
-        }
-
-        // update residual
-        self.residual[..out_len].copy_from_slice(&self.copy[BUFLEN - out_len..]);
+        });
     }
 }
-
-fn copy_contiguous<const N: usize, T: Copy>(from: &CircularBuffer<N, T>, to: &mut [T]) {
-    let (p1, p2) = from.as_slices();
-    to[..p1.len()].copy_from_slice(p1);
-    to[p1.len()..p1.len() + p2.len()].copy_from_slice(p2);
-}
-
-fn interp(x: f32, y: f32, i: usize, l: usize) -> f32 {
-    let r = i as f32 / (l - 1) as f32;
-    (1. - r) * x + r * y
-}
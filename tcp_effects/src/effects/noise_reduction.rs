@@ -1,39 +1,28 @@
 use scamble::dsp::Parameter;
 use scamble::dsp::{Dsp, DspType, ParameterType, ProcessResult};
-use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
-use circular_buffer::CircularBuffer;
-use realfft::num_complex::Complex;
-use realfft::num_traits::Zero;
-use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
-use std::ops::Div;
-use std::sync::Arc;
-
-// sqrt(2048)
-const ADJ: f32 = 45.25483399593904156165403917471;
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::spectral::{Complex, Stft, Window};
+use scamble::dsp::tail::TailTracker;
 
 const BUFLEN: usize = 4096;
 const HBUFLEN: usize = BUFLEN / 2 + 1;
 
+// mean square of the Hann window, to recover the volume of windowed frames
+const WINDOW_POWER: f32 = 0.375;
+
+// gain of the closed noise gate, and how much the gain changes by each hop while it opens or closes
+const GATE_FLOOR: f32 = 1. / 8.;
+const GATE_STEP: f32 = 2.;
+
 pub struct NoiseReduction {
-    // previous window of buffered values
-    delay_left: CircularBuffer<BUFLEN, f32>,
-    delay_right: CircularBuffer<BUFLEN, f32>,
+    // overlapping frames of the input
+    stft: Stft,
     // accumulated persistent frequencies
     persistent_freqs: [f32; HBUFLEN],
-    // ramp up volume after noise gate is released
-    was_gated: bool,
-    // FFT instances
-    dft_fwd: Arc<dyn RealToComplex<f32>>,
-    dft_bwd: Arc<dyn ComplexToReal<f32>>,
+    // gain of the noise gate, ramped between 1 and GATE_FLOOR
+    gate: f32,
     // keep track of previous silence for should-process
-    silence: usize,
-    // buffers
-    scratch: [Complex<f32>; BUFLEN],
-    out_left: [Complex<f32>; HBUFLEN],
-    out_right: [Complex<f32>; HBUFLEN],
-    copy_left: [f32; BUFLEN],
-    copy_right: [f32; BUFLEN],
-    residual: [f32; BUFLEN / 2],
+    tail: TailTracker,
     // parameters
     var_enable: bool,
     var_adj: f32,
@@ -131,21 +120,13 @@ impl Dsp for NoiseReduction {
     }
 
     fn create() -> Self {
-        let mut planner = RealFftPlanner::new();
+        let mut stft = Stft::new(BUFLEN, BUFLEN / 4, Window::Hann);
+        stft.reserve(2);
         NoiseReduction {
-            delay_left: Default::default(),
-            delay_right: Default::default(),
+            stft,
             persistent_freqs: [0.; HBUFLEN],
-            was_gated: false,
-            dft_fwd: planner.plan_fft_forward(BUFLEN),
-            dft_bwd: planner.plan_fft_inverse(BUFLEN),
-            silence: 0,
-            scratch: [Complex::zero(); BUFLEN],
-            out_left: [Complex::zero(); HBUFLEN],
-            out_right: [Complex::zero(); HBUFLEN],
-            copy_left: [0.; BUFLEN],
-            copy_right: [0.; BUFLEN],
-            residual: [0.; BUFLEN / 2],
+            gate: 1.,
+            tail: TailTracker::samples(BUFLEN),
             var_enable: true,
             var_adj: 6.,
             persist_enable: true,
@@ -156,76 +137,59 @@ impl Dsp for NoiseReduction {
     }
 
     fn reset(&mut self) {
-        self.delay_left.clear();
-        self.delay_right.clear();
+        self.stft.reset();
         self.persistent_freqs.fill(0.);
-        self.was_gated = false;
-        self.residual.fill(0.);
-        self.silence = 0;
+        self.gate = 1.;
+        self.tail.reset();
     }
 
     fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
-        if idle {
-            self.silence += incoming_length;
-            if self.silence >= BUFLEN {
-                ProcessResult::SkipSilent
-            } else {
-                ProcessResult::Continue
-            }
-        } else {
-            self.silence = 0;
-            ProcessResult::Continue
-        }
+        self.tail.should_process(idle, incoming_length)
     }
 
     fn preferred_out_channels(&self) -> Option<usize> {
         Some(2)
     }
 
-    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
-        output.fill(0.);
-
-        // extend buffers
-        let (l, r) = input.read_stereo();
-        self.delay_left.extend(l);
-        self.delay_right.extend(r);
+    fn latency_samples(&self) -> usize {
+        self.stft.latency()
+    }
 
-        // when we have enough data...
-        if self.delay_left.is_full() && self.delay_right.is_full() {
-            // copy from circular buffers to scratch space
-            copy_contiguous(&self.delay_left, &mut self.copy_left);
-            copy_contiguous(&self.delay_right, &mut self.copy_right);
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        let NoiseReduction {
+            stft,
+            persistent_freqs,
+            gate,
+            ..
+        } = self;
+        let (var_enable, var_adj) = (self.var_enable, self.var_adj);
+        let (persist_enable, persist_lerp) = (self.persist_enable, self.persist_lerp);
+        let (noise_gate_enable, noise_gate_req) = (self.noise_gate_enable, self.noise_gate_req);
+
+        stft.process_linked(input, output, |channels| {
+            let Some(first) = channels.first() else {
+                return;
+            };
 
             // calculate RMS of amplitude to use later
-            let rms = if self.noise_gate_enable {
-                self.copy_left
-                    .iter()
-                    .map(|x| x * x)
-                    .sum::<f32>()
-                    .div(BUFLEN as f32)
-                    .sqrt()
+            let rms = if noise_gate_enable {
+                // from Parseval's theorem, counting the bins that stand in for negative frequencies twice
+                let energy = first.iter().map(|x| x.norm_sqr()).sum::<f32>() * 2. - first[0].norm_sqr() - first[HBUFLEN - 1].norm_sqr();
+                (energy / (BUFLEN * BUFLEN) as f32 / WINDOW_POWER).sqrt()
             } else {
                 0.1
             };
 
-            // apply forward FFTs
-            self.dft_fwd
-                .process_with_scratch(&mut self.copy_left, &mut self.out_left, &mut self.scratch)
-                .unwrap();
-            self.dft_fwd
-                .process_with_scratch(&mut self.copy_right, &mut self.out_right, &mut self.scratch)
-                .unwrap();
-
             // processing...
 
             // find variance of frequencies; noise tends have very low variance compared to speech
             // adjust by first value to keep numeric stability
             let mut mean = 0.;
             let mut variance = 0.;
-            if self.var_enable {
-                let offset = (self.out_left[0].norm() + self.out_right[0].norm()) / 2.;
+            if var_enable {
+                let offset = channels_mean(channels, 0);
                 for i in 0..HBUFLEN {
-                    let x = (self.out_left[i].norm() + self.out_right[i].norm()) / 2. - offset;
+                    let x = channels_mean(channels, i) - offset;
                     mean += x;
                     variance += x * x;
                 }
@@ -233,102 +197,53 @@ impl Dsp for NoiseReduction {
                 variance = ((variance / HBUFLEN as f32) - mean * mean).sqrt();
             }
 
+            // apply noise gate, ramping between full and gated volume over a few hops as it engages
+            // or releases
+            let adj_amp = 20. * rms.log10();
+            let gated = noise_gate_enable && adj_amp < noise_gate_req;
+            *gate = if gated {
+                (*gate / GATE_STEP).max(GATE_FLOOR)
+            } else {
+                (*gate * GATE_STEP).min(1.)
+            };
+
             // apply filtering
             for i in 0..HBUFLEN {
-                // take the minimum over each channel for each element
-                let left_max = self.out_left[i].norm_sqr() > self.out_right[i].norm_sqr();
-                self.out_left[i] = if left_max {
-                    self.out_right[i]
-                } else {
-                    self.out_left[i]
-                };
+                // the minimum over each channel decides how much each element is reduced
+                let min = channels.iter().map(|it| it[i].norm()).fold(f32::INFINITY, f32::min);
+                let mut gain = *gate;
 
                 // update persistent frequencies
-                if self.persist_enable {
-                    let norm = self.out_left[i].norm();
-                    self.persistent_freqs[i] =
-                        lerp(self.persistent_freqs[i].min(norm), norm, self.persist_lerp);
+                if persist_enable {
+                    persistent_freqs[i] = lerp(persistent_freqs[i].min(min), min, persist_lerp);
                     // then cut them out
-                    self.out_left[i] = sub_real_cmplx(self.persistent_freqs[i], self.out_left[i]);
+                    gain *= if min > 0. { (min - persistent_freqs[i]) / min } else { 0. };
                 } else {
-                    self.persistent_freqs[i] = 0.;
+                    persistent_freqs[i] = 0.;
                 }
 
-                // normalize (part 1)
-                self.out_left[i] /= ADJ;
-
-                if self.var_enable {
+                if var_enable {
                     // reduce with variance
                     // self.out_left[i] *= (v - variance.log2().clamp(0., v)) / v;
-                    let k = (0.4 * self.var_adj + 6.) / 5.;
+                    let k = (0.4 * var_adj + 6.) / 5.;
                     let x1: f32 = (2. / (1. + f32::powf(2., variance * -k.log2()))) - 1.;
-                    self.out_left[i] *= x1;
+                    gain *= x1;
                 }
-            }
-
-            // just in case
-            self.out_left[0].im = 0.;
-            self.out_left[HBUFLEN - 1].im = 0.;
 
-            // apply backwards FFT
-            self.dft_bwd
-                .process_with_scratch(&mut self.out_left, &mut self.copy_left, &mut self.scratch)
-                .unwrap();
-
-            // apply noise gate
-            let adj_amp = 20. * rms.log10();
-            if self.noise_gate_enable && adj_amp < self.noise_gate_req {
-                if self.was_gated {
-                    for i in 0..BUFLEN {
-                        self.copy_left[i] /= 8.;
-                    }
-                } else {
-                    for i in 0..BUFLEN {
-                        self.copy_left[i] /= 1. + 7. * (i as f32 / BUFLEN as f32);
-                    }
-                }
-                self.was_gated = true;
-            } else if self.was_gated {
-                for i in 0..BUFLEN {
-                    self.copy_left[i] /= 1. + 7. * ((BUFLEN - i) as f32 / BUFLEN as f32);
+                // each channel keeps its own content
+                for channel in channels.iter_mut() {
+                    channel[i] *= gain;
                 }
             }
-
-            // normalize outputs (part 2)
-            for i in 0..BUFLEN {
-                self.copy_left[i] /= ADJ;
-            }
-
-            // write to outputs
-            let out_len = output.length();
-            for i in 0..out_len {
-                let new = self.copy_left[BUFLEN - (out_len * 2) + i];
-                let old = self.residual[i];
-                output.write_sample(i, interp(old, new, i, out_len));
-            }
-
-            // update residual
-            self.residual[..out_len].copy_from_slice(&self.copy_left[BUFLEN - out_len..]);
-        }
+        });
     }
 }
 
-fn copy_contiguous<const N: usize, T: Copy>(from: &CircularBuffer<N, T>, to: &mut [T]) {
-    let (p1, p2) = from.as_slices();
-    to[..p1.len()].copy_from_slice(p1);
-    to[p1.len()..p1.len() + p2.len()].copy_from_slice(p2);
+// average magnitude of a bin across channels
+fn channels_mean(channels: &[Vec<Complex<f32>>], i: usize) -> f32 {
+    channels.iter().map(|it| it[i].norm()).sum::<f32>() / channels.len() as f32
 }
 
 fn lerp(from: f32, to: f32, fact: f32) -> f32 {
     from + (to - from) * fact
 }
-
-fn sub_real_cmplx(real: f32, cmplx: Complex<f32>) -> Complex<f32> {
-    let (mag, arg) = cmplx.to_polar();
-    Complex::from_polar(mag - real, arg)
-}
-
-fn interp(x: f32, y: f32, i: usize, l: usize) -> f32 {
-    let r = i as f32 / (l - 1) as f32;
-    (1. - r) * x + r * y
-}
@@ -1,27 +1,21 @@
 use scamble::dsp::Parameter;
-use scamble::dsp::{Dsp, DspType, ParameterType, ProcessResult, interop::with_sidechain};
-use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
-use circular_buffer::CircularBuffer;
-use realfft::num_complex::Complex;
-use realfft::num_traits::Zero;
-use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
-use std::sync::Arc;
+use scamble::dsp::{Dsp, DspType, ParameterType, ProcessResult, interop};
+use scamble::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
+use scamble::dsp::spectral::{Complex, Stft, Window};
+use scamble::dsp::tail::TailTracker;
 
 const BUFLEN: usize = 4096;
 const HBUFLEN: usize = BUFLEN / 2 + 1;
 
 pub struct Vocoder {
-    delay_carrier: CircularBuffer<BUFLEN, f32>,
-    delay_signal: CircularBuffer<BUFLEN, f32>,
-    silence: usize,
-
-    scratch: [Complex<f32>; BUFLEN],
-    out_carrier: [Complex<f32>; HBUFLEN],
-    out_signal: [Complex<f32>; HBUFLEN],
-    copy: [f32; BUFLEN],
-    residual: [f32; BUFLEN / 2],
-    dft_fwd: Arc<dyn RealToComplex<f32>>,
-    dft_bwd: Arc<dyn ComplexToReal<f32>>,
+    // processes the signal and carrier as two channels
+    stft: Stft,
+    tail: TailTracker,
+
+    scratch: [Complex<f32>; HBUFLEN],
+    // interleaved signal and carrier, and the output of processing them
+    pair: Vec<f32>,
+    pair_out: SignalBuf,
 
     sidechain_enabled: bool,
 
@@ -29,6 +23,17 @@ pub struct Vocoder {
     signal_offset: i32,
 }
 
+impl Vocoder {
+    // reserves space for the mixer's largest block, so that reading doesn't allocate
+    fn reserve(&mut self) {
+        if let Some((frames, _)) = interop::max_block() {
+            self.pair.reserve(frames * 2);
+            self.pair_out.reserve(frames, 2);
+        }
+        self.stft.reserve(2);
+    }
+}
+
 impl Dsp for Vocoder {
     fn name() -> &'static str {
         "Vocoder"
@@ -85,143 +90,95 @@ impl Dsp for Vocoder {
     }
 
     fn create() -> Self {
-        let mut planner = RealFftPlanner::new();
-        Vocoder {
-            delay_carrier: Default::default(),
-            delay_signal: Default::default(),
-            silence: 0,
-            scratch: [Complex::zero(); BUFLEN],
-            out_carrier: [Complex::zero(); HBUFLEN],
-            out_signal: [Complex::zero(); HBUFLEN],
-            copy: [0.; BUFLEN],
-            residual: [0.; BUFLEN / 2],
-            dft_fwd: planner.plan_fft_forward(BUFLEN),
-            dft_bwd: planner.plan_fft_inverse(BUFLEN),
+        let mut vocoder = Vocoder {
+            stft: Stft::new(BUFLEN, BUFLEN / 4, Window::Hann),
+            tail: TailTracker::samples(BUFLEN),
+            scratch: [Complex::default(); HBUFLEN],
+            pair: vec![],
+            pair_out: SignalBuf::new(),
             sidechain_enabled: false,
             carrier_offset: 0,
             signal_offset: 0,
-        }
+        };
+        vocoder.reserve();
+        vocoder
     }
 
     fn reset(&mut self) {
-        self.delay_carrier.fill(0.);
-        self.delay_signal.fill(0.);
-        self.residual.fill(0.);
-        self.silence = 0;
+        self.stft.reset();
+        self.tail.reset();
+        self.reserve();
     }
 
     fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
-        if idle {
-            self.silence += incoming_length;
-            if self.silence >= BUFLEN {
-                ProcessResult::SkipSilent
-            } else {
-                ProcessResult::Continue
-            }
-        } else {
-            self.silence = 0;
-            ProcessResult::Continue
-        }
+        self.tail.should_process(idle, incoming_length)
     }
 
-    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
-        output.fill(0.);
+    fn latency_samples(&self) -> usize {
+        self.stft.latency()
+    }
 
-        // extend buffers
-        self.delay_signal.extend(input.read_mono());
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.read_with_sidechain(input, None, output);
+    }
 
-        if self.sidechain_enabled {
-            with_sidechain(|rst| {
-                if let Some(carrier) = rst {
-                    self.delay_carrier.extend(carrier.read_mono());
-
-                    if self.delay_signal.is_full() && self.delay_carrier.is_full() {
-                        copy_contiguous(&self.delay_signal, &mut self.copy);
-                        self.dft_fwd
-                            .process_with_scratch(
-                                &mut self.copy,
-                                &mut self.out_signal,
-                                &mut self.scratch,
-                            )
-                            .unwrap();
-
-                        copy_contiguous(&self.delay_carrier, &mut self.copy);
-                        self.dft_fwd
-                            .process_with_scratch(
-                                &mut self.copy,
-                                &mut self.out_carrier,
-                                &mut self.scratch,
-                            )
-                            .unwrap();
-
-                        let max_carrier = self
-                            .out_carrier
-                            .iter()
-                            .max_by(|l, r| l.norm_sqr().total_cmp(&r.norm_sqr()))
-                            .unwrap()
-                            .norm();
-
-                        for i in 0..HBUFLEN {
-                            self.out_carrier[i] /= max_carrier;
-                        }
+    fn read_with_sidechain(&mut self, input: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) {
+        output.fill(0.);
 
-                        self.scratch[..HBUFLEN].copy_from_slice(&self.out_signal);
-
-                        for i in 0..HBUFLEN {
-                            let sig_i = i as i32 + self.signal_offset;
-                            let car_i = i as i32 + self.carrier_offset;
-                            if sig_i < 0
-                                || sig_i >= HBUFLEN as i32
-                                || car_i < 0
-                                || car_i >= HBUFLEN as i32
-                            {
-                                self.out_signal[i] = Complex::zero();
-                            } else {
-                                self.out_signal[i] = self.scratch[sig_i as usize]
-                                    * self.out_carrier[car_i as usize].norm();
-                            }
-                        }
-                        self.out_signal[0].im = 0.;
-                        self.out_signal[HBUFLEN - 1].im = 0.;
-
-                        self.dft_bwd
-                            .process_with_scratch(
-                                &mut self.out_signal,
-                                &mut self.copy,
-                                &mut self.scratch,
-                            )
-                            .unwrap();
-
-                        let out_len = output.length();
-                        for i in 0..BUFLEN {
-                            self.copy[i] /= BUFLEN as f32;
+        if self.sidechain_enabled {
+            if let Some(carrier) = sidechain {
+                // interleave the signal and carrier
+                self.pair.clear();
+                let mut carrier = carrier.read_mono();
+                for x in input.read_mono() {
+                    self.pair.extend([x, carrier.next().unwrap_or(0.)]);
+                }
+                self.pair_out.resize(self.pair.len() / 2, 2);
+
+                let Vocoder { stft, scratch, pair, pair_out, .. } = self;
+                let (carrier_offset, signal_offset) = (self.carrier_offset, self.signal_offset);
+                stft.process_linked(SignalConst::new(pair, 2), pair_out.as_signal_mut(), |channels| {
+                    let [out_signal, out_carrier] = channels else {
+                        return;
+                    };
+
+                    let max_carrier = out_carrier
+                        .iter()
+                        .max_by(|l, r| l.norm_sqr().total_cmp(&r.norm_sqr()))
+                        .unwrap()
+                        .norm();
+
+                    if max_carrier > 0. {
+                        for x in out_carrier.iter_mut() {
+                            *x /= max_carrier;
                         }
+                    }
 
-                        // write to outputs
-                        for i in 0..out_len {
-                            let new = self.copy[BUFLEN - (out_len * 2) + i];
-                            let old = self.residual[i];
-                            output.write_sample(i, interp(old, new, i, out_len));
+                    scratch.copy_from_slice(out_signal);
+
+                    for (i, out) in out_signal.iter_mut().enumerate() {
+                        let sig_i = i as i32 + signal_offset;
+                        let car_i = i as i32 + carrier_offset;
+                        if sig_i < 0
+                            || sig_i >= HBUFLEN as i32
+                            || car_i < 0
+                            || car_i >= HBUFLEN as i32
+                        {
+                            *out = Complex::default();
+                        } else {
+                            *out = scratch[sig_i as usize]
+                                * out_carrier[car_i as usize].norm();
                         }
-
-                        // update residual
-                        self.residual[..out_len].copy_from_slice(&self.copy[BUFLEN - out_len..]);
                     }
+                });
+
+                // write to outputs
+                for (i, x) in self.pair_out.channel(0).iter().enumerate() {
+                    output.write_sample(i, x);
                 }
-            });
+            }
         } else {
-            self.delay_carrier.fill(0.);
+            self.stft.reset();
         }
     }
 }
-
-fn copy_contiguous<const N: usize, T: Copy>(from: &CircularBuffer<N, T>, to: &mut [T]) {
-    let (p1, p2) = from.as_slices();
-    to[..p1.len()].copy_from_slice(p1);
-    to[p1.len()..p1.len() + p2.len()].copy_from_slice(p2);
-}
-
-fn interp(x: f32, y: f32, i: usize, l: usize) -> f32 {
-    let r = i as f32 / (l - 1) as f32;
-    (1. - r) * x + r * y
-}
@@ -1,5 +1,4 @@
 #![feature(macro_metavar_expr)]
-#![feature(macro_metavar_expr_concat)]
 
 #![feature(portable_simd)]
 
@@ -7,8 +6,9 @@ use crate::effects::noise_reduction::NoiseReduction;
 use crate::effects::vocoder::Vocoder;
 use crate::effects::fantasy::Fantasy;
 
+use scamble::dsp::mix::WetDry;
 use scamble::expose_dsp_list;
 
 pub mod effects;
 
-expose_dsp_list!(NoiseReduction, Fantasy, Vocoder);
\ No newline at end of file
+expose_dsp_list!(WetDry<NoiseReduction>, WetDry<Fantasy>, WetDry<Vocoder>);
\ No newline at end of file
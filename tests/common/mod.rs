@@ -0,0 +1,59 @@
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::tail::TailTracker;
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType, ProcessResult};
+use scamble::{bool_param, int_param};
+
+// delays by a configurable number of samples, optionally inverting, and rings on for 64 samples
+pub struct Delay {
+    pub delay: i32,
+    pub invert: bool,
+    pub buffer: Vec<f32>,
+    tail: TailTracker,
+}
+
+impl Dsp for Delay {
+    fn name() -> &'static str {
+        "Delay"
+    }
+
+    fn version() -> u32 {
+        2
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter::new("delay", int_param!(delay, range: 0..100, default: 0)),
+            Parameter::new("invert", bool_param!(invert, default: false)),
+        ]
+    }
+
+    fn create() -> Self {
+        Delay {
+            delay: 0,
+            invert: false,
+            buffer: vec![],
+            tail: TailTracker::samples(64),
+        }
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.tail.should_process(idle, incoming_length)
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.delay as usize
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        let sign = if self.invert { -1. } else { 1. };
+        for (i, x) in input.read_mono().enumerate() {
+            self.buffer.push(x);
+            let delayed = if self.buffer.len() > self.delay as usize { self.buffer.remove(0) } else { 0. };
+            output.write_sample(i, delayed * sign);
+        }
+    }
+}
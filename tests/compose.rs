@@ -0,0 +1,188 @@
+mod common;
+
+use common::Delay;
+use scamble::dsp::compose::{Chain, Parallel};
+use scamble::dsp::interop::into_desc;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType, ProcessResult};
+use scamble::float_param;
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_DSP_DONTPROCESS, FMOD_ERR_DSP_SILENCE, FMOD_OK};
+use std::ffi::{CStr, c_char};
+
+const BLOCK: usize = 32;
+
+// adds a constant offset
+struct Offset {
+    offset: f32,
+}
+
+impl Dsp for Offset {
+    fn name() -> &'static str {
+        "Offset"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter::new("offset", float_param!(offset, range: -1.0..1.0, default: 0.0))]
+    }
+
+    fn create() -> Self {
+        Offset { offset: 0. }
+    }
+
+    fn should_process(&mut self, idle: bool, _: usize) -> ProcessResult {
+        if self.offset != 0. {
+            ProcessResult::Continue
+        } else if idle {
+            ProcessResult::SkipSilent
+        } else {
+            ProcessResult::SkipNoEffect
+        }
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        for (out, x) in output.samples_mut().iter_mut().zip(input.samples()) {
+            *out = x + self.offset;
+        }
+    }
+}
+
+// widens mono input to stereo, inverting the right channel
+struct Widen;
+
+impl Dsp for Widen {
+    fn name() -> &'static str {
+        "Widen"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Widen
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        for (frame, x) in output.samples_mut().chunks_exact_mut(2).zip(input.read_mono()) {
+            frame[0] = x;
+            frame[1] = -x;
+        }
+    }
+}
+
+#[test]
+fn names_and_versions_are_combined() {
+    assert_eq!(Chain::<Offset, Delay>::name(), "Offset > Delay");
+    assert_eq!(Parallel::<Offset, Delay>::name(), "Offset | Delay");
+    // swapped versions give different combinations
+    assert_ne!(Chain::<Offset, Delay>::version(), Chain::<Delay, Offset>::version());
+    assert!(Chain::<Offset, Delay>::version() > Chain::<Offset, Offset>::version());
+    let names: Vec<_> = Chain::<Offset, Parallel<Delay, Offset>>::parameters().iter().map(|it| it.name).collect();
+    assert_eq!(names, ["1.offset", "2.1.delay", "2.1.invert", "2.2.offset"]);
+    assert_eq!(Chain::<Offset, Parallel<Delay, Offset>>::parameter_count(), names.len());
+    let names: Vec<_> = Parallel::<Offset, Offset>::parameters().iter().map(|it| it.name).collect();
+    assert_eq!(names, ["1.offset", "2.offset"]);
+}
+
+fn text(s: &[c_char]) -> &str {
+    unsafe { CStr::from_ptr(s.as_ptr()) }.to_str().unwrap()
+}
+
+#[test]
+fn long_names_stay_distinct_in_fmod() {
+    type Deep = Chain<Chain<Chain<Chain<Chain<Delay, Offset>, Offset>, Delay>, Offset>, Delay>;
+    let desc = into_desc::<Deep>();
+    // the name would otherwise be cut off after the third stage
+    assert_eq!(text(&desc.name).len(), 31);
+    assert!(text(&desc.name).starts_with("Delay > Offset > Offset > "));
+    assert_ne!(text(&desc.name), text(&into_desc::<Chain<Deep, Delay>>().name));
+
+    let params = unsafe { std::slice::from_raw_parts(desc.paramdesc, desc.numparameters as usize) };
+    let names: Vec<_> = params.iter().map(|it| text(unsafe { &(**it).name })).collect();
+    assert_eq!(names.len(), Deep::parameter_count());
+    assert_eq!(names[0], "1.1.1.1.1.delay");
+    assert!(names.iter().all(|it| it.len() <= 15));
+    let mut unique = names.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), names.len(), "{names:?}");
+}
+
+#[test]
+fn chain_runs_in_order() {
+    let mut host = OfflineHost::<Chain<Offset, Widen>>::new(48000, BLOCK);
+    host.set_float(0, 0.5);
+    let rendered = host.render(SignalConst::new(&[1.; BLOCK], 1), BLOCK);
+    assert_eq!(rendered.channels, 2);
+    assert!(rendered.samples.chunks(2).all(|it| it == [1.5, -1.5]));
+}
+
+#[test]
+fn chain_combines_queries() {
+    let mut host = OfflineHost::<Chain<Offset, Offset>>::new(48000, BLOCK);
+    assert_eq!(host.should_process(false, BLOCK), FMOD_ERR_DSP_DONTPROCESS);
+    assert_eq!(host.should_process(true, BLOCK), FMOD_ERR_DSP_SILENCE);
+    host.set_float(1, 0.5);
+    assert_eq!(host.should_process(true, BLOCK), FMOD_OK);
+
+    // the second stage passes through the first's output
+    let rendered = host.render(SignalConst::new(&[1.; BLOCK], 1), BLOCK);
+    assert_eq!(rendered.samples, [1.5; BLOCK]);
+}
+
+#[test]
+fn chain_rings_on() {
+    let mut host = OfflineHost::<Chain<Offset, Delay>>::new(48000, BLOCK);
+    host.set_int(1, 10);
+    let input: Vec<f32> = (1..=BLOCK).map(|it| it as f32).collect();
+    let rendered = host.render(SignalConst::new(&input, 1), BLOCK * 4);
+    assert_eq!(rendered.queries, [FMOD_OK, FMOD_OK, FMOD_ERR_DSP_SILENCE, FMOD_ERR_DSP_SILENCE]);
+    assert_eq!(host.latency_samples(), 10);
+    assert_eq!(&rendered.samples[10..BLOCK + 10], &input[..]);
+}
+
+#[test]
+fn parallel_aligns_branches() {
+    let mut host = OfflineHost::<Parallel<Delay, Delay>>::new(48000, BLOCK);
+    host.set_int(0, 5);
+    host.set_int(2, 12);
+    let input: Vec<f32> = (1..=BLOCK * 2).map(|it| it as f32).collect();
+    let rendered = host.render_compensated(SignalConst::new(&input, 1), input.len());
+    assert_eq!(host.latency_samples(), 12);
+    let doubled: Vec<f32> = input.iter().map(|it| it * 2.).collect();
+    assert_eq!(rendered.samples, doubled);
+}
+
+#[test]
+fn parallel_mixes_channels() {
+    let mut host = OfflineHost::<Parallel<Widen, Offset>>::new(48000, BLOCK);
+    host.set_float(0, 0.25);
+    let rendered = host.render(SignalConst::new(&[1.; BLOCK], 1), BLOCK);
+    assert_eq!(rendered.channels, 2);
+    assert!(rendered.samples.chunks(2).all(|it| it == [2.25, 0.25]));
+}
+
+#[test]
+fn parallel_ends_with_both_branches() {
+    let mut host = OfflineHost::<Parallel<Offset, Delay>>::new(48000, BLOCK);
+    let rendered = host.render(SignalConst::new(&[1.; BLOCK], 1), BLOCK * 4);
+    assert_eq!(rendered.queries, [FMOD_OK, FMOD_OK, FMOD_ERR_DSP_SILENCE, FMOD_ERR_DSP_SILENCE]);
+    assert_eq!(&rendered.samples[..BLOCK], [2.; BLOCK]);
+}
@@ -0,0 +1,403 @@
+use hound::{Sample, SampleFormat, WavSpec, WavWriter};
+use scamble::dsp::decode::{DecodeError, Downmix, Format, LoadState, Loader, decode, decode_into};
+#[cfg(feature = "raw")]
+use scamble::dsp::decode::{PcmSample, RawPcm, decode_as};
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType, interop};
+use scamble::raw_bindings::FMOD_RESULT::FMOD_OK;
+use std::io::Cursor;
+use std::time::Duration;
+
+// 32-bit samples are written as floats, and narrower ones as integers
+fn wav<S: Sample + Copy>(channels: u16, sample_rate: u32, bits_per_sample: u16, samples: &[S]) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        sample_format: if bits_per_sample == 32 { SampleFormat::Float } else { SampleFormat::Int },
+    };
+    let mut data = Cursor::new(vec![]);
+    let mut writer = WavWriter::new(&mut data, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(*sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    data.into_inner()
+}
+
+#[test]
+fn stereo_is_kept() {
+    let decoded = decode(&wav(2, 44100, 32, &[0.5, -0.5, 0.25, 1.])).unwrap();
+    assert_eq!(decoded.sample_rate, 44100);
+    assert_eq!((decoded.channels(), decoded.frames()), (2, 2));
+    assert_eq!(decoded.signal.samples(), [0.5, -0.5, 0.25, 1.]);
+}
+
+#[test]
+fn downmix_follows_policy() {
+    let decoded = decode(&wav(3, 48000, 32, &[0.5, -0.5, 0.3, 0.25, 1., 1.])).unwrap();
+    assert_eq!(decoded.downmix(Downmix::First), [0.5, 0.25]);
+    assert_eq!(decoded.downmix(Downmix::Channel(1)), [-0.5, 1.]);
+    let average = decoded.downmix(Downmix::Average);
+    assert!(average.iter().zip([0.1, 0.75]).all(|(a, b)| (a - b).abs() < 1e-6), "{average:?}");
+
+    // mono data is kept whichever channel is chosen
+    let mono = decode(&wav(1, 48000, 32, &[0.5, 0.25])).unwrap();
+    assert_eq!(mono.downmix(Downmix::Channel(1)), [0.5, 0.25]);
+}
+
+#[test]
+fn decode_into_keeps_first_channel() {
+    let mut samples = vec![1.];
+    assert_eq!(decode_into(&wav(2, 22050, 32, &[0.5, -0.5, 0.25, 1.]), &mut samples), Ok(22050));
+    assert_eq!(samples, [1., 0.5, 0.25]);
+}
+
+#[test]
+fn integers_use_their_full_range() {
+    let decoded = decode(&wav(1, 48000, 16, &[16384, -32768, 32767])).unwrap();
+    assert_eq!(decoded.signal.samples(), [0.5, -1., 32767. / 32768.]);
+}
+
+#[test]
+fn invalid_data_is_rejected() {
+    assert_eq!(decode(b"not a wav file").unwrap_err(), DecodeError::UnknownFormat);
+    let mut header = wav::<f32>(1, 48000, 32, &[]);
+    // no channels
+    header[22] = 0;
+    assert_eq!(decode(&header).unwrap_err(), DecodeError::BadHeader);
+}
+
+#[test]
+fn decoding_is_all_or_nothing() {
+    let data = wav(1, 48000, 16, &[16384; 8]);
+    let mut samples = vec![1.];
+    assert_eq!(decode_into(&data[..data.len() - 3], &mut samples), Err(DecodeError::Truncated));
+    assert_eq!(samples, [1.]);
+}
+
+// decodes its data parameter, logging failures
+struct Sampler {
+    samples: Vec<f32>,
+}
+
+impl Dsp for Sampler {
+    fn name() -> &'static str {
+        "Sampler"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter {
+            ty: ParameterType::Data {
+                setter: |data, dsp| {
+                    dsp.samples.clear();
+                    if let Err(error) = decode_into(data, &mut dsp.samples) {
+                        interop::log_error(&format!("Couldn't decode samples: {error}"));
+                    }
+                },
+                getter: |_| None,
+            },
+            name: "samples",
+            unit: "",
+            desc: "",
+        }]
+    }
+
+    fn create() -> Self {
+        Sampler { samples: vec![] }
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {}
+}
+
+#[test]
+fn setters_can_log_through_fmod() {
+    let mut host = OfflineHost::<Sampler>::new(48000, 32);
+    OfflineHost::<Sampler>::take_log();
+    assert_eq!(host.set_data(0, &wav(1, 48000, 16, &[16384])), FMOD_OK);
+    assert_eq!(host.dsp().samples, [0.5]);
+    assert!(OfflineHost::<Sampler>::take_log().is_empty());
+
+    host.set_data(0, b"RIFF");
+    assert_eq!(OfflineHost::<Sampler>::take_log(), ["Couldn't decode samples: unknown audio format"]);
+    // outside of a callback, there's no DSP to log for
+    interop::log_error("lost");
+    assert!(OfflineHost::<Sampler>::take_log().is_empty());
+}
+
+// waits for the loader to swap in its latest load
+fn finish(loader: &mut Loader) {
+    assert!(loader.wait(Duration::from_secs(10)), "Decoding took too long");
+}
+
+#[test]
+fn loaders_decode_in_the_background() {
+    let mut loader = Loader::new();
+    assert_eq!(loader.state(), LoadState::Empty);
+    loader.load(&wav(1, 48000, 16, &[16384; 4]));
+    assert_eq!(loader.state(), LoadState::Loading);
+    assert!(loader.current().is_none());
+    finish(&mut loader);
+    assert_eq!(loader.state(), LoadState::Ready);
+    assert_eq!(loader.current().unwrap().signal.samples(), [0.5; 4]);
+
+    // the previous audio stays until the next load finishes
+    loader.load(b"RIFF");
+    assert_eq!(loader.current().unwrap().frames(), 4);
+    finish(&mut loader);
+    assert_eq!(loader.state(), LoadState::Failed(DecodeError::UnknownFormat));
+    assert!(loader.current().is_none());
+}
+
+#[test]
+fn loaders_keep_only_the_latest_load() {
+    let mut loader = Loader::new();
+    loader.load(&wav(1, 48000, 16, &[0; 1 << 16]));
+    loader.load(&wav(1, 48000, 16, &[16384]));
+    finish(&mut loader);
+    assert_eq!(loader.current().unwrap().signal.samples(), [0.5]);
+    // loads finish in order, so the older one was already discarded or skipped
+    assert!(!loader.update());
+    assert_eq!(loader.current().unwrap().frames(), 1);
+
+    // more loads than the worker's queue holds, each replacing the audio
+    for frames in 1..=40 {
+        loader.load(&wav(1, 48000, 16, &vec![16384; frames]));
+        if frames % 8 == 0 {
+            finish(&mut loader);
+            assert_eq!(loader.current().unwrap().frames(), frames);
+        }
+    }
+}
+
+#[test]
+fn formats_are_detected_from_headers() {
+    assert_eq!(Format::detect(&wav(1, 48000, 32, &[0.])), Some(Format::Wav));
+    assert_eq!(Format::detect(b"fLaC\0\0\0\x22"), Some(Format::Flac));
+    // FLAC files may start with an ID3 tag, whose size skips the header
+    let mut tagged = b"ID3\x04\0\0\0\0\0\x02\0\0".to_vec();
+    tagged.extend_from_slice(b"fLaC");
+    assert_eq!(Format::detect(&tagged), Some(Format::Flac));
+    assert_eq!(Format::detect(&aiff(1, 16, 48000, &[])), Some(Format::Aiff));
+
+    let mut ogg = b"OggS".to_vec();
+    ogg.resize(26, 0);
+    ogg.extend_from_slice(b"\x01\x1e\x01vorbis");
+    assert_eq!(Format::detect(&ogg), Some(Format::OggVorbis));
+    // Ogg streams of other codecs aren't supported
+    ogg[28..34].copy_from_slice(b"opus!!");
+    assert_eq!(Format::detect(&ogg), None);
+    assert_eq!(Format::detect(&[0; 64]), None);
+}
+
+// an AIFF file with big-endian integer samples
+fn aiff(channels: u16, bits: u16, sample_rate: u32, samples: &[u8]) -> Vec<u8> {
+    let frames = samples.len() / (channels * bits.div_ceil(8)) as usize;
+    let mut common = vec![];
+    common.extend_from_slice(&channels.to_be_bytes());
+    common.extend_from_slice(&(frames as u32).to_be_bytes());
+    common.extend_from_slice(&bits.to_be_bytes());
+    // sample rates are 80-bit floats, with an explicit integer bit
+    let shift = sample_rate.leading_zeros() + 32;
+    common.extend_from_slice(&(16383 + 63 - shift as u16).to_be_bytes());
+    common.extend_from_slice(&((sample_rate as u64) << shift).to_be_bytes());
+
+    let mut chunks = vec![];
+    for (id, body) in [(b"COMM", common), (b"SSND", [&[0; 8], samples].concat())] {
+        chunks.extend_from_slice(id);
+        chunks.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        chunks.extend_from_slice(&body);
+    }
+    [b"FORM".as_slice(), &(chunks.len() as u32 + 4).to_be_bytes(), b"AIFF", &chunks].concat()
+}
+
+#[test]
+#[cfg(feature = "aiff")]
+fn aiff_is_decoded() {
+    let decoded = decode(&aiff(2, 16, 44100, &[0x40, 0, 0xc0, 0, 0x7f, 0xff, 0x80, 0])).unwrap();
+    assert_eq!(decoded.sample_rate, 44100);
+    assert_eq!(decoded.channels(), 2);
+    assert_eq!(decoded.signal.samples(), [0.5, -0.5, 32767. / 32768., -1.]);
+
+    // 24-bit samples take three bytes
+    let decoded = decode(&aiff(1, 24, 22050, &[0x40, 0, 0, 0xff, 0xff, 0xff])).unwrap();
+    assert_eq!(decoded.sample_rate, 22050);
+    assert_eq!(decoded.signal.samples(), [0.5, -1. / 8388608.]);
+
+    let data = aiff(1, 16, 48000, &[0x40, 0, 0x40, 0]);
+    assert_eq!(decode(&data[..data.len() - 1]).unwrap_err(), DecodeError::Truncated);
+}
+
+// a FLAC stream with a single frame, which holds each channel as verbatim (uncompressed) samples
+#[cfg(feature = "flac")]
+fn flac(channels: u8, bits: u8, sample_rate: u32, samples: &[i32]) -> Vec<u8> {
+    let frames = (samples.len() / channels as usize) as u64;
+    let mut info = BitWriter::default();
+    // the block size range, unknown frame sizes, the format, the length and an unknown checksum
+    for (value, width) in [
+        (16, 16),
+        (16, 16),
+        (0, 48),
+        (sample_rate as u64, 20),
+        (channels as u64 - 1, 3),
+        (bits as u64 - 1, 5),
+        (frames, 36),
+        (0, 64),
+        (0, 64),
+    ] {
+        info.write(value, width);
+    }
+
+    let size_code = match bits {
+        8 => 1,
+        16 => 4,
+        24 => 6,
+        _ => panic!("{bits}-bit samples can't be described in a frame header"),
+    };
+    let mut frame = BitWriter::default();
+    // the sync code, a 16-bit block size after the header, the sample rate from the stream info,
+    // independent channels, and frame number 0
+    for (value, width) in [
+        (0xfff8, 16),
+        (7, 4),
+        (0, 4),
+        (channels as u64 - 1, 4),
+        (size_code << 1, 4),
+        (0, 8),
+        (frames - 1, 16),
+    ] {
+        frame.write(value, width);
+    }
+    frame.write(crc(&frame.bytes, 0x07, 8), 8);
+    for channel in 0..channels as usize {
+        // a verbatim subframe, without wasted bits
+        frame.write(0b10, 8);
+        for sample in samples.iter().skip(channel).step_by(channels as usize) {
+            frame.write(*sample as u64 & ((1 << bits) - 1), bits as u32);
+        }
+    }
+    frame.write(crc(&frame.bytes, 0x8005, 16), 16);
+    [b"fLaC".as_slice(), &[0x80, 0, 0, 34], &info.bytes, &frame.bytes].concat()
+}
+
+// writes bits from the most significant down, padding the last byte with zeros
+#[cfg(feature = "flac")]
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+#[cfg(feature = "flac")]
+impl BitWriter {
+    fn write(&mut self, value: u64, width: u32) {
+        for i in (0..width).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            *self.bytes.last_mut().unwrap() |= ((value >> i & 1) as u8) << (7 - self.len % 8);
+            self.len += 1;
+        }
+    }
+}
+
+#[cfg(feature = "flac")]
+fn crc(data: &[u8], polynomial: u64, width: u32) -> u64 {
+    let mut crc = 0;
+    for byte in data {
+        crc ^= (*byte as u64) << (width - 8);
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc >> width != 0 {
+                crc ^= polynomial | 1 << width;
+            }
+        }
+    }
+    crc
+}
+
+#[test]
+#[cfg(feature = "flac")]
+fn flac_is_decoded() {
+    let decoded = decode(&flac(2, 16, 44100, &[16384, -16384, 32767, -32768])).unwrap();
+    assert_eq!(decoded.sample_rate, 44100);
+    assert_eq!((decoded.channels(), decoded.frames()), (2, 2));
+    assert_eq!(decoded.signal.samples(), [0.5, -0.5, 32767. / 32768., -1.]);
+
+    let decoded = decode(&flac(1, 24, 22050, &[4194304, -1])).unwrap();
+    assert_eq!(decoded.sample_rate, 22050);
+    assert_eq!(decoded.signal.samples(), [0.5, -1. / 8388608.]);
+
+    let data = flac(1, 16, 48000, &[16384; 4]);
+    assert_eq!(decode(&data[..data.len() - 1]).unwrap_err(), DecodeError::Truncated);
+}
+
+// a mono stream at 8 kHz of three 256-sample blocks, each holding only a single spectral line at
+// half of full scale, with a flat floor. Built by hand, since the smallest valid setup is short
+#[cfg(feature = "vorbis")]
+const VORBIS: &[u8] = &[
+    0x4f, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1e, 0x4b, 0xca, 0x05, 0x00, 0x00, 0x00, 0x00, 0xe6, 0x00, 0xd2, 0x26,
+    0x01, 0x1e, 0x01, 0x76, 0x6f, 0x72, 0x62, 0x69, 0x73, 0x00, 0x00, 0x00, 0x00, 0x01, 0x40, 0x1f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x88, 0x01, 0x4f, 0x67, 0x67, 0x53, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1e, 0x4b, 0xca, 0x05, 0x01, 0x00,
+    0x00, 0x00, 0xb3, 0x0c, 0xa5, 0x96, 0x02, 0x10, 0x3d, 0x03, 0x76, 0x6f, 0x72, 0x62, 0x69, 0x73, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x05,
+    0x76, 0x6f, 0x72, 0x62, 0x69, 0x73, 0x00, 0x42, 0x43, 0x56, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x5f, 0x40,
+    0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x1c, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0xf0, 0x07, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x4f, 0x67, 0x67, 0x53, 0x00, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1e, 0x4b, 0xca, 0x05,
+    0x02, 0x00, 0x00, 0x00, 0x2d, 0xa3, 0x69, 0xa3, 0x03, 0x13, 0x13, 0x13, 0xfe, 0xff, 0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0xff, 0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0xff,
+    0x23, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+#[cfg(feature = "vorbis")]
+fn vorbis_is_decoded() {
+    let decoded = decode(VORBIS).unwrap();
+    assert_eq!(decoded.sample_rate, 8000);
+    // the first block only primes the overlap, and each after it adds half a block
+    assert_eq!((decoded.channels(), decoded.frames()), (1, 256));
+    let samples = decoded.signal.samples();
+    assert_eq!(samples[..4], [11934. / 32768., 12595. / 32768., 13205. / 32768., 13762. / 32768.]);
+    assert_eq!(samples.iter().fold(0., |max: f32, it| max.max(it.abs())), 16383. / 32768.);
+    // every block is the same, so the tone repeats
+    assert_eq!(samples[..128], samples[128..]);
+
+    assert_eq!(decode(&VORBIS[..VORBIS.len() - 1]).unwrap_err(), DecodeError::Truncated);
+}
+
+#[test]
+#[cfg(feature = "raw")]
+fn raw_pcm_is_decoded_as_described() {
+    let format = RawPcm {
+        sample: PcmSample::I16,
+        big_endian: false,
+        channels: 2,
+        sample_rate: 8000,
+    };
+    let decoded = decode_as(&[0, 0x40, 0, 0xc0], Format::Raw(format)).unwrap();
+    assert_eq!(decoded.sample_rate, 8000);
+    assert_eq!(decoded.signal.samples(), [0.5, -0.5]);
+    // a trailing partial frame means something is missing
+    assert_eq!(
+        decode_as(&[0, 0x40, 0, 0xc0, 0, 0x20], Format::Raw(format)).unwrap_err(),
+        DecodeError::Truncated
+    );
+
+    let format = RawPcm {
+        sample: PcmSample::U8,
+        channels: 1,
+        ..format
+    };
+    let decoded = decode_as(&[128, 192, 0], Format::Raw(format)).unwrap();
+    assert_eq!(decoded.signal.samples(), [0., 0.5, -1.]);
+    // raw data can't be detected
+    assert_eq!(decode(&[128, 192, 0]).unwrap_err(), DecodeError::UnknownFormat);
+}
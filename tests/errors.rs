@@ -0,0 +1,240 @@
+#![feature(macro_metavar_expr)]
+
+use scamble::FmodError;
+use scamble::dsp::compose::Chain;
+use scamble::dsp::interop;
+use scamble::dsp::mix::WetDry;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspError, DspErrorKind, DspType, Parameter, ParameterType, ParameterValue};
+use scamble::enum_param;
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_FORMAT, FMOD_ERR_INVALID_PARAM, FMOD_ERR_PLUGIN, FMOD_OK};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// only handles mono signals, and only allows a gain of up to 1
+struct Limited {
+    gain: f32,
+}
+
+impl Limited {
+    fn set_gain(&mut self, gain: f32) -> Result<(), DspError> {
+        if gain > 1. {
+            return Err(DspError::invalid_param(format!("gain {gain} would clip")));
+        }
+        self.gain = gain;
+        Ok(())
+    }
+}
+
+impl Dsp for Limited {
+    fn name() -> &'static str {
+        "Limited"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter::new(
+            "gain",
+            ParameterType::Float {
+                min: 0.,
+                max: 2.,
+                default: 1.,
+                setter: |value, dsp| interop::report(dsp.set_gain(value)),
+                getter: |dsp| dsp.gain,
+            },
+        )]
+    }
+
+    fn create() -> Self {
+        Limited { gain: 1. }
+    }
+
+    fn try_reset(&mut self) -> Result<(), DspError> {
+        Err(DspError::plugin("reset isn't supported"))
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        let _ = self.try_read(input, None, output);
+    }
+
+    fn try_read(&mut self, input: SignalConst, _: Option<SignalConst>, mut output: SignalMut) -> Result<(), DspError> {
+        if input.channels() != 1 {
+            return Err(DspError::format("only mono signals are supported"));
+        }
+        output.map(&input, |x: f32| x * self.gain);
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_setters_are_reported() {
+    let mut host = OfflineHost::<Limited>::new(48000, 4);
+    OfflineHost::<Limited>::take_log();
+    assert_eq!(host.set_float(0, 0.5), FMOD_OK);
+    assert_eq!(host.set_float(0, 1.5), FMOD_ERR_INVALID_PARAM);
+    assert_eq!(host.dsp().gain, 0.5);
+    assert_eq!(OfflineHost::<Limited>::take_log(), ["gain 1.5 would clip"]);
+    // the error doesn't linger for later setters
+    assert_eq!(host.set_float(0, 0.25), FMOD_OK);
+}
+
+#[test]
+fn failed_reads_are_reported() {
+    let mut host = OfflineHost::<Chain<WetDry<Limited>, Limited>>::new(48000, 4);
+    OfflineHost::<Chain<WetDry<Limited>, Limited>>::take_log();
+    let mut output = SignalBuf::new();
+    assert_eq!(host.process(SignalConst::new(&[1.; 4], 1), &mut output), FMOD_OK);
+    assert_eq!(host.process(SignalConst::new(&[1.; 8], 2), &mut output), FMOD_ERR_FORMAT);
+    assert_eq!(OfflineHost::<Chain<WetDry<Limited>, Limited>>::take_log(), ["only mono signals are supported"]);
+    assert_eq!(host.set_float(0, 0.5), FMOD_OK);
+
+    assert_eq!(host.reset(), FMOD_ERR_PLUGIN);
+    assert_eq!(OfflineHost::<Chain<WetDry<Limited>, Limited>>::take_log(), ["reset isn't supported"]);
+}
+
+#[test]
+fn setters_return_their_errors() {
+    let mut dsp = Limited::create();
+    let gain = &Limited::parameters()[0];
+    // errors reported outside of setters are ignored, rather than failing the next setter
+    interop::report(Err(DspError::plugin("not in a setter")));
+    assert_eq!(gain.try_set(&mut dsp, &ParameterValue::Float(0.5)), Ok(()));
+    assert_eq!(
+        gain.try_set(&mut dsp, &ParameterValue::Float(1.5)),
+        Err(DspError::invalid_param("gain 1.5 would clip"))
+    );
+    assert_eq!(dsp.gain, 0.5);
+    assert_eq!(
+        gain.try_set(&mut dsp, &ParameterValue::Int(1)).map_err(|it| it.kind()),
+        Err(DspErrorKind::InvalidParam)
+    );
+    assert_eq!(gain.try_set(&mut dsp, &ParameterValue::Float(0.25)), Ok(()));
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Shape {
+    Sine,
+    Square,
+}
+
+// has a shape, and a level whose setter and getter both panic
+struct Fragile {
+    shape: Shape,
+}
+
+impl Dsp for Fragile {
+    fn name() -> &'static str {
+        "Fragile"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter::new("shape", enum_param!(shape: Shape, options: [Sine, Square], default: Sine)),
+            Parameter::new(
+                "level",
+                ParameterType::Float {
+                    min: 0.,
+                    max: 1.,
+                    default: 1.,
+                    setter: |_, _| panic!("level can't be set"),
+                    getter: |_| panic!("level can't be read"),
+                },
+            ),
+        ]
+    }
+
+    fn create() -> Self {
+        Fragile { shape: Shape::Sine }
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        output.copy(&input);
+    }
+}
+
+#[test]
+fn unknown_variants_are_reported() {
+    let mut host = OfflineHost::<Fragile>::new(48000, 4);
+    OfflineHost::<Fragile>::take_log();
+    assert_eq!(host.set_int(0, 1), FMOD_OK);
+    assert_eq!(host.set_int(0, 2), FMOD_ERR_INVALID_PARAM);
+    assert_eq!(host.dsp().shape, Shape::Square);
+    assert_eq!(OfflineHost::<Fragile>::take_log(), ["Unknown variant 2 for field shape"]);
+}
+
+#[test]
+fn panicking_parameters_fail() {
+    let mut host = OfflineHost::<Fragile>::new(48000, 4);
+    assert_eq!(host.set_float(1, 0.5), FMOD_ERR_PLUGIN);
+    assert_eq!(host.get_value_str(1).0, FMOD_ERR_PLUGIN);
+    // the DSP stays usable
+    assert_eq!(host.set_int(0, 1), FMOD_OK);
+    assert_eq!(host.get_value_str(0), (FMOD_OK, "Square".to_string()));
+}
+
+static OPENED: AtomicBool = AtomicBool::new(false);
+
+// a generator that needs a device, which only one instance can hold at a time
+struct Device;
+
+impl Dsp for Device {
+    fn name() -> &'static str {
+        "Device"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn create() -> Self {
+        Self::try_create().unwrap()
+    }
+
+    fn try_create() -> Result<Self, DspError> {
+        if OPENED.swap(true, Ordering::Relaxed) {
+            return Err(FmodError::MEMORY.into());
+        }
+        Ok(Device)
+    }
+
+    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
+        output.fill(0.);
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        OPENED.store(false, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn failed_creation_is_reported() {
+    let host = OfflineHost::<Device>::try_new(48000, 4).ok().unwrap();
+    OfflineHost::<Device>::take_log();
+    assert_eq!(OfflineHost::<WetDry<Device>>::try_new(48000, 4).err(), Some(FmodError::MEMORY));
+    assert_eq!(OfflineHost::<Device>::take_log(), [FmodError::MEMORY.to_string()]);
+
+    // zero-sized DSPs are released like any other
+    drop(host);
+    assert!(OfflineHost::<Device>::try_new(48000, 4).is_ok());
+}
@@ -0,0 +1,123 @@
+#![feature(macro_metavar_expr)]
+
+use scamble::dsp::interop::{into_desc, to_ascii};
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_INVALID_PARAM, FMOD_OK};
+use scamble::{bool_param, enum_param, float_param};
+use std::ffi::{CStr, c_char};
+
+#[derive(Copy, Clone)]
+enum Temperament {
+    Equal,
+    Just,
+}
+
+// a DSP with names and descriptions that FMOD can't display as-is
+struct Tuner {
+    pitch: f32,
+    temperament: Temperament,
+    sharp: bool,
+    muted: bool,
+    desc: &'static str,
+}
+
+impl Dsp for Tuner {
+    fn name() -> &'static str {
+        "Accordeur à l’ancienne"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter::with_unit("hauteur", "±st", float_param!(pitch, range: -12.0..12.0, default: 0.)),
+            Parameter::new("temperament", enum_param!(temperament: Temperament, options: [Equal, Just], default: Equal)),
+            Parameter {
+                ty: ParameterType::Bool {
+                    default: false,
+                    names: Some(("Natural", "Sharp ♯")),
+                    setter: |value, dsp| dsp.sharp = value,
+                    getter: |dsp| dsp.sharp,
+                },
+                name: "sharp",
+                unit: "",
+                desc: "",
+            },
+            Parameter::new("muted", bool_param!(muted, default: false)),
+            Parameter {
+                ty: ParameterType::Data {
+                    setter: |_, _| {},
+                    getter: |dsp| Some((&[], Some(dsp.desc))),
+                },
+                name: "table\0nul",
+                unit: "",
+                desc: "Tuning table, with “quotes”",
+            },
+        ]
+    }
+
+    fn create() -> Self {
+        Tuner {
+            pitch: 0.,
+            temperament: Temperament::Equal,
+            sharp: false,
+            muted: false,
+            desc: "",
+        }
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {}
+}
+
+fn text(s: &[c_char]) -> &str {
+    unsafe { CStr::from_ptr(s.as_ptr()) }.to_str().unwrap()
+}
+
+#[test]
+fn names_are_converted_to_ascii() {
+    let desc = into_desc::<Tuner>();
+    // accents and typographic punctuation have plain equivalents
+    assert_eq!(text(&desc.name), "Accordeur a l'ancienne");
+    let params = unsafe { std::slice::from_raw_parts(desc.paramdesc, desc.numparameters as usize) };
+    let param = |i: usize| unsafe { &*params[i] };
+    assert_eq!(text(&param(0).label), "+-st");
+    assert_eq!(text(&param(4).name), "table");
+    assert_eq!(unsafe { CStr::from_ptr(param(4).description) }.to_str(), Ok("Tuning table, with \"quotes\""));
+    assert_eq!(to_ascii("ÆØÅ æøå"), "AEOA aeoa");
+}
+
+#[test]
+fn values_are_shown_as_text() {
+    let mut host = OfflineHost::<Tuner>::new(48000, 32);
+    host.set_float(0, -1.5);
+    host.set_int(1, 1);
+    assert_eq!(host.get_value_str(0), (FMOD_OK, "-1.50".to_string()));
+    assert_eq!(host.get_value_str(1), (FMOD_OK, "Just".to_string()));
+    assert_eq!(host.get_value_str(2), (FMOD_OK, "Natural".to_string()));
+    host.set_bool(2, true);
+    assert_eq!(host.get_value_str(2), (FMOD_OK, "Sharp #".to_string()));
+    assert_eq!(host.get_value_str(3), (FMOD_OK, "Off".to_string()));
+    assert_eq!(host.get_value_str(5).0, FMOD_ERR_INVALID_PARAM);
+}
+
+#[test]
+fn data_descriptions_fit_any_length() {
+    let mut host = OfflineHost::<Tuner>::new(48000, 32);
+    for (desc, shown) in [
+        ("", ""),
+        ("Pythagoréen", "Pythagoreen"),
+        ("Werckmeister III, with a very long description", "Werckmeister III, with a very l"),
+        ("ééééééééééééééééééééééééééééééééé", "eeeeeeeeeeeeeeeeeeeeeeeeeeeeeee"),
+    ] {
+        host.dsp_mut().desc = desc;
+        assert_eq!(host.get_value_str(4), (FMOD_OK, shown.to_string()));
+    }
+}
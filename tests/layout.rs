@@ -0,0 +1,123 @@
+use scamble::dsp::layout::{ChannelLayout, MixMatrix, Speaker, Upmix};
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType};
+use std::f32::consts::FRAC_1_SQRT_2;
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+#[test]
+fn layouts_are_ordered_by_speaker() {
+    let layout = ChannelLayout::of(&[Speaker::SurroundLeft, Speaker::FrontRight, Speaker::FrontLeft]);
+    assert_eq!(layout.channels(), 3);
+    assert_eq!(layout.speaker(0), Some(Speaker::FrontLeft));
+    assert_eq!(layout.position(Speaker::SurroundLeft), Some(2));
+    assert_eq!(layout.position(Speaker::FrontCenter), None);
+    assert_eq!(ChannelLayout::from_channels(6), ChannelLayout::FIVE_POINT_ONE);
+    assert_eq!(ChannelLayout::from_channels(3), ChannelLayout::raw(3));
+    assert_eq!(ChannelLayout::raw(3).speaker(0), None);
+}
+
+#[test]
+fn channel_masks_are_read() {
+    // front left, right, and back centre
+    let layout = ChannelLayout::from_channel_mask(0x103, 3);
+    assert_eq!(layout.speaker(2), Some(Speaker::BackCenter));
+    assert_eq!(ChannelLayout::from_channel_mask(0x1, 1), ChannelLayout::MONO);
+    // more speakers than channels can't be right
+    assert_eq!(ChannelLayout::from_channel_mask(0x3f, 2), ChannelLayout::STEREO);
+}
+
+#[test]
+fn surround_downmixes_with_itu_coefficients() {
+    let matrix = MixMatrix::new(ChannelLayout::FIVE_POINT_ONE, ChannelLayout::STEREO, Upmix::default());
+    // left gets front left, centre, and surround left, and no LFE
+    let left: Vec<f32> = (0..6).map(|it| matrix.gain(0, it)).collect();
+    let expected = [1., 0., FRAC_1_SQRT_2, 0., FRAC_1_SQRT_2, 0.];
+    assert!(left.iter().zip(expected).all(|(a, b)| close(*a, b)), "{left:?}");
+
+    // back speakers fold into the surrounds, and from there into the fronts
+    let matrix = MixMatrix::new(ChannelLayout::SEVEN_POINT_ONE, ChannelLayout::FIVE_POINT_ONE, Upmix::default());
+    assert_eq!(matrix.gain(4, 6), 1.);
+    let matrix = MixMatrix::new(ChannelLayout::SEVEN_POINT_ONE, ChannelLayout::STEREO, Upmix::default());
+    assert!(close(matrix.gain(0, 6), FRAC_1_SQRT_2));
+    assert_eq!(matrix.gain(1, 6), 0.);
+}
+
+#[test]
+fn mono_and_stereo_convert_as_before() {
+    let stereo = SignalConst::new(&[1., 0.5], 2);
+    let mono: Vec<f32> = stereo.mix_to(ChannelLayout::MONO, Upmix::default()).collect();
+    assert_eq!(mono, [0.75]);
+
+    let mono = SignalConst::new(&[0.5, 1.], 1);
+    let (left, right) = mono.read_stereo();
+    assert_eq!(left.collect::<Vec<_>>(), [0.5, 1.]);
+    assert_eq!(right.collect::<Vec<_>>(), [0.5, 1.]);
+    let discrete: Vec<f32> = mono.mix_to(ChannelLayout::STEREO, Upmix::DISCRETE).collect();
+    assert!(discrete.iter().zip([0.5, 0.5, 1., 1.]).all(|(a, b)| close(*a, b * FRAC_1_SQRT_2)));
+}
+
+#[test]
+fn read_stereo_uses_the_layout() {
+    // only the LFE channel is playing, which is dropped
+    let frame = [0., 0., 0., 1., 0., 0.];
+    let signal = SignalConst::new(&frame, 6);
+    let (left, right) = signal.read_stereo();
+    assert_eq!(left.chain(right).collect::<Vec<_>>(), [0., 0.]);
+
+    // as raw channels, the first two are taken as they are
+    let frame = [0.25, 0.5, 1.];
+    let signal = SignalConst::new(&frame, 3);
+    let (left, right) = signal.read_stereo();
+    assert_eq!(left.chain(right).collect::<Vec<_>>(), [0.25, 0.5]);
+}
+
+#[test]
+fn spread_upmix_fills_missing_speakers() {
+    let stereo = SignalConst::new(&[1., 0.], 2);
+    let surround: Vec<f32> = stereo.mix_to(ChannelLayout::FIVE_POINT_ONE, Upmix::SPREAD).collect();
+    assert!(
+        surround.iter().zip([1., 0., 0.5, 0., FRAC_1_SQRT_2, 0.]).all(|(a, b)| close(*a, b)),
+        "{surround:?}"
+    );
+    let surround: Vec<f32> = stereo.mix_to(ChannelLayout::FIVE_POINT_ONE, Upmix::DISCRETE).collect();
+    assert_eq!(surround, [1., 0., 0., 0., 0., 0.]);
+}
+
+// records the layouts it's given
+struct Layouts {
+    seen: Vec<(ChannelLayout, ChannelLayout)>,
+}
+
+impl Dsp for Layouts {
+    fn name() -> &'static str {
+        "Layouts"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Layouts { seen: vec![] }
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.seen.push((input.layout(), output.layout()));
+    }
+}
+
+#[test]
+fn layouts_are_passed_from_fmod() {
+    let mut host = OfflineHost::<Layouts>::new(48000, 4);
+    let surround = SignalConst::new(&[1.; 24], 6).with_layout(ChannelLayout::FIVE_POINT_ONE);
+    host.render(surround, 4);
+    assert_eq!(host.dsp().seen, [(ChannelLayout::FIVE_POINT_ONE, ChannelLayout::FIVE_POINT_ONE)]);
+}
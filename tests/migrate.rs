@@ -0,0 +1,241 @@
+#![feature(macro_metavar_expr)]
+
+use scamble::dsp::compose::Chain;
+use scamble::dsp::migrate::{Change, ChangeKind, Migration, ParameterKind, ParameterTable, Preset, migrate_index, migrate_name, migrate_value, pair_versions};
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType, ParameterValue};
+use scamble::{enum_param, float_param};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Hard,
+    Soft,
+}
+
+// version 2 had `volume`, `pan`, `tone` and `mode`; version 5 renamed `volume` and removed `tone`;
+// version 7 renamed `pan`, moved it after `mode`, and swapped the options of `mode`
+struct Shaper {
+    gain: f32,
+    mode: Mode,
+    width: f32,
+}
+
+impl Dsp for Shaper {
+    fn name() -> &'static str {
+        "Shaper"
+    }
+
+    fn version() -> u32 {
+        7
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![
+            Parameter::new("gain", float_param!(gain, range: 0.0..2.0, default: 1.0)),
+            Parameter::new("mode", enum_param!(mode: Mode, options: [Hard, Soft], default: Hard)),
+            Parameter::new("width", float_param!(width, range: -1.0..1.0, default: 0.0)),
+        ]
+    }
+
+    fn migrations() -> Vec<Migration> {
+        // listed out of order, which shouldn't matter
+        vec![
+            Migration::new(5, &["gain", "pan", "mode"])
+                .rename("pan", "width")
+                .remap_ordinals("mode", &[(0, 1), (1, 0)]),
+            Migration::new(2, &["volume", "pan", "tone", "mode"]).rename("volume", "gain"),
+        ]
+    }
+
+    fn create() -> Self {
+        Shaper {
+            gain: 1.,
+            mode: Mode::Hard,
+            width: 0.,
+        }
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {}
+}
+
+#[test]
+fn data_between_migrations_is_chained() {
+    // version 3 is covered by the migration for version 2, then the one for version 5
+    for version in [2, 3, 4] {
+        assert_eq!(migrate_name::<Shaper>(version, "volume").as_deref(), Some("gain"));
+        assert_eq!(migrate_name::<Shaper>(version, "pan").as_deref(), Some("width"));
+        assert_eq!(migrate_name::<Shaper>(version, "tone"), None);
+        assert_eq!(migrate_index::<Shaper>(version, 1), Some(2));
+        assert_eq!(migrate_index::<Shaper>(version, 3), Some(1));
+        assert_eq!(
+            migrate_value::<Shaper>(version, "mode", ParameterValue::Int(0)),
+            Some((1, ParameterValue::Int(1)))
+        );
+    }
+    for version in [5, 6] {
+        assert_eq!(migrate_name::<Shaper>(version, "volume"), None);
+        assert_eq!(migrate_name::<Shaper>(version, "pan").as_deref(), Some("width"));
+        assert_eq!(migrate_index::<Shaper>(version, 2), Some(1));
+    }
+    assert_eq!(migrate_name::<Shaper>(7, "width").as_deref(), Some("width"));
+    assert_eq!(migrate_name::<Shaper>(7, "pan"), None);
+
+    let preset = Preset {
+        version: 3,
+        values: vec![
+            ("volume".into(), ParameterValue::Float(0.5)),
+            ("pan".into(), ParameterValue::Float(-0.5)),
+            ("tone".into(), ParameterValue::Float(1.)),
+            ("mode".into(), ParameterValue::Int(0)),
+        ],
+    };
+    let mut dsp = Shaper::create();
+    assert_eq!(preset.apply(&mut dsp), ["tone"]);
+    assert_eq!((dsp.gain, dsp.mode, dsp.width), (0.5, Mode::Soft, -0.5));
+}
+
+#[test]
+fn versions_pair_uniquely() {
+    let mut paired = vec![];
+    for first in 0..40 {
+        for second in 0..40 {
+            let version = pair_versions(first, second);
+            assert!(version < pair_versions(first + 1, second) && version < pair_versions(first, second + 1));
+            paired.push(version);
+        }
+    }
+    paired.sort();
+    paired.dedup();
+    assert_eq!(paired.len(), 40 * 40);
+}
+
+#[test]
+fn stages_are_migrated_separately() {
+    type Pair = Chain<Shaper, Shaper>;
+    // saved while the first stage was at version 3, and the second at version 5
+    let version = pair_versions(3, 5);
+    assert_eq!(migrate_name::<Pair>(version, "1.volume").as_deref(), Some("1.gain"));
+    assert_eq!(migrate_name::<Pair>(version, "2.volume"), None);
+    assert_eq!(migrate_name::<Pair>(version, "2.pan").as_deref(), Some("2.width"));
+    // saved as 1.volume, 1.pan, 1.tone, 1.mode, 2.gain, 2.pan and 2.mode
+    assert_eq!(migrate_index::<Pair>(version, 1), Some(2));
+    assert_eq!(migrate_index::<Pair>(version, 2), None);
+    assert_eq!(migrate_index::<Pair>(version, 5), Some(5));
+    assert_eq!(migrate_index::<Pair>(version, 6), Some(4));
+    assert_eq!(
+        migrate_value::<Pair>(version, "2.mode", ParameterValue::Int(0)),
+        Some((4, ParameterValue::Int(1)))
+    );
+    assert_eq!(migrate_name::<Pair>(Pair::version(), "2.width").as_deref(), Some("2.width"));
+    assert_eq!(migrate_name::<Pair>(Pair::version(), "2.pan"), None);
+
+    // nested stages are found through each combination in turn
+    let version = pair_versions(7, pair_versions(7, 2));
+    assert_eq!(migrate_name::<Chain<Shaper, Pair>>(version, "2.2.volume").as_deref(), Some("2.2.gain"));
+    assert_eq!(migrate_name::<Chain<Shaper, Pair>>(version, "2.1.volume"), None);
+}
+
+const V3: &str = "version\t3
+float\tvolume\t0\t2\t1
+float\tpan\t-1\t1\t0
+float\ttone\t0\t1\t0.5
+int\tmode\t0\t1\t0\tSoft,Hard
+";
+
+#[test]
+fn tables_are_diffed() {
+    let old: ParameterTable = V3.parse().unwrap();
+    let changes = old.diff(&ParameterTable::of::<Shaper>(), &Shaper::migrations());
+    let change = |kind, breaking, migrated| Change { kind, breaking, migrated };
+    assert_eq!(
+        changes,
+        [
+            change(
+                ChangeKind::Renamed {
+                    from: "volume".into(),
+                    to: "gain".into()
+                },
+                true,
+                true
+            ),
+            change(
+                ChangeKind::Renamed {
+                    from: "pan".into(),
+                    to: "width".into()
+                },
+                true,
+                true
+            ),
+            change(
+                ChangeKind::Moved {
+                    name: "width".into(),
+                    from: 1,
+                    to: 2
+                },
+                true,
+                true
+            ),
+            change(ChangeKind::Removed { name: "tone".into(), index: 2 }, true, true),
+            change(
+                ChangeKind::Moved {
+                    name: "mode".into(),
+                    from: 3,
+                    to: 1
+                },
+                true,
+                true
+            ),
+            change(ChangeKind::OptionsChanged { name: "mode".into() }, true, true),
+        ]
+    );
+
+    // without migrations, renames look like removals and additions
+    let unmigrated = old.diff(&ParameterTable::of::<Shaper>(), &[]);
+    assert!(unmigrated.contains(&change(
+        ChangeKind::Removed {
+            name: "volume".into(),
+            index: 0
+        },
+        true,
+        false
+    )));
+    assert!(unmigrated.contains(&change(ChangeKind::Added { name: "gain".into(), index: 0 }, false, false)));
+
+    // narrowing a range breaks saved values, but changing a default doesn't
+    let mut newer = ParameterTable::of::<Shaper>();
+    newer.version = 8;
+    newer.parameters[0].kind = ParameterKind::Float {
+        min: 0.,
+        max: 1.,
+        default: 0.5,
+    };
+    let changes: Vec<_> = ParameterTable::of::<Shaper>().diff(&newer, &[]).iter().map(Change::to_string).collect();
+    assert_eq!(changes, ["changed the range of `gain` (breaking)", "changed the default of `gain`"]);
+}
+
+#[test]
+fn tables_round_trip_through_text() {
+    let table = ParameterTable::of::<Shaper>();
+    let text = table.to_string();
+    assert_eq!(
+        text,
+        "version\t7\nfloat\tgain\t0\t2\t1\nint\tmode\t0\t1\t0\tHard,Soft\nfloat\twidth\t-1\t1\t0\n"
+    );
+    assert_eq!(text.parse(), Ok(table));
+
+    let old: ParameterTable = V3.replace('\n', "\r\n").parse().unwrap();
+    assert_eq!(old.to_string().parse(), Ok(old));
+
+    for (text, line) in [
+        ("", 1),
+        ("version\tx\n", 1),
+        ("\nversion\t1\nfloat\tgain\t0\t1\n", 3),
+        ("version\t1\nbool\tb\tmaybe\n", 2),
+    ] {
+        assert_eq!(text.parse::<ParameterTable>().map_err(|it| it.line), Err(line), "{text:?}");
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use common::Delay;
+use scamble::dsp::migrate::{ParameterTable, Preset};
+use scamble::dsp::mix::WetDry;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspErrorKind, ParameterValue};
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_DSP_DONTPROCESS, FMOD_ERR_INVALID_PARAM, FMOD_OK};
+
+const BLOCK: usize = 64;
+
+#[test]
+fn parameters_are_appended() {
+    let table = ParameterTable::of::<WetDry<Delay>>();
+    let names: Vec<_> = table.parameters.iter().map(|it| it.name.as_str()).collect();
+    assert_eq!(names, ["delay", "invert", "mix", "bypass"]);
+    assert_eq!(WetDry::<Delay>::parameter_count(), names.len());
+}
+
+#[test]
+fn parameters_are_forwarded() {
+    let mut host = OfflineHost::<WetDry<Delay>>::new(48000, BLOCK);
+    assert_eq!(host.set_int(0, 20), FMOD_OK);
+    assert_eq!(host.set_bool(1, true), FMOD_OK);
+    assert_eq!(host.set_float(2, 50.), FMOD_OK);
+    assert_eq!(host.set_float(0, 1.), FMOD_ERR_INVALID_PARAM);
+    assert_eq!(host.set_bool(4, true), FMOD_ERR_INVALID_PARAM);
+    assert_eq!(host.dsp().inner().delay, 20);
+    assert!(host.dsp().inner().invert);
+
+    let preset = Preset::capture(host.dsp_mut());
+    assert_eq!(
+        preset.values,
+        [
+            ("delay".to_string(), ParameterValue::Int(20)),
+            ("invert".to_string(), ParameterValue::Bool(true)),
+            ("mix".to_string(), ParameterValue::Float(50.)),
+            ("bypass".to_string(), ParameterValue::Bool(false)),
+        ]
+    );
+}
+
+#[test]
+fn dry_signal_is_aligned() {
+    // an inverted copy cancels out the dry signal once both line up
+    let mut host = OfflineHost::<WetDry<Delay>>::new(48000, BLOCK);
+    host.set_int(0, 10);
+    host.set_bool(1, true);
+    host.set_float(2, 50.);
+    host.render(SignalConst::new(&[], 1), BLOCK);
+
+    let input: Vec<f32> = (0..BLOCK * 8).map(|it| (it as f32 / 5.).sin()).collect();
+    let rendered = host.render(SignalConst::new(&input, 1), input.len());
+    assert_eq!(host.latency_samples(), 10);
+    assert!(rendered.samples.iter().all(|it| it.abs() < 1e-6));
+}
+
+#[test]
+fn dry_signal_follows_latency_changes() {
+    let mut host = OfflineHost::<WetDry<Delay>>::new(48000, BLOCK);
+    host.set_int(0, 10);
+    host.set_float(2, 0.);
+    host.render(SignalConst::new(&[], 1), BLOCK);
+
+    host.set_int(0, 30);
+    let input: Vec<f32> = (0..BLOCK * 4).map(|it| it as f32).collect();
+    let rendered = host.render_compensated(SignalConst::new(&input, 1), input.len());
+    assert_eq!(rendered.samples, input);
+}
+
+#[test]
+fn bypass_skips_processing() {
+    let mut host = OfflineHost::<WetDry<Delay>>::new(48000, BLOCK);
+    host.set_bool(3, true);
+    let input: Vec<f32> = (0..BLOCK * 2).map(|it| it as f32).collect();
+    let rendered = host.render(SignalConst::new(&input, 2), BLOCK);
+    assert_eq!(rendered.queries, [FMOD_ERR_DSP_DONTPROCESS]);
+    assert_eq!(rendered.samples, input);
+    assert!(host.dsp().inner().buffer.is_empty());
+
+    // the inner DSP doesn't run even if the query is bypassed, such as by a host reading directly
+    let mut dsp = WetDry::<Delay>::create();
+    assert!(WetDry::<Delay>::parameters()[3].set(&mut dsp, &ParameterValue::Bool(true)));
+    let mut output = [0.; BLOCK * 2];
+    dsp.read(SignalConst::new(&input, 2), SignalMut::new(&mut output, 2));
+    assert_eq!(output[..], input[..]);
+    assert!(dsp.inner().buffer.is_empty());
+}
+
+#[test]
+fn forwarded_parameters_fail_without_the_inner_dsp() {
+    let mut dsp = WetDry::<Delay>::create();
+    let delay = &WetDry::<Delay>::parameters()[0];
+    assert_eq!(delay.get(&mut dsp), None);
+    assert_eq!(
+        delay.try_set(&mut dsp, &ParameterValue::Int(20)).map_err(|it| it.kind()),
+        Err(DspErrorKind::InvalidParam)
+    );
+    assert!(!delay.set(&mut dsp, &ParameterValue::Int(20)));
+    assert_eq!(dsp.inner().delay, 0);
+}
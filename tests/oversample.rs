@@ -0,0 +1,159 @@
+use scamble::dsp::interop;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::oversample::{Oversampled, Quality};
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+use scamble::float_param;
+use std::f32::consts::PI;
+
+const BLOCK: usize = 64;
+
+// squares its input, producing harmonics at twice the input's frequency
+struct Square {
+    gain: f32,
+    latency: usize,
+    lengths: Vec<usize>,
+}
+
+impl Dsp for Square {
+    fn name() -> &'static str {
+        "Square"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter::new("gain", float_param!(gain, range: 0.0..2.0, default: 1.0))]
+    }
+
+    fn create() -> Self {
+        Square {
+            gain: 1.,
+            latency: 0,
+            lengths: vec![],
+        }
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.latency
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        self.lengths.push(output.length());
+        for (out, x) in output.samples_mut().iter_mut().zip(input.samples()) {
+            *out = x * x * self.gain;
+        }
+    }
+}
+
+// records the sample rate and block size it sees
+struct Rate {
+    rate: Option<u32>,
+    block: Option<(usize, usize)>,
+}
+
+impl Dsp for Rate {
+    fn name() -> &'static str {
+        "Rate"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Rate {
+            rate: interop::sample_rate(),
+            block: interop::max_block(),
+        }
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {
+        self.rate = interop::sample_rate();
+    }
+}
+
+fn sine(frequency: f32, frames: usize) -> Vec<f32> {
+    (0..frames).map(|it| (2. * PI * frequency * it as f32 / 48000.).sin()).collect()
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|it| it * it).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn parameters_are_appended() {
+    let names: Vec<_> = Oversampled::<Square, 2>::parameters().iter().map(|it| it.name).collect();
+    assert_eq!(names, ["gain", "quality"]);
+
+    let mut host = OfflineHost::<Oversampled<Square, 2>>::new(48000, BLOCK);
+    host.set_float(0, 0.5);
+    host.set_int(1, 2);
+    assert_eq!(host.dsp().inner().gain, 0.5);
+    assert_eq!(host.dsp().quality(), Quality::High);
+}
+
+#[test]
+fn inner_runs_at_higher_rate() {
+    let mut host = OfflineHost::<Oversampled<Square, 4>>::new(48000, BLOCK);
+    host.render(SignalConst::new(&[1.; BLOCK * 2], 1), BLOCK * 2);
+    assert_eq!(host.dsp().inner().lengths, [BLOCK * 4, BLOCK * 4]);
+    // latency at the higher rate is rounded up to whole samples at the mixer's rate
+    host.dsp_mut().inner_mut().latency = 5;
+    assert_eq!(host.latency_samples(), Quality::Medium.latency() + 2);
+
+    let mut host = OfflineHost::<Oversampled<Rate, 8>>::new(48000, BLOCK);
+    host.render(SignalConst::new(&[1.; BLOCK], 1), BLOCK);
+    assert_eq!(host.dsp().inner().rate, Some(48000 * 8));
+    // the block size is scaled alongside, with the offline host defaulting to stereo
+    assert_eq!(host.dsp().inner().block, Some((BLOCK * 8, 2)));
+}
+
+#[test]
+fn quality_changes_latency() {
+    let mut host = OfflineHost::<Oversampled<Square, 2>>::new(48000, BLOCK);
+    host.set_int(1, 0);
+    let low = host.latency_samples();
+    host.set_int(1, 2);
+    let high = host.latency_samples();
+    assert!(low < high);
+}
+
+#[test]
+fn aliasing_is_reduced() {
+    // squaring 15kHz gives 30kHz, which aliases to 18kHz at 48kHz but is filtered out when oversampled
+    let input = sine(15000., BLOCK * 32);
+    let mut plain = OfflineHost::<Square>::new(48000, BLOCK);
+    let plain = plain.render(SignalConst::new(&input, 1), input.len());
+    let mut oversampled = OfflineHost::<Oversampled<Square, 4>>::new(48000, BLOCK);
+    oversampled.set_int(1, 2);
+    let oversampled = oversampled.render_compensated(SignalConst::new(&input, 1), input.len());
+
+    // remove the DC offset of 0.5, leaving only the harmonic
+    let harmonic = |samples: &[f32]| rms(&samples[BLOCK..].iter().map(|it| it - 0.5).collect::<Vec<_>>());
+    assert!(harmonic(&plain.samples) > 0.3);
+    assert!(harmonic(&oversampled.samples) < 0.03);
+}
+
+#[test]
+fn stereo_is_preserved() {
+    let mut host = OfflineHost::<Oversampled<Square, 2>>::new(48000, BLOCK);
+    let input: Vec<f32> = [0.25, 0.5].repeat(BLOCK * 4);
+    let rendered = host.render_compensated(SignalConst::new(&input, 2), BLOCK * 4);
+    assert_eq!(rendered.channels, 2);
+    // away from the edges of the input, where the filters ring
+    for frame in rendered.samples.chunks(2).skip(BLOCK).take(BLOCK * 2) {
+        assert!((frame[0] - 0.0625).abs() < 1e-3);
+        assert!((frame[1] - 0.25).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,269 @@
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::tail::TailTracker;
+use scamble::dsp::{Dsp, DspType, ProcessResult};
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_DSP_DONTPROCESS, FMOD_ERR_DSP_SILENCE, FMOD_ERR_PLUGIN, FMOD_OK};
+
+const BLOCK: usize = 256;
+
+// passes input through, ringing on for a fixed tail
+struct Ringing {
+    tail: TailTracker,
+}
+
+impl Dsp for Ringing {
+    fn name() -> &'static str {
+        "Ringing"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Ringing {
+            tail: TailTracker::samples(BLOCK * 2),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.tail.reset();
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.tail.should_process(idle, incoming_length)
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        output.samples_mut().copy_from_slice(input.samples());
+    }
+}
+
+// an effect with the default should_process
+struct Silent;
+
+impl Dsp for Silent {
+    fn name() -> &'static str {
+        "Silent"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Silent
+    }
+
+    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
+        output.fill(1.);
+    }
+}
+
+// never does anything
+struct Bypassed;
+
+impl Dsp for Bypassed {
+    fn name() -> &'static str {
+        "Bypassed"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Bypassed
+    }
+
+    fn should_process(&mut self, _: bool, _: usize) -> ProcessResult {
+        ProcessResult::SkipNoEffect
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {
+        panic!("should not be processed");
+    }
+}
+
+// plays a fixed number of samples, then finishes
+struct OneShot {
+    remaining: usize,
+}
+
+impl Dsp for OneShot {
+    fn name() -> &'static str {
+        "One Shot"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn create() -> Self {
+        OneShot { remaining: BLOCK * 3 }
+    }
+
+    fn should_process(&mut self, _: bool, _: usize) -> ProcessResult {
+        if self.remaining == 0 {
+            ProcessResult::SkipSilent
+        } else {
+            ProcessResult::Continue
+        }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
+        let len = output.length().min(self.remaining);
+        output.samples_mut()[..len].fill(1.);
+        self.remaining -= len;
+    }
+}
+
+// a generator with the default should_process
+struct Drone;
+
+impl Dsp for Drone {
+    fn name() -> &'static str {
+        "Drone"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn create() -> Self {
+        Drone
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
+        output.fill(1.);
+    }
+}
+
+struct Panicking;
+
+impl Dsp for Panicking {
+    fn name() -> &'static str {
+        "Panicking"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Panicking
+    }
+
+    fn should_process(&mut self, _: bool, _: usize) -> ProcessResult {
+        panic!("query failed");
+    }
+
+    fn read(&mut self, _: SignalConst, _: SignalMut) {}
+}
+
+#[test]
+fn effect_ends_after_tail() {
+    let mut host = OfflineHost::<Ringing>::new(48000, BLOCK);
+    let input = vec![0.5; BLOCK];
+    let rendered = host.render(SignalConst::new(&input, 1), BLOCK * 6);
+    assert_eq!(
+        rendered.queries,
+        [
+            FMOD_OK,
+            FMOD_OK,
+            FMOD_ERR_DSP_SILENCE,
+            FMOD_ERR_DSP_SILENCE,
+            FMOD_ERR_DSP_SILENCE,
+            FMOD_ERR_DSP_SILENCE
+        ]
+    );
+    assert_eq!(&rendered.samples[..BLOCK], &input[..]);
+    assert!(rendered.samples[BLOCK..].iter().all(|it| *it == 0.));
+}
+
+#[test]
+fn effect_restarts_after_reset() {
+    let mut host = OfflineHost::<Ringing>::new(48000, BLOCK);
+    host.render(SignalConst::new(&[], 1), BLOCK * 4);
+    assert_eq!(host.should_process(true, BLOCK), FMOD_ERR_DSP_SILENCE);
+    assert_eq!(host.reset(), FMOD_OK);
+    assert_eq!(host.should_process(true, BLOCK), FMOD_OK);
+}
+
+#[test]
+fn default_effect_skips_silence() {
+    let mut host = OfflineHost::<Silent>::new(48000, BLOCK);
+    let rendered = host.render(SignalConst::new(&[], 2), BLOCK * 2);
+    assert_eq!(rendered.queries, [FMOD_ERR_DSP_SILENCE; 2]);
+    assert!(rendered.samples.iter().all(|it| *it == 0.));
+    assert_eq!(host.should_process(true, BLOCK), FMOD_ERR_DSP_SILENCE);
+    assert_eq!(host.should_process(false, BLOCK), FMOD_OK);
+}
+
+#[test]
+fn no_effect_passes_input_through() {
+    let mut host = OfflineHost::<Bypassed>::new(48000, BLOCK);
+    let input: Vec<f32> = (0..BLOCK * 4).map(|it| it as f32).collect();
+    let rendered = host.render(SignalConst::new(&input, 2), BLOCK * 2);
+    assert_eq!(rendered.queries, [FMOD_ERR_DSP_DONTPROCESS; 2]);
+    assert_eq!(rendered.samples, input);
+    assert_eq!(host.should_process(false, BLOCK), FMOD_ERR_DSP_DONTPROCESS);
+}
+
+#[test]
+fn generator_ends() {
+    let mut host = OfflineHost::<OneShot>::new(48000, BLOCK);
+    let rendered = host.render(SignalConst::new(&[], 0), BLOCK * 5);
+    assert_eq!(rendered.queries, [FMOD_OK, FMOD_OK, FMOD_OK, FMOD_ERR_DSP_SILENCE, FMOD_ERR_DSP_SILENCE]);
+    assert_eq!(rendered.samples.iter().filter(|it| **it == 1.).count(), BLOCK * 3);
+    assert_eq!(host.should_process(true, BLOCK), FMOD_ERR_DSP_SILENCE);
+}
+
+#[test]
+fn default_generator_continues() {
+    let mut host = OfflineHost::<Drone>::new(48000, BLOCK);
+    let rendered = host.render(SignalConst::new(&[], 0), BLOCK * 3);
+    assert_eq!(rendered.queries, [FMOD_OK; 3]);
+    assert!(rendered.samples.iter().all(|it| *it == 1.));
+    assert_eq!(host.should_process(true, BLOCK), FMOD_OK);
+}
+
+#[test]
+fn panicking_query_fails() {
+    let mut host = OfflineHost::<Panicking>::new(48000, BLOCK);
+    let rendered = host.render(SignalConst::new(&[], 1), BLOCK);
+    assert_eq!(rendered.queries, [FMOD_ERR_PLUGIN]);
+    assert_eq!(host.should_process(false, BLOCK), FMOD_ERR_PLUGIN);
+}
@@ -0,0 +1,137 @@
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::reblock::Reblocked;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::tail::TailTracker;
+use scamble::dsp::{Dsp, DspType, ProcessResult};
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_DSP_SILENCE, FMOD_OK};
+
+const N: usize = 48;
+
+// reverses each block, which only makes sense if blocks have a fixed size
+struct Reverse {
+    lengths: Vec<usize>,
+    tail: TailTracker,
+}
+
+impl Dsp for Reverse {
+    fn name() -> &'static str {
+        "Reverse"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Reverse {
+            lengths: vec![],
+            tail: TailTracker::samples(N),
+        }
+    }
+
+    fn should_process(&mut self, idle: bool, incoming_length: usize) -> ProcessResult {
+        self.tail.should_process(idle, incoming_length)
+    }
+
+    fn latency_samples(&self) -> usize {
+        N
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        let length = input.length();
+        self.lengths.push(length);
+        let channels = input.channels();
+        for i in 0..length {
+            let from = length - 1 - i;
+            output.samples_mut()[i * channels..(i + 1) * channels].copy_from_slice(&input.samples()[from * channels..(from + 1) * channels]);
+        }
+    }
+}
+
+// counts up from 1, one per frame
+struct Counter {
+    next: f32,
+}
+
+impl Dsp for Counter {
+    fn name() -> &'static str {
+        "Counter"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn create() -> Self {
+        Counter { next: 1. }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
+        for it in output.samples_mut() {
+            *it = self.next;
+            self.next += 1.;
+        }
+    }
+}
+
+fn reversed_blocks(input: &[f32]) -> Vec<f32> {
+    input.chunks(N).flat_map(|it| it.iter().rev().copied()).collect()
+}
+
+#[test]
+fn blocks_have_fixed_size() {
+    for mixer_block in [7, N, 100, 512] {
+        let mut host = OfflineHost::<Reblocked<Reverse, N>>::new(48000, mixer_block);
+        let input: Vec<f32> = (1..=N * 10).map(|it| it as f32).collect();
+        let rendered = host.render_compensated(SignalConst::new(&input, 1), input.len());
+        assert!(host.dsp().inner().lengths.iter().all(|it| *it == N));
+        assert_eq!(host.latency_samples(), N * 2 - 1);
+        // the inner DSP's own latency isn't real, so output is early by that much
+        assert_eq!(&rendered.samples[..N * 9], &reversed_blocks(&input)[N..]);
+    }
+}
+
+#[test]
+fn channels_stay_interleaved() {
+    let mut host = OfflineHost::<Reblocked<Reverse, N>>::new(48000, 100);
+    let input: Vec<f32> = (0..N * 4).flat_map(|it| [it as f32, -(it as f32)]).collect();
+    let rendered = host.render(SignalConst::new(&input, 2), N * 4);
+    assert_eq!(rendered.channels, 2);
+    let frames: Vec<_> = rendered.samples[(N - 1) * 2..].chunks(2).collect();
+    assert!(frames.iter().all(|it| it[0] == -it[1]));
+    assert_eq!(frames[0], [(N - 1) as f32, -((N - 1) as f32)]);
+}
+
+#[test]
+fn generators_are_reblocked() {
+    let mut host = OfflineHost::<Reblocked<Counter, N>>::new(48000, 100);
+    let rendered = host.render(SignalConst::new(&[], 0), 300);
+    let expected: Vec<f32> = (1..=300 - (N - 1)).map(|it| it as f32).collect();
+    assert_eq!(&rendered.samples[..N - 1], [0.; N - 1]);
+    assert_eq!(&rendered.samples[N - 1..], expected);
+}
+
+#[test]
+fn buffered_output_rings_on() {
+    let mut host = OfflineHost::<Reblocked<Reverse, N>>::new(48000, 32);
+    host.dsp_mut().inner_mut().tail = TailTracker::samples(0);
+    let rendered = host.render(SignalConst::new(&[1.; 32], 1), 32 * 6);
+    // the input is only complete after the second block, and is output during the third
+    assert_eq!(
+        rendered.queries,
+        [FMOD_OK, FMOD_OK, FMOD_OK, FMOD_ERR_DSP_SILENCE, FMOD_ERR_DSP_SILENCE, FMOD_ERR_DSP_SILENCE]
+    );
+    assert_eq!(rendered.samples.iter().filter(|it| **it == 1.).count(), 32);
+}
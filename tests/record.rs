@@ -0,0 +1,147 @@
+use hound::WavReader;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::record::{Recorded, Source};
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+use scamble::float_param;
+use std::path::PathBuf;
+
+struct Gain {
+    gain: f32,
+}
+
+impl Dsp for Gain {
+    fn name() -> &'static str {
+        "Gain"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter::new("gain", float_param!(gain, range: 0.0..2.0, default: 1.0))]
+    }
+
+    fn create() -> Self {
+        Gain { gain: 1. }
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        output.map(&input, |x: f32| x * self.gain);
+    }
+}
+
+struct Ramp {
+    next: f32,
+}
+
+impl Dsp for Ramp {
+    fn name() -> &'static str {
+        "Ramp"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Generator
+    }
+
+    fn create() -> Self {
+        Ramp { next: 0. }
+    }
+
+    fn preferred_out_channels(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn read(&mut self, _: SignalConst, mut output: SignalMut) {
+        for it in output.samples_mut() {
+            *it = self.next;
+            self.next += 1.;
+        }
+    }
+}
+
+const RECORD: usize = 1;
+const SOURCE: usize = 2;
+const FILE: usize = 3;
+
+// a host recording to a fresh file in the temp directory
+fn recording(name: &str) -> (OfflineHost<Recorded<Gain>>, PathBuf) {
+    let path = std::env::temp_dir().join(format!("scamble_record_{name}.wav"));
+    let mut host = OfflineHost::new(48000, 4);
+    host.set_data(FILE, path.to_str().unwrap().as_bytes());
+    (host, path)
+}
+
+fn read(path: &PathBuf) -> (u16, Vec<f32>) {
+    let mut reader = WavReader::open(path).unwrap();
+    let samples = reader.samples().map(Result::unwrap).collect();
+    std::fs::remove_file(path).unwrap();
+    (reader.spec().channels, samples)
+}
+
+#[test]
+fn both_signals_are_recorded_side_by_side() {
+    let (mut host, path) = recording("both");
+    host.set_float(0, 2.);
+    host.set_int(SOURCE, 2);
+    assert_eq!(host.dsp().source(), Source::Both);
+    host.set_bool(RECORD, true);
+    assert!(host.dsp().is_recording());
+    host.render(SignalConst::new(&[0.25, -0.25].repeat(6), 2), 6);
+    host.set_bool(RECORD, false);
+
+    let (channels, samples) = read(&path);
+    assert_eq!(channels, 4);
+    assert_eq!(samples, [0.25, -0.25, 0.5, -0.5].repeat(6));
+}
+
+#[test]
+fn generators_record_their_output_from_both() {
+    let path = std::env::temp_dir().join("scamble_record_generator.wav");
+    let mut host = OfflineHost::<Recorded<Ramp>>::new(48000, 4);
+    host.set_data(2, path.to_str().unwrap().as_bytes());
+    host.set_int(1, 2);
+    host.set_bool(0, true);
+    host.render(SignalConst::new(&[], 0), 8);
+    host.set_bool(0, false);
+    assert_eq!(host.dsp().dropped_frames(), 0);
+
+    let (channels, samples) = read(&path);
+    assert_eq!(channels, 1);
+    assert_eq!(samples, (0..8).map(|it| it as f32).collect::<Vec<_>>());
+}
+
+#[test]
+fn blocks_of_other_channel_counts_are_dropped() {
+    let (mut host, path) = recording("dropped");
+    host.set_bool(RECORD, true);
+    host.render(SignalConst::new(&[0.5; 4], 1), 4);
+    host.render(SignalConst::new(&[0.5; 8], 2), 4);
+    host.set_bool(RECORD, false);
+    assert_eq!(host.dsp().dropped_frames(), 4);
+
+    let (channels, samples) = read(&path);
+    assert_eq!(channels, 1);
+    assert_eq!(samples, [0.5; 4]);
+}
+
+#[test]
+fn unwritable_files_are_logged() {
+    let (mut host, _) = recording("unused");
+    host.set_data(FILE, b"/nonexistent/scamble/recording.wav");
+    OfflineHost::<Recorded<Gain>>::take_log();
+    host.set_bool(RECORD, true);
+    assert!(!host.dsp().is_recording());
+    let log = OfflineHost::<Recorded<Gain>>::take_log();
+    assert_eq!(log.len(), 1);
+    assert!(log[0].starts_with("Couldn't record to /nonexistent/scamble/recording.wav"), "{log:?}");
+}
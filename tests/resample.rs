@@ -0,0 +1,75 @@
+use scamble::dsp::oversample::Quality;
+use scamble::dsp::resample::{Interpolation, Resampler, resample};
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use std::f32::consts::PI;
+
+fn sine(frames: usize, cycles_per_frame: f32) -> Vec<f32> {
+    (0..frames).map(|it| (2. * PI * cycles_per_frame * it as f32).sin()).collect()
+}
+
+#[test]
+fn linear_interpolates_between_frames() {
+    let input: Vec<f32> = (0..8).map(|it| it as f32).collect();
+    let output = resample(SignalConst::new(&input, 1), 24000, 48000, Interpolation::Linear);
+    assert_eq!(output.length(), 16);
+    let expected: Vec<f32> = (0..15).map(|it| it as f32 / 2.).collect();
+    assert_eq!(output.samples()[..15], expected);
+}
+
+#[test]
+fn same_rate_keeps_frames() {
+    let input = sine(64, 0.1);
+    for interpolation in [Interpolation::Linear, Interpolation::Cubic] {
+        let output = resample(SignalConst::new(&input, 1), 48000, 48000, interpolation);
+        assert_eq!(output.samples(), input);
+    }
+}
+
+#[test]
+fn streaming_matches_whole_signal() {
+    let input: Vec<f32> = sine(200, 0.05).into_iter().flat_map(|it| [it, -it]).collect();
+    let interpolation = Interpolation::Sinc(Quality::Low);
+    let whole = resample(SignalConst::new(&input, 2), 44100, 48000, interpolation);
+
+    // feed the input in small blocks, taking output in blocks of a different size
+    let mut resampler = Resampler::between(interpolation, 44100, 48000);
+    let mut streamed = vec![];
+    let mut block = [0.; 10];
+    for mut chunk in input.chunks(14) {
+        while !chunk.is_empty() {
+            let (read, written) = resampler.process(SignalConst::new(chunk, 2), SignalMut::new(&mut block, 2));
+            streamed.extend_from_slice(&block[..written * 2]);
+            chunk = &chunk[read * 2..];
+        }
+    }
+    // the stream is behind by the latency, as the last frames haven't been produced yet
+    assert!(streamed.len() >= whole.samples().len() - 2 * 2 * resampler.latency());
+    assert_eq!(streamed, whole.samples()[..streamed.len()]);
+}
+
+#[test]
+fn ratio_sets_speed() {
+    let input = [1.; 100];
+    let mut output = [0.; 100];
+    let mut resampler = Resampler::new(Interpolation::Linear, 2.);
+    let (read, written) = resampler.process(SignalConst::new(&input, 1), SignalMut::new(&mut output, 1));
+    assert_eq!((read, written), (100, 50));
+
+    resampler.set_ratio(0.5);
+    let (read, written) = resampler.process(SignalConst::new(&input, 1), SignalMut::new(&mut output, 1));
+    // one more frame is read to fill the window at the new rate
+    assert_eq!((read, written), (51, 100));
+}
+
+#[test]
+fn sinc_filters_out_frequencies_above_nyquist() {
+    // too high to keep at half the rate
+    let input = sine(4800, 0.4);
+    let rms = |interpolation| {
+        let output = resample(SignalConst::new(&input, 1), 48000, 24000, interpolation);
+        SignalConst::new(&output.samples()[100..2300], 1).rms()
+    };
+    assert!(rms(Interpolation::Sinc(Quality::High)) < 0.05);
+    // while linear interpolation lets it alias
+    assert!(rms(Interpolation::Linear) > 0.3);
+}
@@ -0,0 +1,27 @@
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_FILE_NOTFOUND, FMOD_OK};
+use scamble::result::{check, into_raw};
+use scamble::{FmodError, Result};
+use std::error::Error;
+
+#[test]
+fn raw_codes_round_trip() {
+    assert_eq!(FmodError::new(FMOD_OK), None);
+    assert_eq!(FmodError::try_from(FMOD_OK), Err(()));
+    let error = FmodError::new(FMOD_ERR_FILE_NOTFOUND).unwrap();
+    assert_eq!(error.code(), FMOD_ERR_FILE_NOTFOUND);
+    assert_eq!(check(FMOD_ERR_FILE_NOTFOUND), Err(error));
+    assert_eq!(into_raw(check(FMOD_ERR_FILE_NOTFOUND)), FMOD_ERR_FILE_NOTFOUND);
+    assert_eq!(into_raw(check(FMOD_OK)), FMOD_OK);
+}
+
+#[test]
+fn errors_convert_to_boxed_errors() {
+    fn open() -> Result<()> {
+        check(FMOD_ERR_FILE_NOTFOUND)
+    }
+    fn load() -> std::result::Result<(), Box<dyn Error>> {
+        open()?;
+        Ok(())
+    }
+    assert_eq!(load().unwrap_err().to_string(), "File not found. (FMOD_ERR_FILE_NOTFOUND)");
+}
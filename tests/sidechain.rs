@@ -0,0 +1,176 @@
+use scamble::dsp::compose::Chain;
+use scamble::dsp::mix::WetDry;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::oversample::Oversampled;
+use scamble::dsp::reblock::Reblocked;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::{Dsp, DspType, Parameter, ParameterType};
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_INVALID_PARAM, FMOD_OK};
+
+const BLOCK: usize = 32;
+
+// outputs the last channel of its sidechain, or -1 without one
+struct Key {
+    sidechain: bool,
+}
+
+impl Dsp for Key {
+    fn name() -> &'static str {
+        "Key"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter::new(
+            "sidechain",
+            ParameterType::Sidechain {
+                setter: |value, dsp| dsp.sidechain = value,
+                getter: |dsp| dsp.sidechain,
+            },
+        )]
+    }
+
+    fn create() -> Self {
+        Key { sidechain: false }
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.read_with_sidechain(input, None, output);
+    }
+
+    fn read_with_sidechain(&mut self, _: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) {
+        match sidechain {
+            None => output.fill(-1.),
+            Some(sidechain) => {
+                assert_eq!(sidechain.length(), output.length());
+                for (i, frame) in sidechain.samples().chunks_exact(sidechain.channels()).enumerate() {
+                    output.write_sample(i, frame[frame.len() - 1]);
+                }
+            }
+        }
+    }
+}
+
+// a stereo sidechain, with the frame index negated on the right
+fn stereo_ramp(frames: usize) -> Vec<f32> {
+    (0..frames).flat_map(|it| [it as f32, -(it as f32)]).collect()
+}
+
+fn ramp(frames: usize) -> Vec<f32> {
+    (0..frames).map(|it| -(it as f32)).collect()
+}
+
+#[test]
+fn sidechain_is_sliced_by_channel_count() {
+    let mut host = OfflineHost::<Key>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let frames = BLOCK * 3 + 5;
+    let side = stereo_ramp(frames);
+    let rendered = host.render_with_sidechain(SignalConst::new(&[1.; BLOCK * 4], 1), SignalConst::new(&side, 2), frames);
+    assert_eq!(rendered.channels, 1);
+    assert_eq!(rendered.samples, ramp(frames));
+}
+
+#[test]
+fn missing_sidechain_is_none() {
+    let mut host = OfflineHost::<Key>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let rendered = host.render(SignalConst::new(&[1.; BLOCK], 1), BLOCK);
+    assert_eq!(rendered.samples, [-1.; BLOCK]);
+}
+
+// reports whether it was given a sidechain, without declaring a sidechain parameter
+struct Unkeyed {
+    keyed: bool,
+}
+
+impl Dsp for Unkeyed {
+    fn name() -> &'static str {
+        "Unkeyed"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Unkeyed { keyed: false }
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        self.read_with_sidechain(input, None, output);
+    }
+
+    fn read_with_sidechain(&mut self, _: SignalConst, sidechain: Option<SignalConst>, mut output: SignalMut) {
+        self.keyed |= sidechain.is_some();
+        output.fill(0.);
+    }
+}
+
+#[test]
+fn sidechain_needs_an_enabled_parameter() {
+    let side = stereo_ramp(BLOCK);
+    let input = [1.; BLOCK];
+
+    let mut host = OfflineHost::<Key>::new(48000, BLOCK);
+    let rendered = host.render_with_sidechain(SignalConst::new(&input, 1), SignalConst::new(&side, 2), BLOCK);
+    assert_eq!(rendered.samples, [-1.; BLOCK]);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let rendered = host.render_with_sidechain(SignalConst::new(&input, 1), SignalConst::new(&side, 2), BLOCK);
+    assert_eq!(rendered.samples, ramp(BLOCK));
+    assert_eq!(host.enable_sidechain(false), FMOD_OK);
+    let rendered = host.render_with_sidechain(SignalConst::new(&input, 1), SignalConst::new(&side, 2), BLOCK);
+    assert_eq!(rendered.samples, [-1.; BLOCK]);
+
+    let mut host = OfflineHost::<Unkeyed>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_ERR_INVALID_PARAM);
+    host.render_with_sidechain(SignalConst::new(&input, 1), SignalConst::new(&side, 2), BLOCK);
+    assert!(!host.dsp().keyed);
+}
+
+#[test]
+fn sidechain_is_forwarded_by_wrappers() {
+    let side = stereo_ramp(BLOCK * 2);
+    let input = [1.; BLOCK * 2];
+
+    let mut host = OfflineHost::<WetDry<Key>>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let rendered = host.render_with_sidechain(SignalConst::new(&input, 1), SignalConst::new(&side, 2), BLOCK * 2);
+    assert_eq!(rendered.samples, ramp(BLOCK * 2));
+
+    let mut host = OfflineHost::<Chain<Key, Key>>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let rendered = host.render_with_sidechain(SignalConst::new(&input, 1), SignalConst::new(&side, 2), BLOCK * 2);
+    assert_eq!(rendered.samples, ramp(BLOCK * 2));
+}
+
+#[test]
+fn sidechain_is_buffered_by_reblocked() {
+    const N: usize = 24;
+    let mut host = OfflineHost::<Reblocked<Key, N>>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let side = stereo_ramp(BLOCK * 3);
+    let rendered = host.render_with_sidechain(SignalConst::new(&[1.; BLOCK * 3], 1), SignalConst::new(&side, 2), BLOCK * 3);
+    assert_eq!(&rendered.samples[N - 1..], &ramp(BLOCK * 3 - (N - 1))[..]);
+}
+
+#[test]
+fn sidechain_is_resampled_by_oversampled() {
+    let mut host = OfflineHost::<Oversampled<Key, 2>>::new(48000, BLOCK);
+    assert_eq!(host.enable_sidechain(true), FMOD_OK);
+    let side: Vec<f32> = [0.5, -0.25].repeat(BLOCK * 8);
+    let rendered = host.render_with_sidechain(SignalConst::new(&[1.; BLOCK * 8], 1), SignalConst::new(&side, 2), BLOCK * 8);
+    // away from the start, constant signals pass through the filters unchanged
+    assert!(rendered.samples[BLOCK * 2..].iter().all(|it| (it + 0.25).abs() < 1e-3));
+}
@@ -0,0 +1,109 @@
+use scamble::dsp::layout::ChannelLayout;
+use scamble::dsp::signal::{Signal, SignalBuf, SignalConst, SignalMut};
+
+#[test]
+fn channels_are_strided() {
+    let data = [1., 2., 3., 4., 5., 6.];
+    let signal = SignalConst::new(&data, 3);
+    assert_eq!(signal.channel(1).len(), 2);
+    assert_eq!(signal.channel(1).iter().collect::<Vec<_>>(), [2., 5.]);
+    assert_eq!(signal.channel(2)[1], 6.);
+    assert_eq!(signal.channel(0).get(2), None);
+    let channels: Vec<Vec<f32>> = signal.iter_channels().map(|it| it.into_iter().collect()).collect();
+    assert_eq!(channels, [[1., 4.], [2., 5.], [3., 6.]]);
+}
+
+#[test]
+#[should_panic]
+fn channel_out_of_range_panics() {
+    SignalConst::new(&[0.; 4], 2).channel(2);
+}
+
+#[test]
+fn channels_are_written_independently() {
+    let mut data = [0.; 6];
+    let mut signal = SignalMut::new(&mut data, 2);
+    signal.channel_mut(1).fill(1.);
+    for (c, mut channel) in signal.iter_channels_mut().enumerate() {
+        channel[0] += c as f32 * 10.;
+    }
+    signal.write_frame(2, &[5., 6.]);
+    assert_eq!(data, [0., 11., 0., 1., 5., 6.]);
+}
+
+#[test]
+fn zip_pairs_matching_channels() {
+    let input = [1., 2., 3., 4.];
+    let input = SignalConst::new(&input, 2);
+    let mut data = [0.; 6];
+    let mut output = SignalMut::new(&mut data, 3);
+    let mut zipped = 0;
+    for (input, mut output) in output.zip_channels(&input) {
+        output.copy_from(input.iter().map(|it| it * 2.));
+        zipped += 1;
+    }
+    // the third output channel has no input to pair with
+    assert_eq!(zipped, 2);
+    assert_eq!(data, [2., 4., 0., 6., 8., 0.]);
+}
+
+// long enough to use whole vectors, with some samples left over
+fn ramp() -> Vec<f32> {
+    (0..37).map(|it| it as f32 / 4. - 4.).collect()
+}
+
+#[test]
+fn kernels_match_scalar_loops() {
+    let input = ramp();
+    let input = SignalConst::new(&input, 1);
+    let mut data = vec![0.; 37];
+    let mut output = SignalMut::new(&mut data, 1);
+
+    output.map(&input, |x: f32| x * x);
+    assert!(output.samples().iter().zip(input.samples()).all(|(out, x)| *out == x * x));
+
+    output.copy(&input);
+    output.gain(2.);
+    output.add_scaled(&input, -1.);
+    assert_eq!(output.samples(), input.samples());
+
+    output.clamp(-1., 0.5);
+    assert!(output.samples().iter().zip(input.samples()).all(|(out, x)| *out == x.clamp(-1., 0.5)));
+
+    output.copy(&input);
+    output.abs();
+    output.add(&input);
+    assert!(output.samples().iter().zip(input.samples()).all(|(out, x)| *out == x.abs() + x));
+}
+
+#[test]
+fn peak_and_rms_cover_all_samples() {
+    let mut data = ramp();
+    assert_eq!(SignalConst::new(&data, 1).peak(), 5.);
+    data[36] = -6.;
+    assert_eq!(SignalConst::new(&data, 1).peak(), 6.);
+    assert_eq!(SignalConst::new(&[3., -3., 3., -3.], 2).rms(), 3.);
+    assert_eq!(SignalConst::new(&[], 2).rms(), 0.);
+}
+
+#[test]
+fn buffers_resize_within_capacity() {
+    let mut buf = SignalBuf::with_capacity(64, 2);
+    let capacity = buf.capacity();
+    buf.resize(64, 2);
+    assert_eq!((buf.length(), buf.channels()), (64, 2));
+    buf.as_signal_mut().fill(1.);
+    buf.resize(16, 4);
+    assert_eq!(buf.samples(), [0.; 64]);
+    buf.resize(32, 1);
+    assert_eq!(buf.capacity(), capacity);
+
+    buf.resize_to_layout(2, ChannelLayout::raw(2));
+    assert_eq!(buf.as_signal().layout(), ChannelLayout::raw(2));
+    buf.copy_from(&SignalConst::new(&[1., 2., 3.], 3));
+    assert_eq!(buf.as_signal().samples(), [1., 2., 3.]);
+    assert_eq!(buf.layout(), ChannelLayout::raw(3));
+
+    // outside of a DSP there's no mixer to size blocks for
+    assert_eq!(SignalBuf::for_block().capacity(), 0);
+}
@@ -0,0 +1,139 @@
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{SignalConst, SignalMut};
+use scamble::dsp::spectral::{Complex, Stft, Window};
+use scamble::dsp::{Dsp, DspType, ProcessResult};
+
+// passes audio through an STFT, optionally swapping the spectra of the first two channels
+struct Passthrough {
+    stft: Stft,
+    swap: bool,
+}
+
+impl Dsp for Passthrough {
+    fn name() -> &'static str {
+        "Passthrough"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn create() -> Self {
+        Passthrough {
+            stft: Stft::new(64, 16, Window::Hann),
+            swap: false,
+        }
+    }
+
+    fn should_process(&mut self, idle: bool, _: usize) -> ProcessResult {
+        if idle && self.stft.is_silent() {
+            ProcessResult::SkipSilent
+        } else {
+            ProcessResult::Continue
+        }
+    }
+
+    fn latency_samples(&self) -> usize {
+        self.stft.latency()
+    }
+
+    fn read(&mut self, input: SignalConst, output: SignalMut) {
+        if self.swap {
+            self.stft.process_linked(input, output, |channels| channels.swap(0, 1));
+        } else {
+            self.stft.process(input, output, |_, _| {});
+        }
+    }
+}
+
+fn noise(len: usize) -> Vec<f32> {
+    // deterministic, without needing a random number generator
+    (0..len).map(|it| ((it * 7919) % 1000) as f32 / 500. - 1.).collect()
+}
+
+#[test]
+fn windows_reconstruct_input() {
+    for (fft_size, hop_size, window) in [
+        (64, 16, Window::Hann),
+        (64, 32, Window::SqrtHann),
+        (64, 64, Window::Rectangular),
+        (64, 8, Window::Hann),
+    ] {
+        let mut host = OfflineHost::<Passthrough>::new(48000, 50);
+        host.dsp_mut().stft = Stft::new(fft_size, hop_size, window);
+        let input = noise(1000);
+        let rendered = host.render_compensated(SignalConst::new(&input, 1), input.len());
+        assert_eq!(host.latency_samples(), fft_size - 1);
+        // the first frames overlap with silence, which only windows that fade in account for
+        for (out, x) in rendered.samples.iter().zip(&input).skip(fft_size) {
+            assert!((out - x).abs() < 1e-4, "{window:?} with hop {hop_size}: expected {x}, got {out}");
+        }
+    }
+}
+
+#[test]
+fn channels_are_processed_separately() {
+    let mut host = OfflineHost::<Passthrough>::new(48000, 50);
+    let input: Vec<f32> = noise(500).into_iter().flat_map(|it| [it, it * 0.5]).collect();
+    let rendered = host.render_compensated(SignalConst::new(&input, 2), 500);
+    for (out, x) in rendered.samples.iter().zip(&input).skip(128) {
+        assert!((out - x).abs() < 1e-4);
+    }
+
+    host.dsp_mut().swap = true;
+    host.dsp_mut().stft.reset();
+    let rendered = host.render_compensated(SignalConst::new(&input, 2), 500);
+    for (out, x) in rendered.samples.chunks(2).zip(input.chunks(2)).skip(64) {
+        assert!((out[0] - x[1]).abs() < 1e-4 && (out[1] - x[0]).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn silence_is_tracked() {
+    let mut stft = Stft::new(64, 16, Window::Hann);
+    let mut output = [0.; 32];
+    stft.process(SignalConst::new(&[1.; 32], 1), SignalMut::new(&mut output, 1), |_, bins| {
+        bins.fill(Complex::default())
+    });
+    assert!(!stft.is_silent());
+    for _ in 0..4 {
+        stft.process(SignalConst::new(&[0.; 32], 1), SignalMut::new(&mut output, 1), |_, _| {});
+    }
+    assert!(stft.is_silent());
+    assert_eq!(stft.bins(), 33);
+}
+
+#[test]
+fn short_or_narrow_input_is_padded() {
+    let mut stft = Stft::new(16, 16, Window::Rectangular);
+    let latency = stft.latency();
+    let mut output = [0.; 64];
+    // input that ends before the output is followed by silence
+    stft.process(SignalConst::new(&[1.; 8], 2), SignalMut::new(&mut output, 2), |_, _| {});
+    for (i, frame) in output.chunks(2).enumerate() {
+        let expected = if (latency..latency + 4).contains(&i) { 1. } else { 0. };
+        assert!(frame.iter().all(|it| (it - expected).abs() < 1e-5), "{i}: {frame:?}");
+    }
+
+    // mono input is spread to every channel
+    stft.reset();
+    stft.process(SignalConst::new(&[1.; 32], 1), SignalMut::new(&mut output, 2), |_, _| {});
+    assert!(output[latency * 2..].iter().all(|it| (it - 1.).abs() < 1e-5), "{output:?}");
+}
+
+#[test]
+fn reserving_other_channel_counts_clears_audio() {
+    let mut stft = Stft::new(64, 16, Window::Hann);
+    stft.reserve(2);
+    let mut output = [0.; 64];
+    stft.process(SignalConst::new(&[1.; 64], 2), SignalMut::new(&mut output, 2), |_, _| {});
+    assert!(!stft.is_silent());
+    stft.reserve(2);
+    assert!(!stft.is_silent());
+    stft.reserve(1);
+    assert!(stft.is_silent());
+}
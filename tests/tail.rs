@@ -0,0 +1,67 @@
+use scamble::dsp::ProcessResult;
+use scamble::dsp::tail::TailTracker;
+use std::cell::Cell;
+
+const BLOCK: usize = 32;
+
+#[test]
+fn tail_counts_down_across_idle_blocks() {
+    let mut tail = TailTracker::samples(BLOCK * 3 + 1);
+    assert_eq!(tail.should_process(false, BLOCK), ProcessResult::Continue);
+    for block in 1..=3 {
+        assert_eq!(tail.should_process(true, BLOCK), ProcessResult::Continue);
+        assert_eq!(tail.silence(), BLOCK * block);
+        assert!(!tail.finished());
+    }
+    // the tail ends partway through the fourth block
+    assert_eq!(tail.should_process(true, BLOCK), ProcessResult::SkipSilent);
+    assert!(tail.finished());
+    assert_eq!(tail.should_process(true, BLOCK), ProcessResult::SkipSilent);
+
+    // without a tail, processing stops as soon as the input does
+    let mut tail = TailTracker::samples(0);
+    assert_eq!(tail.should_process(true, BLOCK), ProcessResult::SkipSilent);
+}
+
+#[test]
+fn input_restarts_the_tail() {
+    let mut tail = TailTracker::samples(BLOCK * 2);
+    tail.should_process(true, BLOCK);
+    tail.should_process(true, BLOCK);
+    assert!(tail.finished());
+
+    assert_eq!(tail.should_process(false, BLOCK), ProcessResult::Continue);
+    assert_eq!(tail.silence(), 0);
+    assert!(!tail.finished());
+    assert_eq!(tail.should_process(true, BLOCK), ProcessResult::Continue);
+    assert_eq!(tail.should_process(true, BLOCK), ProcessResult::SkipSilent);
+
+    tail.reset();
+    assert_eq!(tail.silence(), 0);
+    assert_eq!(tail.should_process(true, BLOCK), ProcessResult::Continue);
+}
+
+#[test]
+fn ringing_extends_the_tail() {
+    let mut tail = TailTracker::samples(BLOCK);
+    let calls = &Cell::new(0);
+    let ringing = |result| {
+        move || {
+            calls.set(calls.get() + 1);
+            result
+        }
+    };
+    // only asked once the tail has finished
+    assert_eq!(tail.should_process_while(false, BLOCK, ringing(true)), ProcessResult::Continue);
+    assert_eq!(calls.get(), 0);
+    assert_eq!(tail.should_process_while(true, BLOCK, ringing(true)), ProcessResult::Continue);
+    assert_eq!(calls.get(), 1);
+    assert_eq!(tail.should_process_while(true, BLOCK, ringing(false)), ProcessResult::SkipSilent);
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn seconds_use_the_fallback_rate_outside_fmod() {
+    assert_eq!(TailTracker::seconds(0.5).tail_samples(), 24000);
+    assert_eq!(TailTracker::seconds(-1.).tail_samples(), 0);
+}
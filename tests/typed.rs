@@ -0,0 +1,160 @@
+use scamble::dsp::migrate::Preset;
+use scamble::dsp::offline::OfflineHost;
+use scamble::dsp::signal::{Signal, SignalConst, SignalMut};
+use scamble::dsp::typed::{Binary, Codec, Json, MAX_DEPTH, Structured, Typed, Value};
+use scamble::dsp::{Dsp, DspError, DspType, Parameter, ParameterType, ParameterValue};
+use scamble::raw_bindings::FMOD_RESULT::{FMOD_ERR_FORMAT, FMOD_OK};
+use scamble::typed_param;
+
+fn nested() -> Value {
+    Value::Map(vec![
+        ("name".into(), Value::String("tab\t\"quote\" \u{1} ü 🎵".into())),
+        ("gains".into(), Value::List(vec![Value::Float(1.), Value::Float(-0.25), Value::Int(-3)])),
+        ("muted".into(), Value::Bool(false)),
+        ("next".into(), Value::Null),
+    ])
+}
+
+fn round_trip<C: Codec>(value: &Value) -> Option<Value> {
+    let mut data = vec![];
+    C::encode(value, &mut data);
+    C::decode(&data)
+}
+
+#[test]
+fn codecs_round_trip() {
+    assert_eq!(round_trip::<Binary>(&nested()), Some(nested()));
+    assert_eq!(round_trip::<Json>(&nested()), Some(nested()));
+}
+
+#[test]
+fn json_is_read_leniently() {
+    // escaped characters outside the BMP are surrogate pairs
+    let text = r#" { "a" : [ 1e3 , -2, "ü\ud83c\udfb5\/" ] , "b":{} } "#;
+    assert_eq!(
+        Json::decode(text.as_bytes()),
+        Some(Value::Map(vec![
+            ("a".into(), Value::List(vec![Value::Float(1000.), Value::Int(-2), Value::String("ü🎵/".into())])),
+            ("b".into(), Value::Map(vec![])),
+        ]))
+    );
+    // non-finite floats have no JSON equivalent
+    let mut data = vec![];
+    Json::encode(&Value::List(vec![Value::Float(f64::NAN)]), &mut data);
+    assert_eq!(data, b"[null]");
+}
+
+#[test]
+fn invalid_data_is_rejected() {
+    for text in [&b"[1,]"[..], b"{\"a\" 1}", b"\"open", b"[1] 2", b"nul", b"\xff"] {
+        assert_eq!(Json::decode(text), None, "{}", String::from_utf8_lossy(text));
+    }
+    let mut data = vec![];
+    Binary::encode(&nested(), &mut data);
+    assert_eq!(Binary::decode(&data[..data.len() - 1]), None);
+    data.push(0);
+    assert_eq!(Binary::decode(&data), None);
+    // a huge length can't be satisfied
+    assert_eq!(Binary::decode(&[6, 0xff, 0xff, 0xff, 0xff]), None);
+    // a high surrogate must be followed by a low one
+    for text in [r#""\ud83c\u0041""#, r#""\ud83c\ud83c""#, r#""\udfb5""#] {
+        assert_eq!(Json::decode(text.as_bytes()), None, "{text}");
+    }
+}
+
+// a list nested `depth` times, around an empty list
+fn nested_list(depth: usize) -> Value {
+    (0..depth).fold(Value::List(vec![]), |inner, _| Value::List(vec![inner]))
+}
+
+#[test]
+fn deep_nesting_is_rejected() {
+    // the innermost empty list counts too
+    let deepest = nested_list(MAX_DEPTH - 1);
+    assert_eq!(round_trip::<Binary>(&deepest), Some(deepest.clone()));
+    assert_eq!(round_trip::<Json>(&deepest), Some(deepest));
+    assert_eq!(round_trip::<Binary>(&nested_list(MAX_DEPTH)), None);
+    assert_eq!(round_trip::<Json>(&nested_list(MAX_DEPTH)), None);
+
+    // far deeper than the stack could handle
+    let json = "[{\"a\":".repeat(1_000_000);
+    assert_eq!(Json::decode(json.as_bytes()), None);
+    let binary = [6, 1, 0, 0, 0].repeat(1_000_000);
+    assert_eq!(Binary::decode(&binary), None);
+}
+
+#[test]
+fn large_unsigned_integers_saturate() {
+    assert_eq!(u64::MAX.to_value(), Value::Int(i64::MAX));
+    assert_eq!(u64::from_value(&u64::MAX.to_value()), Some(i64::MAX as u64));
+    assert_eq!(u8::from_value(&Value::Int(-1)), None);
+}
+
+// exposes the peak of each channel of the last block, and a settable list of steps
+struct Meter {
+    peaks: Typed<Vec<f32>, Json>,
+    steps: Typed<Vec<u8>>,
+}
+
+impl Dsp for Meter {
+    fn name() -> &'static str {
+        "Meter"
+    }
+
+    fn version() -> u32 {
+        1
+    }
+
+    fn ty() -> DspType {
+        DspType::Effect
+    }
+
+    fn parameters() -> Vec<Parameter<Self>> {
+        vec![Parameter::new("peaks", typed_param!(peaks)), Parameter::new("steps", typed_param!(steps))]
+    }
+
+    fn create() -> Self {
+        Meter {
+            peaks: Typed::default(),
+            steps: Typed::new(vec![1]),
+        }
+    }
+
+    fn read(&mut self, input: SignalConst, mut output: SignalMut) {
+        output.copy(&input);
+        self.peaks.clear();
+        self.peaks.extend(input.iter_channels().map(|it| it.iter().fold(0., |peak, x| x.abs().max(peak))));
+    }
+}
+
+#[test]
+fn computed_data_can_be_read() {
+    let mut host = OfflineHost::<Meter>::new(48000, 4);
+    assert_eq!(host.get_data(0), (FMOD_OK, b"[]".to_vec()));
+    host.render(SignalConst::new(&[0.5, -0.25, -1., 0.], 2), 2);
+    assert_eq!(host.get_data(0), (FMOD_OK, b"[1.0,0.25]".to_vec()));
+
+    assert_eq!(host.set_data(1, &[6, 2, 0, 0, 0, 3, 4, 0, 0, 0, 0, 0, 0, 0, 3, 8, 0, 0, 0, 0, 0, 0, 0]), FMOD_OK);
+    assert_eq!(*host.dsp().steps, [4, 8]);
+    let preset = Preset::capture(host.dsp_mut());
+    assert_eq!(preset.values[1], ("steps".to_string(), ParameterValue::Data(host.get_data(1).1)));
+}
+
+#[test]
+fn undecodable_data_is_logged() {
+    let mut host = OfflineHost::<Meter>::new(48000, 4);
+    OfflineHost::<Meter>::take_log();
+    // steps must fit in a byte
+    let mut data = vec![];
+    Binary::encode(&Value::List(vec![Value::Int(256)]), &mut data);
+    assert_eq!(host.set_data(1, &data), FMOD_ERR_FORMAT);
+    assert_eq!(*host.dsp().steps, [1]);
+    assert_eq!(OfflineHost::<Meter>::take_log(), ["Couldn't decode data for field steps"]);
+
+    let mut meter = Meter::create();
+    assert_eq!(
+        Meter::parameters()[1].try_set(&mut meter, &ParameterValue::Data(data)),
+        Err(DspError::format("Couldn't decode data for field steps"))
+    );
+    assert_eq!(*meter.steps, [1]);
+}